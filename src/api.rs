@@ -0,0 +1,249 @@
+use crate::can_redo;
+use crate::can_undo;
+use crate::curve_at;
+use crate::init;
+use crate::load_scene;
+use crate::request_animation_frame;
+use crate::scene_bounds;
+use crate::set_curve;
+use crate::Curve;
+use crate::Point;
+use crate::State;
+use crate::MAX_SUBDIVISION_DEPTH;
+use std::rc::Rc;
+use wasm_bindgen::prelude::Closure;
+use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::js_sys::Object;
+use web_sys::js_sys::Reflect;
+use web_sys::window;
+use web_sys::HtmlCanvasElement;
+
+/// Name of the object on `window` that the page hosting the editor can call
+/// into.
+const API_NAME: &str = "slipsole";
+
+/// Publishes the page's editor to the hosting page as `window.slipsole`,
+/// with the methods of `editor` plus:
+///
+/// - `init(canvas)` sets up another, independent editor on a canvas, given
+///   as an element or an element id, and returns an object with the same
+///   methods for it. Returns `null` when there is no such canvas.
+/// - `setMaxSubdivisionDepth(depth)` limits how finely curves are subdivided
+///   when flattening them and finding intersections, for every editor.
+///   Lower is faster and safer on pathological curves but less accurate.
+///   Returns the previous limit.
+pub fn expose(canvas: HtmlCanvasElement, state: Rc<State>) {
+    let api = editor(canvas, state);
+
+    let set_max_subdivision_depth =
+        Closure::<dyn Fn(u32) -> u32>::new(|depth| MAX_SUBDIVISION_DEPTH.replace(depth));
+
+    Reflect::set(
+        &api,
+        &JsValue::from("setMaxSubdivisionDepth"),
+        set_max_subdivision_depth.as_ref(),
+    )
+    .unwrap();
+    set_max_subdivision_depth.forget();
+
+    let init = Closure::<dyn Fn(JsValue) -> JsValue>::new(|canvas: JsValue| {
+        let canvas = match canvas.as_string() {
+            Some(id) => window()
+                .unwrap()
+                .document()
+                .unwrap()
+                .get_element_by_id(&id)
+                .map(JsValue::from),
+            None => Some(canvas),
+        };
+
+        match canvas.and_then(|canvas| canvas.dyn_into::<HtmlCanvasElement>().ok()) {
+            Some(canvas) => editor(canvas.clone(), init(canvas)).into(),
+            None => JsValue::NULL,
+        }
+    });
+
+    Reflect::set(&api, &JsValue::from("init"), init.as_ref()).unwrap();
+    init.forget();
+
+    Reflect::set(&window().unwrap(), &JsValue::from(API_NAME), &api).unwrap();
+}
+
+/// Queries about one editor's scene, and ways to change it, as methods of
+/// the returned object:
+///
+/// - `sceneBounds()` returns the scene's bounding box in world units as
+///   `{ minX, minY, maxX, maxY }`, or `null` when there are no curves.
+/// - `canUndo()` and `canRedo()` tell whether undoing or redoing would do
+///   anything, for enabling buttons.
+/// - `curveAt(x, y, tolerance)` returns the index of the topmost curve
+///   passing within `tolerance` of `(x, y)`, all in world units, or `null`.
+/// - `endAngles(index)` returns the directions the curve at `index` leaves
+///   its start and arrives at its end as `[start, end]`, in radians
+///   clockwise from the x axis, or `null` when there is no such curve.
+/// - `loadScene(encoded, append, fit)` loads a scene in the encoding of
+///   shared links, the part after `#scene=`. It replaces the scene unless
+///   `append` is true, in which case the curves are added as one group,
+///   moved clear of those already there. `fit` brings the scene into view.
+///   It is undoable, and returns `null`, or a message saying why nothing was
+///   loaded.
+/// - `setCurve(index, points)` moves the curve at `index` to `points`, given
+///   as `[ax, ay, bx, by, cx, cy, dx, dy]` for a cubic or
+///   `[ax, ay, bx, by, dx, dy]` for a quadratic, keeping its style. A pinned
+///   curve is bent back through its pin, and one locked straight is
+///   straightened. It isn't undoable, so it can be called every frame.
+///   Returns whether there was such a curve and the points were well formed
+///   and finite.
+/// - `getMeta(index)` returns the text the host attached to the curve at
+///   `index`, or `null` when there is none or no such curve. The editor
+///   keeps it with the curve through edits, undo, sharing and GeoJSON, but
+///   never reads it.
+/// - `setMeta(index, text)` attaches `text` to the curve at `index`, or
+///   removes what was attached when `text` is `null`. It isn't undoable.
+///   Returns whether there was such a curve.
+/// - `requestRedraw()` draws the editor again on the next animation frame.
+///   Calls before that frame share it, so a host can call it after every
+///   change in a batch and still have the editor drawn once.
+fn editor(canvas: HtmlCanvasElement, state: Rc<State>) -> Object {
+    let api = Object::new();
+
+    for (name, query) in [
+        ("canUndo", can_undo as fn(&State) -> bool),
+        ("canRedo", can_redo),
+    ] {
+        let state = state.clone();
+        let query = Closure::<dyn Fn() -> bool>::new(move || query(&state));
+
+        Reflect::set(&api, &JsValue::from(name), query.as_ref()).unwrap();
+        query.forget();
+    }
+
+    let query = Closure::<dyn Fn(f64, f64, f64) -> Option<u32>>::new({
+        let state = state.clone();
+
+        move |x, y, tolerance| {
+            curve_at(&state, Point::new(x, y), tolerance).map(|index| index as u32)
+        }
+    });
+
+    Reflect::set(&api, &JsValue::from("curveAt"), query.as_ref()).unwrap();
+    query.forget();
+
+    let end_angles = Closure::<dyn Fn(u32) -> Option<Vec<f64>>>::new({
+        let state = state.clone();
+
+        move |index| {
+            let curves = state.curves.borrow();
+            let curve = curves.get(index as usize)?;
+
+            Some(vec![curve.start_angle(), curve.end_angle()])
+        }
+    });
+
+    Reflect::set(&api, &JsValue::from("endAngles"), end_angles.as_ref()).unwrap();
+    end_angles.forget();
+
+    let load = Closure::<dyn Fn(String, Option<bool>, Option<bool>) -> Option<String>>::new({
+        let state = state.clone();
+
+        move |encoded: String, append: Option<bool>, fit: Option<bool>| {
+            load_scene(
+                &canvas,
+                &state,
+                &encoded,
+                !append.unwrap_or(false),
+                fit.unwrap_or(false),
+            )
+            .err()
+        }
+    });
+
+    Reflect::set(&api, &JsValue::from("loadScene"), load.as_ref()).unwrap();
+    load.forget();
+
+    let set = Closure::<dyn Fn(u32, Vec<f64>) -> bool>::new({
+        let state = state.clone();
+
+        move |index, points: Vec<f64>| {
+            let point = |i: usize| Point::new(points[2 * i], points[2 * i + 1]);
+
+            let shape = match points.len() {
+                8 => Curve::new(point(0), point(1), point(2), point(3)),
+                6 => Curve::quadratic(point(0), point(1), point(2)),
+                _ => return false,
+            };
+
+            set_curve(&state, index as usize, shape).is_ok()
+        }
+    });
+
+    Reflect::set(&api, &JsValue::from("setCurve"), set.as_ref()).unwrap();
+    set.forget();
+
+    let get_meta = Closure::<dyn Fn(u32) -> Option<String>>::new({
+        let state = state.clone();
+
+        move |index| {
+            let curves = state.curves.borrow();
+
+            Some(curves.get(index as usize)?.meta.as_deref()?.to_string())
+        }
+    });
+
+    Reflect::set(&api, &JsValue::from("getMeta"), get_meta.as_ref()).unwrap();
+    get_meta.forget();
+
+    let set_meta = Closure::<dyn Fn(u32, Option<String>) -> bool>::new({
+        let state = state.clone();
+
+        move |index, text: Option<String>| {
+            let mut curves = state.curves.borrow_mut();
+
+            let Some(curve) = curves.get_mut(index as usize)
+            else {
+                return false;
+            };
+
+            curve.meta = text.map(Rc::from);
+            true
+        }
+    });
+
+    Reflect::set(&api, &JsValue::from("setMeta"), set_meta.as_ref()).unwrap();
+    set_meta.forget();
+
+    let redraw = Closure::<dyn Fn()>::new({
+        let state = state.clone();
+
+        move || request_animation_frame(&state)
+    });
+
+    Reflect::set(&api, &JsValue::from("requestRedraw"), redraw.as_ref()).unwrap();
+    redraw.forget();
+
+    let scene_bounds = Closure::<dyn Fn() -> JsValue>::new(move || {
+        let Some((min, max)) = scene_bounds(&state)
+        else {
+            return JsValue::NULL;
+        };
+
+        let bounds = Object::new();
+
+        for (key, value) in [
+            ("minX", min.x),
+            ("minY", min.y),
+            ("maxX", max.x),
+            ("maxY", max.y),
+        ] {
+            Reflect::set(&bounds, &JsValue::from(key), &JsValue::from(value)).unwrap();
+        }
+
+        bounds.into()
+    });
+
+    Reflect::set(&api, &JsValue::from("sceneBounds"), scene_bounds.as_ref()).unwrap();
+    scene_bounds.forget();
+
+    api
+}
@@ -0,0 +1,598 @@
+use crate::AnchorType;
+use crate::AxisLock;
+use crate::CapStyle;
+use crate::Color;
+use crate::ColorScale;
+use crate::Curve;
+use crate::CurveKind;
+use crate::LineJoin;
+use crate::Pin;
+use crate::Point;
+use crate::StrokeAlign;
+use crate::Style;
+use crate::DEFAULT_MITER_LIMIT;
+use std::rc::Rc;
+
+/// First byte of every encoding, bumped whenever the layout changes.
+const FORMAT_VERSION: u8 = 3;
+/// The URL-safe base64 alphabet, so encodings can go in links unescaped.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const QUADRATIC: u8 = 1 << 0;
+const DASHED: u8 = 1 << 1;
+const GROUPED: u8 = 1 << 2;
+const PINNED: u8 = 1 << 3;
+const STRAIGHT: u8 = 1 << 4;
+/// The curve's points are stored in double precision.
+const PRECISE: u8 = 1 << 5;
+/// A byte after the flags says what the curve is colored by.
+const COLOR_SCALE: u8 = 1 << 6;
+/// A byte and a number after that say how the curve joins the next one.
+const JOIN: u8 = 1 << 7;
+
+/// Flags in a second byte, which encodings from version 1 lack.
+///
+/// Four weights after the curve's points make it rational.
+const RATIONAL: u8 = 1 << 0;
+/// The next two bits are the stroke's alignment.
+const ALIGN_SHIFT: u8 = 1;
+/// The curve's metadata follows everything else, as a length and UTF-8.
+const META: u8 = 1 << 3;
+
+/// Largest change, in world units, that storing a coordinate in single
+/// precision may make. Scenes reaching far enough from the origin to be moved
+/// more are stored in double precision.
+const SINGLE_PRECISION_TOLERANCE: f64 = 1e-3;
+
+/// Encodes the curves compactly as unpadded URL-safe base64, for sharing
+/// scenes in links. Numbers are stored in single precision if that keeps every
+/// coordinate within `SINGLE_PRECISION_TOLERANCE`, and in double precision for
+/// scenes reaching far from the origin. Either way the whole scene is stored
+/// alike, so points that coincide stay coincident.
+///
+/// A `selection` of curve indices is appended after the curves if given, for
+/// resuming work; a scene shared with someone else is better left without.
+pub fn export_binary(curves: &[Curve], selection: Option<&[usize]>) -> String {
+    let mut bytes = vec![FORMAT_VERSION];
+    write_varint(&mut bytes, curves.len() as u32);
+
+    let precise = curves
+        .iter()
+        .flat_map(|curve| {
+            let points = match curve.kind {
+                CurveKind::Cubic => vec![curve.a, curve.b, curve.c, curve.d],
+                CurveKind::Quadratic => vec![curve.a, curve.b, curve.d],
+            };
+
+            points.into_iter().chain(curve.pin.map(|pin| pin.point))
+        })
+        .flat_map(|point| [point.x, point.y])
+        .any(|value| (value as f32 as f64 - value).abs() > SINGLE_PRECISION_TOLERANCE);
+
+    for curve in curves {
+        let mut flags = 0;
+
+        if curve.kind == CurveKind::Quadratic {
+            flags |= QUADRATIC;
+        }
+
+        if curve.style.dashed {
+            flags |= DASHED;
+        }
+
+        if curve.group.is_some() {
+            flags |= GROUPED;
+        }
+
+        if curve.pin.is_some() {
+            flags |= PINNED;
+        }
+
+        if curve.locked_straight {
+            flags |= STRAIGHT;
+        }
+
+        let points: &[Point] = match curve.kind {
+            CurveKind::Cubic => &[curve.a, curve.b, curve.c, curve.d],
+            CurveKind::Quadratic => &[curve.a, curve.b, curve.d],
+        };
+
+        if precise {
+            flags |= PRECISE;
+        }
+
+        if curve.style.color_scale.is_some() {
+            flags |= COLOR_SCALE;
+        }
+
+        let join =
+            curve.style.join != LineJoin::Miter || curve.style.miter_limit != DEFAULT_MITER_LIMIT;
+
+        if join {
+            flags |= JOIN;
+        }
+
+        bytes.push(flags);
+        bytes.push(
+            if curve.weights.is_some() { RATIONAL } else { 0 }
+                | (curve.style.align as u8) << ALIGN_SHIFT
+                | if curve.meta.is_some() { META } else { 0 },
+        );
+
+        match curve.style.color_scale {
+            None => {}
+            Some(ColorScale::Position) => bytes.push(0),
+            Some(ColorScale::Curvature) => bytes.push(1),
+        }
+
+        if join {
+            bytes.push(curve.style.join as u8);
+            write_f32(&mut bytes, curve.style.miter_limit);
+        }
+
+        // Caps take the high bits, so encodings from before caps read as
+        // having none.
+        bytes.push(
+            curve.a_type as u8
+                | (curve.d_type as u8) << 2
+                | (curve.style.start_cap as u8) << 4
+                | (curve.style.end_cap as u8) << 6,
+        );
+        bytes.push(
+            curve
+                .locks
+                .iter()
+                .enumerate()
+                .fold(0, |locks, (index, &lock)| {
+                    locks | (lock as u8) << (2 * index)
+                }),
+        );
+
+        let Color { r, g, b } = curve.style.color;
+        bytes.extend([r, g, b]);
+        write_f32(&mut bytes, curve.style.width);
+        write_f32(&mut bytes, curve.style.opacity);
+
+        for &point in points {
+            write_point(&mut bytes, point, precise);
+        }
+
+        if let Some(group) = curve.group {
+            write_varint(&mut bytes, group);
+        }
+
+        if let Some(pin) = curve.pin {
+            write_f32(&mut bytes, pin.t);
+            write_point(&mut bytes, pin.point, precise);
+        }
+
+        for weight in curve.weights.into_iter().flatten() {
+            write_f32(&mut bytes, weight);
+        }
+
+        if let Some(text) = &curve.meta {
+            write_varint(&mut bytes, text.len() as u32);
+            bytes.extend(text.as_bytes());
+        }
+    }
+
+    if let Some(selection) = selection {
+        write_varint(&mut bytes, selection.len() as u32);
+
+        for &index in selection {
+            write_varint(&mut bytes, index as u32);
+        }
+    }
+
+    encode_base64(&bytes)
+}
+
+/// Decodes curves encoded by `export_binary`, and the selection if one was
+/// stored. Selected indices beyond the curves or repeated are dropped.
+pub fn import_binary(text: &str) -> Result<(Vec<Curve>, Vec<usize>), String> {
+    let bytes = decode_base64(text.trim())?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        position: 0,
+    };
+
+    let version = reader.byte()?;
+
+    if version == 0 || version > FORMAT_VERSION {
+        return Err(format!("unsupported format version {version}"));
+    }
+
+    let count = reader.varint()?;
+    let mut curves = Vec::new();
+
+    for _ in 0..count {
+        let flags = reader.byte()?;
+        let more_flags = match version {
+            1 => 0,
+            _ => reader.byte()?,
+        };
+        let precise = flags & PRECISE != 0;
+        let color_scale = match flags & COLOR_SCALE {
+            0 => None,
+            _ => Some(color_scale(reader.byte()?)?),
+        };
+        let (join, miter_limit) = match flags & JOIN {
+            0 => (LineJoin::Miter, DEFAULT_MITER_LIMIT),
+            _ => (line_join(reader.byte()?)?, reader.f32()?),
+        };
+        let types = reader.byte()?;
+        let locks = reader.byte()?;
+        let color = Color::new(reader.byte()?, reader.byte()?, reader.byte()?);
+
+        let style = Style {
+            color,
+            width: reader.f32()?,
+            dashed: flags & DASHED != 0,
+            opacity: reader.f32()?,
+            color_scale,
+            join,
+            miter_limit,
+            start_cap: cap_style(types >> 4 & 0b11),
+            end_cap: cap_style(types >> 6),
+            align: stroke_align(more_flags >> ALIGN_SHIFT & 0b11)?,
+        };
+
+        let mut curve = if flags & QUADRATIC != 0 {
+            Curve::quadratic(
+                reader.point(precise)?,
+                reader.point(precise)?,
+                reader.point(precise)?,
+            )
+        }
+        else {
+            Curve::new(
+                reader.point(precise)?,
+                reader.point(precise)?,
+                reader.point(precise)?,
+                reader.point(precise)?,
+            )
+        };
+
+        curve.style = style;
+        curve.locked_straight = flags & STRAIGHT != 0;
+        curve.a_type = anchor_type(types & 0b11)?;
+        curve.d_type = anchor_type(types >> 2 & 0b11)?;
+
+        for (index, lock) in curve.locks.iter_mut().enumerate() {
+            *lock = axis_lock(locks >> (2 * index) & 0b11)?;
+        }
+
+        if flags & GROUPED != 0 {
+            curve.group = Some(reader.varint()?);
+        }
+
+        if flags & PINNED != 0 {
+            curve.pin = Some(Pin {
+                t: reader.f32()?,
+                point: reader.point(precise)?,
+            });
+        }
+
+        if more_flags & RATIONAL != 0 {
+            curve.weights = Some([reader.f32()?, reader.f32()?, reader.f32()?, reader.f32()?]);
+        }
+
+        if more_flags & META != 0 {
+            let length = reader.varint()? as usize;
+            let bytes = (0..length)
+                .map(|_| reader.byte())
+                .collect::<Result<Vec<u8>, String>>()?;
+            let text = String::from_utf8(bytes).map_err(|_| "metadata is not UTF-8")?;
+
+            curve.meta = Some(Rc::from(text));
+        }
+
+        curves.push(curve);
+    }
+
+    let mut selection = Vec::new();
+
+    if reader.position != bytes.len() {
+        for _ in 0..reader.varint()? {
+            let index = reader.varint()? as usize;
+
+            if index < curves.len() && !selection.contains(&index) {
+                selection.push(index);
+            }
+        }
+    }
+
+    if reader.position != bytes.len() {
+        return Err("trailing bytes".to_string());
+    }
+
+    Ok((curves, selection))
+}
+
+fn anchor_type(value: u8) -> Result<AnchorType, String> {
+    match value {
+        0 => Ok(AnchorType::Corner),
+        1 => Ok(AnchorType::Smooth),
+        2 => Ok(AnchorType::Symmetric),
+        _ => Err(format!("invalid anchor type {value}")),
+    }
+}
+
+fn cap_style(value: u8) -> CapStyle {
+    match value {
+        0 => CapStyle::None,
+        1 => CapStyle::Dot,
+        2 => CapStyle::Arrow,
+        _ => CapStyle::Bar,
+    }
+}
+
+fn color_scale(value: u8) -> Result<ColorScale, String> {
+    match value {
+        0 => Ok(ColorScale::Position),
+        1 => Ok(ColorScale::Curvature),
+        _ => Err(format!("invalid color scale {value}")),
+    }
+}
+
+fn line_join(value: u8) -> Result<LineJoin, String> {
+    match value {
+        0 => Ok(LineJoin::Miter),
+        1 => Ok(LineJoin::Round),
+        2 => Ok(LineJoin::Bevel),
+        _ => Err(format!("invalid line join {value}")),
+    }
+}
+
+fn stroke_align(value: u8) -> Result<StrokeAlign, String> {
+    match value {
+        0 => Ok(StrokeAlign::Center),
+        1 => Ok(StrokeAlign::Inside),
+        2 => Ok(StrokeAlign::Outside),
+        _ => Err(format!("invalid stroke alignment {value}")),
+    }
+}
+
+fn axis_lock(value: u8) -> Result<AxisLock, String> {
+    match value {
+        0 => Ok(AxisLock::None),
+        1 => Ok(AxisLock::X),
+        2 => Ok(AxisLock::Y),
+        _ => Err(format!("invalid axis lock {value}")),
+    }
+}
+
+/// Seven bits per byte, least significant first, with the high bit set on
+/// all but the last byte.
+fn write_varint(bytes: &mut Vec<u8>, mut value: u32) {
+    while value >= 0x80 {
+        bytes.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+
+    bytes.push(value as u8);
+}
+
+fn write_f32(bytes: &mut Vec<u8>, value: f64) {
+    bytes.extend((value as f32).to_le_bytes());
+}
+
+fn write_point(bytes: &mut Vec<u8>, point: Point, precise: bool) {
+    for value in [point.x, point.y] {
+        if precise {
+            bytes.extend(value.to_le_bytes());
+        }
+        else {
+            write_f32(bytes, value);
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, String> {
+        let byte = *self
+            .bytes
+            .get(self.position)
+            .ok_or("unexpected end of data")?;
+
+        self.position += 1;
+
+        Ok(byte)
+    }
+
+    fn varint(&mut self) -> Result<u32, String> {
+        let mut value = 0;
+
+        for shift in (0..32).step_by(7) {
+            let byte = self.byte()?;
+            value |= u32::from(byte & 0x7f) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err("varint too long".to_string())
+    }
+
+    fn f32(&mut self) -> Result<f64, String> {
+        let bytes = [self.byte()?, self.byte()?, self.byte()?, self.byte()?];
+        Ok(f32::from_le_bytes(bytes) as f64)
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        let mut bytes = [0; 8];
+
+        for byte in &mut bytes {
+            *byte = self.byte()?;
+        }
+
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn point(&mut self, precise: bool) -> Result<Point, String> {
+        let point = if precise {
+            Point::new(self.f64()?, self.f64()?)
+        }
+        else {
+            Point::new(self.f32()?, self.f32()?)
+        };
+
+        if !point.x.is_finite() || !point.y.is_finite() {
+            return Err("coordinate out of range".to_string());
+        }
+
+        Ok(point)
+    }
+}
+
+fn encode_base64(bytes: &[u8]) -> String {
+    let mut text = String::new();
+
+    for chunk in bytes.chunks(3) {
+        let group = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |group, (index, &byte)| {
+                group | u32::from(byte) << (16 - 8 * index)
+            });
+
+        // Three bytes make four characters; a shorter final chunk makes one
+        // character more than it has bytes.
+        for index in 0..=chunk.len() {
+            text.push(BASE64_ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize] as char);
+        }
+    }
+
+    text
+}
+
+fn decode_base64(text: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    for chunk in text.as_bytes().chunks(4) {
+        if chunk.len() == 1 {
+            return Err("truncated base64".to_string());
+        }
+
+        let mut group = 0;
+
+        for (index, &character) in chunk.iter().enumerate() {
+            let value = BASE64_ALPHABET
+                .iter()
+                .position(|&candidate| candidate == character)
+                .ok_or_else(|| format!("invalid base64 character {:?}", character as char))?;
+
+            group |= (value as u32) << (18 - 6 * index);
+        }
+
+        for index in 0..chunk.len() - 1 {
+            bytes.push((group >> (16 - 8 * index)) as u8);
+        }
+    }
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geojson::export_geojson;
+    use crate::tests::detached_state;
+
+    /// Curves using most of what the encoding stores, with numbers that
+    /// single precision holds exactly.
+    fn sample_curves() -> Vec<Curve> {
+        let mut styled = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(10.5, 20.25),
+            Point::new(30.0, -4.0),
+            Point::new(40.0, 8.0),
+        );
+        styled.style.color = Color {
+            r: 200,
+            g: 10,
+            b: 30,
+        };
+        styled.style.width = 2.5;
+        styled.style.opacity = 0.5;
+        styled.style.dashed = true;
+        styled.style.join = LineJoin::Round;
+        styled.style.end_cap = CapStyle::Arrow;
+        styled.style.align = StrokeAlign::Outside;
+        styled.group = Some(3);
+        styled.d_type = AnchorType::Smooth;
+        styled.locks[1] = AxisLock::X;
+
+        let mut rational = Curve::new(
+            Point::new(40.0, 8.0),
+            Point::new(50.0, 8.0),
+            Point::new(60.0, 18.0),
+            Point::new(60.0, 28.0),
+        );
+        rational.weights = Some([1.0, 0.75, 0.75, 1.0]);
+        rational.pin = Some(Pin {
+            t: 0.5,
+            point: Point::new(55.0, 12.0),
+        });
+
+        // Far enough out to be stored in double precision.
+        let far = Curve::line(Point::new(1e7 + 0.1, 3.0), Point::new(1e7 + 0.2, 4.0));
+
+        vec![
+            styled,
+            rational,
+            Curve::quadratic(
+                Point::new(0.0, 0.0),
+                Point::new(5.0, 9.0),
+                Point::new(10.0, 0.0),
+            ),
+            far,
+        ]
+    }
+
+    #[test]
+    fn binary_round_trips() {
+        let curves = sample_curves();
+        let encoded = export_binary(&curves, Some(&[2, 0]));
+        let (decoded, selection) = import_binary(&encoded).unwrap();
+
+        assert!(decoded == curves);
+        assert_eq!(selection, [2, 0]);
+
+        // Only the second curve reaches far enough for double precision, but
+        // the point they share has to come back the same in both.
+        let joined = [
+            Curve::line(Point::new(0.0, 0.0), Point::new(1000.3, 7.1)),
+            Curve::line(Point::new(1000.3, 7.1), Point::new(100000.3, 0.0)),
+        ];
+        let (decoded, _) = import_binary(&export_binary(&joined, None)).unwrap();
+
+        assert!(decoded == joined);
+        assert!(decoded[0].d == decoded[1].a);
+    }
+
+    #[test]
+    fn binary_round_trip_exports_the_same_geojson() {
+        let state = detached_state();
+
+        *state.curves.borrow_mut() = sample_curves();
+        let before = export_geojson(&state, 0.25);
+
+        let encoded = export_binary(&state.curves.borrow(), None);
+        *state.curves.borrow_mut() = import_binary(&encoded).unwrap().0;
+
+        assert_eq!(export_geojson(&state, 0.25), before);
+    }
+
+    #[test]
+    fn binary_rejects_newer_versions() {
+        assert!(import_binary(&encode_base64(&[FORMAT_VERSION + 1, 0])).is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use crate::distance;
+use crate::format_coordinate;
+use crate::Camera;
+use crate::Point;
+use crate::PointHandle;
+use crate::State;
+use crate::ARROWHEAD_SIZE;
+use std::f64::consts::PI;
+use web_sys::js_sys::JsString;
+use web_sys::CanvasRenderingContext2d;
+
+const DIMENSION_COLOR: &str = "rgb(0, 128, 128)";
+const DIMENSION_FONT_SIZE: f64 = 12.0;
+/// Distance of the dimension line from the measured anchors.
+const DIMENSION_OFFSET: f64 = 24.0;
+/// Space left between an anchor and its extension line, and by which
+/// extension lines overshoot the dimension line.
+const DIMENSION_GAP: f64 = 4.0;
+
+/// A measurement between two curve ends, drawn like in technical drawings.
+/// It refers to the ends by curve index, so it follows them as they move.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Dimension {
+    pub start: (usize, PointHandle),
+    pub end: (usize, PointHandle),
+}
+
+/// Where a dimension's parts go, in whatever units its anchors are in.
+struct Layout {
+    extensions: [(Point, Point); 2],
+    /// Ends of the dimension line, where the arrows point.
+    line: (Point, Point),
+    /// Unit vector along the dimension line, from start to end.
+    direction: (f64, f64),
+    /// Middle of the label's baseline.
+    label: Point,
+    /// Rotation of the label, kept within a quarter turn so it reads upright.
+    angle: f64,
+}
+
+/// Adds a dimension between the last two grabbed anchors, or removes the one
+/// already there.
+pub fn toggle_dimension(state: &State) -> bool {
+    let (Some(start), Some(end)) = (state.previous_anchor.get(), state.active_anchor.get())
+    else {
+        return false;
+    };
+
+    let mut dimensions = state.dimensions.borrow_mut();
+    let count = dimensions.len();
+
+    dimensions.retain(|dimension| {
+        *dimension != Dimension { start, end }
+            && *dimension
+                != Dimension {
+                    start: end,
+                    end: start,
+                }
+    });
+
+    if dimensions.len() == count {
+        dimensions.push(Dimension { start, end });
+    }
+
+    true
+}
+
+/// Points the dimensions' ends at the curves' new indices after curves have
+/// been removed, reordered or split. `new_end` gives where an end is now, or
+/// `None` if its curve is gone, which drops the dimension.
+pub fn remap_dimensions(
+    state: &State,
+    new_end: impl Fn(usize, PointHandle) -> Option<(usize, PointHandle)>,
+) {
+    state.dimensions.borrow_mut().retain_mut(|dimension| {
+        let (Some(start), Some(end)) = (
+            new_end(dimension.start.0, dimension.start.1),
+            new_end(dimension.end.0, dimension.end.1),
+        )
+        else {
+            return false;
+        };
+
+        *dimension = Dimension { start, end };
+        true
+    });
+}
+
+/// The smallest box holding every dimension as drawn in world units, label
+/// included, or `None` if there are none.
+pub fn dimensions_bounds(state: &State) -> Option<(Point, Point)> {
+    state
+        .dimensions
+        .borrow()
+        .iter()
+        .filter_map(|&dimension| {
+            let (start, end) = endpoints(state, dimension);
+            layout(start, end)
+        })
+        .flat_map(|layout| {
+            let [(start, start_end), (end, end_end)] = layout.extensions;
+            let label = layout.label;
+
+            // The label is assumed to be no wider than a few lines are tall.
+            let reach = DIMENSION_FONT_SIZE * 3.0;
+
+            [
+                start,
+                start_end,
+                end,
+                end_end,
+                Point::new(label.x - reach, label.y - reach),
+                Point::new(label.x + reach, label.y + reach),
+            ]
+        })
+        .fold(None, |bounds, point| {
+            let (min, max) = bounds.unwrap_or((point, point));
+
+            Some((
+                Point::new(min.x.min(point.x), min.y.min(point.y)),
+                Point::new(max.x.max(point.x), max.y.max(point.y)),
+            ))
+        })
+}
+
+/// The measured points of `dimension`, in world units.
+fn endpoints(state: &State, dimension: Dimension) -> (Point, Point) {
+    let curves = state.curves.borrow();
+    let point = |(index, handle): (usize, PointHandle)| curves[index].point(handle);
+
+    (point(dimension.start), point(dimension.end))
+}
+
+/// Lays out a dimension between `start` and `end`, or `None` if they
+/// coincide. The dimension line goes on the side the label reads above.
+fn layout(start: Point, end: Point) -> Option<Layout> {
+    let length = distance(start, end);
+
+    if length == 0.0 {
+        return None;
+    }
+
+    let (x, y) = ((end.x - start.x) / length, (end.y - start.y) / length);
+    let mut angle = y.atan2(x);
+
+    if angle.abs() > PI / 2.0 {
+        angle -= PI.copysign(angle);
+    }
+
+    // Up in the label's frame, with y pointing down.
+    let (nx, ny) = (angle.sin(), -angle.cos());
+    let along =
+        |point: Point, offset: f64| Point::new(point.x + nx * offset, point.y + ny * offset);
+
+    Some(Layout {
+        extensions: [start, end].map(|point| {
+            (
+                along(point, DIMENSION_GAP),
+                along(point, DIMENSION_OFFSET + DIMENSION_GAP),
+            )
+        }),
+        line: (along(start, DIMENSION_OFFSET), along(end, DIMENSION_OFFSET)),
+        direction: (x, y),
+        label: along(start.lerp(end, 0.5), DIMENSION_OFFSET + DIMENSION_GAP),
+        angle,
+    })
+}
+
+/// The corners of the arrowheads at both ends of the dimension line, tips
+/// first.
+fn arrowheads(layout: &Layout) -> [[Point; 3]; 2] {
+    let (x, y) = layout.direction;
+    let arrowhead = |tip: Point, (x, y): (f64, f64)| {
+        let back = Point::new(tip.x - x * ARROWHEAD_SIZE, tip.y - y * ARROWHEAD_SIZE);
+        let (nx, ny) = (-y * ARROWHEAD_SIZE / 4.0, x * ARROWHEAD_SIZE / 4.0);
+
+        [
+            tip,
+            Point::new(back.x + nx, back.y + ny),
+            Point::new(back.x - nx, back.y - ny),
+        ]
+    };
+
+    [
+        arrowhead(layout.line.0, (-x, -y)),
+        arrowhead(layout.line.1, (x, y)),
+    ]
+}
+
+pub fn draw_dimensions(context: &CanvasRenderingContext2d, state: &State, camera: Camera) {
+    let precision = state.export_precision.get();
+
+    context.save();
+    context.set_stroke_style(&JsString::from(DIMENSION_COLOR));
+    context.set_fill_style(&JsString::from(DIMENSION_COLOR));
+    context.set_line_width(1.0);
+    context.set_font(&format!("{DIMENSION_FONT_SIZE}px sans-serif"));
+    context.set_text_align("center");
+    context.set_text_baseline("bottom");
+
+    for &dimension in state.dimensions.borrow().iter() {
+        let (start, end) = endpoints(state, dimension);
+
+        let Some(layout) = layout(camera.to_screen(start), camera.to_screen(end))
+        else {
+            continue;
+        };
+
+        context.begin_path();
+
+        for (from, to) in layout.extensions.into_iter().chain([layout.line]) {
+            context.move_to(from.x, from.y);
+            context.line_to(to.x, to.y);
+        }
+
+        context.stroke();
+        context.begin_path();
+
+        for [tip, left, right] in arrowheads(&layout) {
+            context.move_to(tip.x, tip.y);
+            context.line_to(left.x, left.y);
+            context.line_to(right.x, right.y);
+            context.close_path();
+        }
+
+        context.fill();
+
+        context.save();
+        context.translate(layout.label.x, layout.label.y).unwrap();
+        context.rotate(layout.angle).unwrap();
+        context
+            .fill_text(
+                &format_coordinate(distance(start, end), precision),
+                0.0,
+                0.0,
+            )
+            .unwrap();
+        context.restore();
+    }
+
+    context.restore();
+}
+
+/// The dimensions as an SVG `<g>` each, in world units. They are drawn with
+/// lines, polygons and text rather than paths, so importing the document
+/// again doesn't turn them into curves.
+pub fn svg_dimensions(state: &State) -> String {
+    let precision = state.export_precision.get();
+    let format = |value: f64| format_coordinate(value, precision);
+    let mut svg = String::new();
+
+    for &dimension in state.dimensions.borrow().iter() {
+        let (start, end) = endpoints(state, dimension);
+
+        let Some(layout) = layout(start, end)
+        else {
+            continue;
+        };
+
+        svg.push_str(&format!(
+            r#"<g class="dimension" stroke="{DIMENSION_COLOR}" fill="{DIMENSION_COLOR}">"#
+        ));
+
+        for (from, to) in layout.extensions.into_iter().chain([layout.line]) {
+            svg.push_str(&format!(
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}"/>"#,
+                format(from.x),
+                format(from.y),
+                format(to.x),
+                format(to.y)
+            ));
+        }
+
+        for corners in arrowheads(&layout) {
+            let points: Vec<String> = corners
+                .iter()
+                .map(|point| format!("{},{}", format(point.x), format(point.y)))
+                .collect();
+
+            svg.push_str(&format!(
+                r#"<polygon points="{}" stroke="none"/>"#,
+                points.join(" ")
+            ));
+        }
+
+        svg.push_str(&format!(
+            r#"<text x="{}" y="{}" transform="rotate({} {} {})" font-family="sans-serif" font-size="{DIMENSION_FONT_SIZE}" text-anchor="middle" stroke="none">{}</text>"#,
+            format(layout.label.x),
+            format(layout.label.y),
+            format(layout.angle.to_degrees()),
+            format(layout.label.x),
+            format(layout.label.y),
+            format(distance(start, end))
+        ));
+
+        svg.push_str("</g>");
+    }
+
+    svg
+}
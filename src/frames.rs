@@ -0,0 +1,308 @@
+use crate::curves_bounds;
+use crate::distance;
+use crate::ease_in_out;
+use crate::offscreen_canvas;
+use crate::stroke_curve;
+use crate::Camera;
+use crate::Curve;
+use crate::Point;
+use std::f64::consts::PI;
+use web_sys::js_sys::JsString;
+use web_sys::CanvasRenderingContext2d;
+
+const DOT_RADIUS: f64 = 5.0;
+const DOT_COLOR: &str = "orangered";
+/// Frames per second of playback, for turning a speed into a frame count.
+const FRAME_RATE: f64 = 30.0;
+/// Points per curve in the table mapping distance along the path to `t`.
+const ARC_LENGTH_SAMPLES: usize = 100;
+/// Bisection steps for finding where a cubic-bezier easing reaches a time.
+const EASING_ITERATIONS: usize = 40;
+
+/// How the dot moves along the path.
+#[derive(Clone, Copy)]
+pub enum Pacing {
+    /// Over this many frames, spending equal time on every curve and moving
+    /// by equal steps of `t` within one, so its speed follows the curves'
+    /// parameterization.
+    Parameter(usize),
+    /// At this constant speed along the path, in pixels of the frames per
+    /// second of playback at `FRAME_RATE`, for as many frames as that takes.
+    Speed(f64),
+}
+
+/// How the dot's progress follows time, both going from 0 to 1.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Easing {
+    Linear,
+    /// Slow at both ends, like view animations.
+    EaseInOut,
+    /// As CSS's `cubic-bezier(x1, y1, x2, y2)`: time runs along x and
+    /// progress along y of a cubic from `(0, 0)` to `(1, 1)` with these
+    /// control points.
+    CubicBezier(f64, f64, f64, f64),
+}
+
+impl Easing {
+    /// Reads `linear`, `ease-in-out` or `cubic-bezier(x1, y1, x2, y2)`, with
+    /// both x within 0 to 1 so that time only runs forwards.
+    pub fn parse(text: &str) -> Option<Self> {
+        match text.trim() {
+            "linear" => Some(Easing::Linear),
+            "ease-in-out" => Some(Easing::EaseInOut),
+            text => {
+                let values: Vec<f64> = text
+                    .strip_prefix("cubic-bezier(")?
+                    .strip_suffix(')')?
+                    .split(',')
+                    .map(|value| {
+                        value
+                            .trim()
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|value| value.is_finite())
+                    })
+                    .collect::<Option<_>>()?;
+
+                let [x1, y1, x2, y2] = values[..]
+                else {
+                    return None;
+                };
+
+                ((0.0..=1.0).contains(&x1) && (0.0..=1.0).contains(&x2))
+                    .then_some(Easing::CubicBezier(x1, y1, x2, y2))
+            }
+        }
+    }
+
+    /// The text `parse` reads back.
+    pub fn css(self) -> String {
+        match self {
+            Easing::Linear => "linear".to_string(),
+            Easing::EaseInOut => "ease-in-out".to_string(),
+            Easing::CubicBezier(x1, y1, x2, y2) => format!("cubic-bezier({x1}, {y1}, {x2}, {y2})"),
+        }
+    }
+
+    /// Progress at `time`.
+    pub fn apply(self, time: f64) -> f64 {
+        let (x1, y1, x2, y2) = match self {
+            Easing::Linear => return time,
+            Easing::EaseInOut => return ease_in_out(time),
+            Easing::CubicBezier(x1, y1, x2, y2) => (x1, y1, x2, y2),
+        };
+
+        let bezier = |a: f64, b: f64, s: f64| {
+            let ms = 1.0 - s;
+            3.0 * ms * ms * s * a + 3.0 * ms * s * s * b + s * s * s
+        };
+
+        // With both x within 0 to 1, x only grows with the curve parameter.
+        let (mut low, mut high) = (0.0, 1.0);
+
+        for _ in 0..EASING_ITERATIONS {
+            let middle = (low + high) / 2.0;
+
+            if bezier(x1, x2, middle) < time {
+                low = middle;
+            }
+            else {
+                high = middle;
+            }
+        }
+
+        bezier(y1, y2, (low + high) / 2.0)
+    }
+}
+
+/// Renders images of a dot travelling along `path`, one curve after another,
+/// and returns them as PNG data URLs. The dot sets off `start` along the
+/// path, counted in curves, so `1.5` is halfway along the second one, and
+/// moves as `easing` says over either the curves' parameter or the length,
+/// as `pacing` says. With a speed, that is the average speed. Each frame is
+/// `width` by `height` pixels on a white background, with the path fitted to
+/// it.
+pub fn render_path_frames(
+    path: &[Curve],
+    pacing: Pacing,
+    easing: Easing,
+    start: f64,
+    width: u32,
+    height: u32,
+) -> Vec<String> {
+    let Some(bounds) = curves_bounds(path)
+    else {
+        return Vec::new();
+    };
+
+    let (canvas, context) = offscreen_canvas(width, height);
+
+    let camera = Camera::fitting(bounds, width as _, height as _);
+
+    // Positions along the whole path, from 0 at its start to `path.len()`
+    // at its end.
+    let positions: Vec<f64> = match pacing {
+        Pacing::Parameter(1) => vec![start],
+        Pacing::Parameter(frames) => (0..frames)
+            .map(|frame| {
+                let progress = easing.apply(frame as f64 / (frames - 1) as f64);
+                (start + progress * (path.len() as f64 - start)).clamp(0.0, path.len() as f64)
+            })
+            .collect(),
+        Pacing::Speed(speed) => {
+            let table = arc_length_table(path, camera);
+            let length = table.last().unwrap().0;
+            let start = length_at_position(&table, start);
+            let steps = ((length - start) / speed * FRAME_RATE).ceil() as usize;
+
+            (0..=steps)
+                .map(|step| {
+                    let progress = easing.apply(step as f64 / steps.max(1) as f64);
+                    position_at_length(
+                        &table,
+                        (start + progress * (length - start)).clamp(0.0, length),
+                    )
+                })
+                .collect()
+        }
+    };
+
+    positions
+        .into_iter()
+        .map(|position| {
+            let index = (position as usize).min(path.len() - 1);
+            let dot = camera.to_screen(path[index].point_at(position - index as f64));
+
+            context.set_fill_style(&JsString::from("white"));
+            context.fill_rect(0.0, 0.0, width as _, height as _);
+
+            for curve in path {
+                stroke_curve(&context, camera.curve_to_screen(curve.clone()), 1.0);
+            }
+
+            draw_dot(&context, dot);
+
+            canvas.to_data_url().unwrap()
+        })
+        .collect()
+}
+
+/// Draws the travelling dot at `point`, in screen pixels.
+pub fn draw_dot(context: &CanvasRenderingContext2d, point: Point) {
+    context.set_fill_style(&JsString::from(DOT_COLOR));
+    context.begin_path();
+    context
+        .arc(point.x, point.y, DOT_RADIUS, 0.0, 2.0 * PI)
+        .unwrap();
+    context.fill();
+}
+
+/// Where the dot is on `path` once `progress` of the way along its length,
+/// eased by `easing`.
+pub fn eased_point(path: &[Curve], easing: Easing, progress: f64) -> Point {
+    let table = arc_length_table(path, Camera::default());
+    let length = table.last().unwrap().0;
+    let position = position_at_length(&table, (easing.apply(progress) * length).clamp(0.0, length));
+    let index = (position as usize).min(path.len() - 1);
+
+    path[index].point_at(position - index as f64)
+}
+
+/// Distances along the path as drawn by `camera`, each with the position
+/// along the path reached there, in increasing order from `(0, 0)`.
+pub fn arc_length_table(path: &[Curve], camera: Camera) -> Vec<(f64, f64)> {
+    let mut table = vec![(0.0, 0.0)];
+    let mut length = 0.0;
+
+    for (index, curve) in path.iter().enumerate() {
+        let curve = camera.curve_to_screen(curve.clone());
+        let mut previous = curve.a;
+
+        for sample in 1..=ARC_LENGTH_SAMPLES {
+            let t = sample as f64 / ARC_LENGTH_SAMPLES as f64;
+            let point = curve.point_at(t);
+
+            length += distance(previous, point);
+            table.push((length, index as f64 + t));
+            previous = point;
+        }
+    }
+
+    table
+}
+
+/// How far along the path `position` is, the inverse of `position_at_length`.
+fn length_at_position(table: &[(f64, f64)], position: f64) -> f64 {
+    let after = table
+        .partition_point(|&(_, entry)| entry < position)
+        .clamp(1, table.len() - 1);
+    let (length_before, position_before) = table[after - 1];
+    let (length_after, position_after) = table[after];
+
+    match position_after - position_before {
+        0.0 => length_after,
+        span => {
+            length_before + (length_after - length_before) * (position - position_before) / span
+        }
+    }
+}
+
+/// The position along the path `length` pixels from its start, interpolated
+/// between the nearest entries of `table`.
+pub fn position_at_length(table: &[(f64, f64)], length: f64) -> f64 {
+    let after = table
+        .partition_point(|&(entry, _)| entry < length)
+        .clamp(1, table.len() - 1);
+    let (length_before, position_before) = table[after - 1];
+    let (length_after, position_after) = table[after];
+
+    match length_after - length_before {
+        0.0 => position_after,
+        span => {
+            position_before + (position_after - position_before) * (length - length_before) / span
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn linear_easing_moves_uniformly() {
+        let linear_bezier = Easing::CubicBezier(1.0 / 3.0, 1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0);
+
+        for step in 0..=10 {
+            let time = step as f64 / 10.0;
+
+            assert_eq!(Easing::Linear.apply(time), time);
+            assert!((linear_bezier.apply(time) - time).abs() < 1e-9);
+        }
+
+        // Equal steps in time cover equal distances, across curves too.
+        let path = [
+            Curve::line(Point::new(0.0, 0.0), Point::new(30.0, 0.0)),
+            Curve::line(Point::new(30.0, 0.0), Point::new(30.0, 70.0)),
+        ];
+        let points: Vec<_> = (0..=20)
+            .map(|step| eased_point(&path, Easing::Linear, step as f64 / 20.0))
+            .collect();
+
+        for pair in points.windows(2) {
+            assert!((distance(pair[0], pair[1]) - 5.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn easings_read_back_their_css() {
+        for easing in [
+            Easing::Linear,
+            Easing::EaseInOut,
+            Easing::CubicBezier(0.25, 0.1, 0.25, 1.0),
+        ] {
+            assert!(Easing::parse(&easing.css()) == Some(easing));
+        }
+
+        assert!(Easing::parse("cubic-bezier(1.5, 0, 0.5, 1)").is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use crate::format_coordinate;
+use crate::json;
+use crate::json::Json;
+use crate::Curve;
+use crate::Point;
+use crate::State;
+use std::rc::Rc;
+use wasm_bindgen::JsValue;
+use web_sys::console;
+
+/// Serializes every curve as a GeoJSON `Feature`, flattened to within
+/// `tolerance` pixels. Open curves become `LineString`s and closed ones
+/// (`a == d`) become single-ring `Polygon`s. Each feature carries the curve's
+/// index in `properties.index`, its style as simplestyle `stroke`,
+/// `stroke-width` and `stroke-opacity`, `properties.group` for grouped
+/// curves, and `properties.meta` for curves carrying host data.
+///
+/// Coordinates are rounded to `state.export_precision` decimal places and are
+/// in world space, which matches canvas pixels (y pointing down) at the
+/// default view. No transform is applied; reproject the result if the target
+/// expects real-world coordinates.
+pub fn export_geojson(state: &State, tolerance: f64) -> String {
+    let precision = state.export_precision.get();
+
+    let features: Vec<String> = state
+        .curves
+        .borrow()
+        .iter()
+        .enumerate()
+        .map(|(index, curve)| {
+            let coordinates = curve
+                .flatten(tolerance)
+                .iter()
+                .map(|&(x, y)| {
+                    format!(
+                        "[{},{}]",
+                        format_coordinate(x, precision),
+                        format_coordinate(y, precision)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(",");
+
+            let geometry = if curve.is_closed() {
+                format!(r#"{{"type":"Polygon","coordinates":[[{coordinates}]]}}"#)
+            }
+            else {
+                format!(r#"{{"type":"LineString","coordinates":[{coordinates}]}}"#)
+            };
+
+            let mut properties = format!(
+                r#""index":{index},"stroke":"{}","stroke-width":{},"stroke-opacity":{}"#,
+                curve.style.color.css(),
+                curve.style.width,
+                curve.style.opacity
+            );
+
+            if let Some(group) = curve.group {
+                properties.push_str(&format!(r#","group":{group}"#));
+            }
+
+            if let Some(meta) = &curve.meta {
+                properties.push_str(&format!(r#","meta":{}"#, json::quote(meta)));
+            }
+
+            format!(r#"{{"type":"Feature","properties":{{{properties}}},"geometry":{geometry}}}"#)
+        })
+        .collect();
+
+    format!(
+        r#"{{"type":"FeatureCollection","features":[{}]}}"#,
+        features.join(",")
+    )
+}
+
+/// Appends the `LineString` and `Polygon` features of a GeoJSON
+/// `FeatureCollection` to the scene as chains of straight curves, one chain
+/// per line or ring. Each feature becomes its own group, and a string
+/// `properties.meta` is kept with each of its curves. Features with other
+/// geometry types are skipped with a console warning.
+///
+/// Returns the number of features imported. Nothing is added if the document
+/// is malformed.
+pub fn import_geojson(state: &State, text: &str) -> Result<usize, String> {
+    let document = json::parse(text)?;
+
+    if document.get("type").and_then(Json::as_str) != Some("FeatureCollection") {
+        return Err("expected a FeatureCollection".to_string());
+    }
+
+    let features = document
+        .get("features")
+        .and_then(Json::as_array)
+        .ok_or("missing features array")?;
+
+    let mut chains = Vec::new();
+
+    for (index, feature) in features.iter().enumerate() {
+        let geometry = feature
+            .get("geometry")
+            .ok_or_else(|| format!("feature {index} has no geometry"))?;
+
+        let coordinates = geometry
+            .get("coordinates")
+            .ok_or_else(|| format!("feature {index} has no coordinates"));
+
+        let lines = match geometry.get("type").and_then(Json::as_str) {
+            Some("LineString") => vec![parse_positions(coordinates?)?],
+            Some("Polygon") => coordinates?
+                .as_array()
+                .ok_or_else(|| format!("feature {index} has malformed rings"))?
+                .iter()
+                .map(parse_positions)
+                .collect::<Result<_, _>>()?,
+            other => {
+                console::warn_1(&JsValue::from(format!(
+                    "Skipping GeoJSON feature {index} with unsupported geometry {}",
+                    other.unwrap_or("(none)")
+                )));
+
+                continue;
+            }
+        };
+
+        let meta = feature
+            .get("properties")
+            .and_then(|properties| properties.get("meta"))
+            .and_then(Json::as_str)
+            .map(Rc::from);
+
+        chains.push((lines, meta));
+    }
+
+    let imported = chains.len();
+    let mut curves = state.curves.borrow_mut();
+
+    for (lines, meta) in chains {
+        let group = state.next_group.get();
+        state.next_group.set(group + 1);
+
+        for line in lines {
+            for pair in line.windows(2) {
+                curves.push(Curve {
+                    group: Some(group),
+                    meta: meta.clone(),
+                    ..Curve::line(pair[0], pair[1])
+                });
+            }
+        }
+    }
+
+    Ok(imported)
+}
+
+fn parse_positions(positions: &Json) -> Result<Vec<Point>, String> {
+    positions
+        .as_array()
+        .ok_or("expected an array of positions")?
+        .iter()
+        .map(|position| {
+            // Positions may carry a third (altitude) value, which is dropped.
+            match position.as_array() {
+                Some([x, y, ..]) => match (x.as_f64(), y.as_f64()) {
+                    (Some(x), Some(y)) => Ok(Point::new(x, y)),
+                    _ => Err("position coordinates must be numbers".to_string()),
+                },
+                _ => Err("expected a position of at least two numbers".to_string()),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,352 @@
+use crate::Mode;
+use crate::State;
+
+const EVERY_MODE: &[Mode] = &[
+    Mode::Edit,
+    Mode::Eyedropper,
+    Mode::Preview,
+    Mode::InsertAnchor,
+    Mode::Rotate,
+    Mode::Mirror,
+];
+
+struct Shortcut {
+    keys: &'static str,
+    description: &'static str,
+    /// Modes the shortcut is listed in.
+    modes: &'static [Mode],
+}
+
+const fn shortcut(
+    keys: &'static str,
+    description: &'static str,
+    modes: &'static [Mode],
+) -> Shortcut {
+    Shortcut {
+        keys,
+        description,
+        modes,
+    }
+}
+
+/// What every key does, for the help panel. Keep in step with the `keydown`
+/// handler.
+const SHORTCUTS: &[Shortcut] = &[
+    shortcut("?", "Show or hide this help", EVERY_MODE),
+    shortcut("Ctrl+Z", "Undo", EVERY_MODE),
+    shortcut("Ctrl+Shift+Z, Ctrl+Y", "Redo", EVERY_MODE),
+    shortcut("E", "Switch between editing and preview", EVERY_MODE),
+    shortcut("Middle drag", "Pan the view", EVERY_MODE),
+    shortcut("f", "Fit the scene to the view", EVERY_MODE),
+    shortcut("0", "Reset the view", EVERY_MODE),
+    shortcut("c", "Center the view on the selection", EVERY_MODE),
+    shortcut("o", "Import GeoJSON or SVG", EVERY_MODE),
+    shortcut(
+        "7",
+        "Toggle making imported outlines counterclockwise and holes clockwise",
+        &[Mode::Edit],
+    ),
+    shortcut("g", "Export GeoJSON", EVERY_MODE),
+    shortcut("v", "Export the view as SVG", EVERY_MODE),
+    shortcut("V", "Export the scene as SVG, cropped to it", EVERY_MODE),
+    shortcut("P", "Export the scene as PNG at a chosen size", EVERY_MODE),
+    shortcut(
+        "p",
+        "Export frames of a dot along the selection",
+        EVERY_MODE,
+    ),
+    shortcut("(", "Choose how the dot eases, and preview it", EVERY_MODE),
+    shortcut("H", "Put the scene into the page's link", EVERY_MODE),
+    shortcut(
+        "*",
+        "Put the scene and the selection into the page's link",
+        EVERY_MODE,
+    ),
+    shortcut("W", "Toggle shadows", EVERY_MODE),
+    shortcut("d", "Toggle marching dashes", EVERY_MODE),
+    shortcut("A", "Toggle high contrast", EVERY_MODE),
+    shortcut(
+        "Click",
+        "Pick a curve's style, then apply it to others",
+        &[Mode::Eyedropper],
+    ),
+    shortcut("Esc", "Back to editing", &[Mode::Eyedropper]),
+    shortcut("Click", "Insert an anchor", &[Mode::InsertAnchor]),
+    shortcut("N, Esc", "Back to editing", &[Mode::InsertAnchor]),
+    shortcut("Drag", "Rotate the selection", &[Mode::Rotate]),
+    shortcut("Shift+Drag", "Rotate in 15° steps", &[Mode::Rotate]),
+    shortcut("Alt+Click", "Place the pivot", &[Mode::Rotate]),
+    shortcut("Z, Esc", "Back to editing", &[Mode::Rotate]),
+    shortcut("Drag", "Draw the mirror axis", &[Mode::Mirror]),
+    shortcut(
+        "Space",
+        "Mirror across the vertical or horizontal through the selection",
+        &[Mode::Mirror],
+    ),
+    shortcut("Enter", "Add the mirrored copy", &[Mode::Mirror]),
+    shortcut("<, Esc", "Back to editing", &[Mode::Mirror]),
+    shortcut(
+        "Click twice",
+        "Draw a curve, unless ; has changed which clicks draw",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "Click a selected end",
+        "Draw a curve continuing it smoothly; hold Alt for a corner",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        ";",
+        "Cycle which clicks draw: any, Ctrl+click only, or none",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        ">",
+        "Switch between drawing separate curves and paths end to end",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "Enter, Esc",
+        "Finish or cancel the path being drawn; click its start to close it",
+        &[Mode::Edit],
+    ),
+    shortcut("N", "Insert anchors by clicking curves", &[Mode::Edit]),
+    shortcut("Z", "Rotate the selection", &[Mode::Edit]),
+    shortcut("<", "Mirror a copy of the selection", &[Mode::Edit]),
+    shortcut(
+        "Shift+Click",
+        "Add to or remove from the selection",
+        &[Mode::Edit],
+    ),
+    shortcut("q", "Draw cubic or quadratic curves", &[Mode::Edit]),
+    shortcut(
+        "Q",
+        "Convert selected curves between cubic and quadratic",
+        &[Mode::Edit],
+    ),
+    shortcut("1–6", "Color selected curves", &[Mode::Edit]),
+    shortcut("w", "Cycle the selected curves' width", &[Mode::Edit]),
+    shortcut(
+        "G",
+        "Color selected curves by position or curvature",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "^",
+        "Cycle the selected paths' corners between miter, round and bevel",
+        &[Mode::Edit],
+    ),
+    shortcut("&", "Set the selected paths' miter limit", &[Mode::Edit]),
+    shortcut(
+        ")",
+        "Cycle the selected curves' stroke between centered, inside and outside",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "[, ]",
+        "Cycle the selected curves' start or end cap: none, dot, arrow, bar",
+        &[Mode::Edit],
+    ),
+    shortcut("i", "Eyedropper", &[Mode::Edit]),
+    shortcut(
+        "s, S, e",
+        "Select curves of the same color, width, or closedness",
+        &[Mode::Edit],
+    ),
+    shortcut("Ctrl+I", "Invert the selection", &[Mode::Edit]),
+    shortcut("O", "Select open paths", &[Mode::Edit]),
+    shortcut("C", "Close the selected path", &[Mode::Edit]),
+    shortcut(
+        "B",
+        "Break the path at the last grabbed anchor",
+        &[Mode::Edit],
+    ),
+    shortcut("D", "Duplicate the selection", &[Mode::Edit]),
+    shortcut(
+        "Ctrl+Shift+C",
+        "Copy the selection's points to the clipboard",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "Ctrl+V",
+        "Paste SVG, SVG path data or a shared scene",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "X",
+        "Add or remove a dimension between the last two grabbed anchors",
+        &[Mode::Edit],
+    ),
+    shortcut("K", "Weld all ends that nearly meet", &[Mode::Edit]),
+    shortcut("F", "Weld the next ends that nearly meet", &[Mode::Edit]),
+    shortcut(
+        "%",
+        "Join the selected curves into one path, welding ends that nearly meet",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "r, R",
+        "Reverse selected curves, or their whole paths",
+        &[Mode::Edit],
+    ),
+    shortcut("I", "Split curves where they cross", &[Mode::Edit]),
+    shortcut(
+        "/",
+        "Split the selected curve into pieces of equal length",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "8",
+        "Space the anchors of the selected path evenly, keeping its shape",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "|",
+        "Straighten selected curves that are nearly straight",
+        &[Mode::Edit],
+    ),
+    shortcut("m", "Smooth gentle corners", &[Mode::Edit]),
+    shortcut("a", "Cycle the last grabbed anchor's type", &[Mode::Edit]),
+    shortcut("b", "Balance handle lengths at junctions", &[Mode::Edit]),
+    shortcut(
+        "y",
+        "Line up the handles where the two selected curves meet",
+        &[Mode::Edit],
+    ),
+    shortcut("+, -", "Change the selected curves' tension", &[Mode::Edit]),
+    shortcut(
+        "\"",
+        "Weight the selected curves' control points, for exact circular arcs",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        ":",
+        "Make the selected curve symmetric, with matching handles",
+        &[Mode::Edit],
+    ),
+    shortcut("n", "Pin or unpin the selected curve", &[Mode::Edit]),
+    shortcut(
+        "j",
+        "Cycle the last grabbed handle's axis lock",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "J",
+        "Lock or unlock selected curves straight",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "T, Enter",
+        "Trim the selected curve, then apply",
+        &[Mode::Edit],
+    ),
+    shortcut("Esc", "Cancel trimming", &[Mode::Edit]),
+    shortcut(
+        "Arrows",
+        "Nudge the last grabbed handle to the next grid node, or by a pixel",
+        &[Mode::Edit],
+    ),
+    shortcut("Shift+Arrows", "Nudge by ten pixels", &[Mode::Edit]),
+    shortcut(
+        "\\",
+        "Switch the arrows between moving and scaling control handles",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "., ,",
+        "Grab the next or previous handle of the same curve",
+        &[Mode::Edit],
+    ),
+    shortcut("x", "Remove degenerate curves", &[Mode::Edit]),
+    shortcut("Backspace", "Remove the newest curve", &[Mode::Edit]),
+    shortcut("#", "Cycle the grid", &[Mode::Edit]),
+    shortcut(
+        "{",
+        "Toggle labels giving grid points' coordinates",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "9",
+        "Toggle a table of the selected curve's points beside it",
+        &[Mode::Edit],
+    ),
+    shortcut("!", "Turn snapping on or off", &[Mode::Edit]),
+    shortcut(
+        "Alt",
+        "Hold to drag or draw without snapping",
+        &[Mode::Edit],
+    ),
+    shortcut("l", "Cycle length snapping", &[Mode::Edit]),
+    shortcut(
+        "Y",
+        "Toggle splitting curves that ends are dropped onto",
+        &[Mode::Edit],
+    ),
+    shortcut("t", "Toggle tangent extensions", &[Mode::Edit]),
+    shortcut(
+        "}",
+        "Toggle warnings at sharp junctions; click one to smooth it",
+        &[Mode::Edit],
+    ),
+    shortcut("k", "Toggle the osculating circle", &[Mode::Edit]),
+    shortcut(
+        "~",
+        "Toggle lines showing each control point's influence",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "@",
+        "Toggle a marker to drag along the selected curve",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "$",
+        "Toggle a box around the selection for scaling and rotating it",
+        &[Mode::Edit],
+    ),
+    shortcut(
+        "Shift+Drag",
+        "Scale uniformly or rotate in 15° steps with the box",
+        &[Mode::Edit],
+    ),
+    shortcut("h", "Toggle the crosshair", &[Mode::Edit]),
+    shortcut(
+        "`",
+        "Toggle labels giving each handle's curve index and letter",
+        &[Mode::Edit],
+    ),
+    shortcut("M", "Toggle rulers", &[Mode::Edit]),
+    shortcut(
+        "'",
+        "Toggle an outline of the canvas at the default view",
+        &[Mode::Edit],
+    ),
+    shortcut("u, U, L, _", "Change how handles look", &[Mode::Edit]),
+];
+
+/// Lists the shortcuts for the current mode in the help panel while it is
+/// shown. Like the layers panel, it is only rebuilt when its contents change.
+pub fn update_help_panel(state: &State) {
+    if state.help_panel.hidden() {
+        return;
+    }
+
+    let mode = state.mode.get();
+
+    let html: String = SHORTCUTS
+        .iter()
+        .filter(|shortcut| shortcut.modes.contains(&mode))
+        .map(|shortcut| {
+            format!(
+                "<tr><th>{}</th><td>{}</td></tr>",
+                shortcut.keys, shortcut.description
+            )
+        })
+        .collect();
+
+    let html = format!("<table>{html}</table>");
+
+    if state.help_panel.inner_html() != html {
+        state.help_panel.set_inner_html(&html);
+    }
+}
@@ -0,0 +1,57 @@
+use crate::Curve;
+
+/// Most snapshots kept for undo; the oldest are dropped beyond this.
+const HISTORY_LIMIT: usize = 100;
+
+/// Snapshots of the curve list taken before each edit.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<Curve>>,
+    redo: Vec<Vec<Curve>>,
+}
+
+impl History {
+    /// Saves `curves` as they were before an edit. Anything that could be
+    /// redone is discarded.
+    pub fn record(&mut self, curves: Vec<Curve>) {
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.remove(0);
+        }
+
+        self.undo.push(curves);
+        self.redo.clear();
+    }
+
+    /// Restores the latest snapshot into `curves`, or returns `false` if
+    /// there is nothing to undo.
+    pub fn undo(&mut self, curves: &mut Vec<Curve>) -> bool {
+        let Some(previous) = self.undo.pop()
+        else {
+            return false;
+        };
+
+        self.redo.push(std::mem::replace(curves, previous));
+
+        true
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Reapplies the latest undone edit, or returns `false` if there is none.
+    pub fn redo(&mut self, curves: &mut Vec<Curve>) -> bool {
+        let Some(next) = self.redo.pop()
+        else {
+            return false;
+        };
+
+        self.undo.push(std::mem::replace(curves, next));
+
+        true
+    }
+}
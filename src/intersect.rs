@@ -0,0 +1,244 @@
+use crate::warn_depth_reached;
+use crate::Curve;
+use crate::Point;
+use crate::MAX_SUBDIVISION_DEPTH;
+
+/// Subdivided pieces smaller than this, in world units, are taken to meet.
+const INTERSECTION_TOLERANCE: f64 = 1e-3;
+/// Hits closer than this in both parameters are the same intersection, found
+/// once from each side of a subdivision boundary.
+const DUPLICATE_PARAMETER_DISTANCE: f64 = 1e-3;
+/// Intersections this close to an end of a curve are where curves merely
+/// meet at a junction, not places to split.
+const END_PARAMETER_MARGIN: f64 = 1e-3;
+/// Most pairs of pieces examined for one pair of curves. Overlapping curves
+/// would otherwise subdivide along their whole shared stretch.
+const SUBDIVISION_BUDGET: usize = 20_000;
+
+/// Parameters `(t, u)` where `a.point_at(t)` meets `b.point_at(u)`, ordered
+/// by `t`. Found by recursively halving both curves and discarding pairs of
+/// pieces whose control polygons' bounds don't overlap. Pieces still
+/// overlapping at `MAX_SUBDIVISION_DEPTH` count as meeting.
+pub fn intersections(a: &Curve, b: &Curve) -> Vec<(f64, f64)> {
+    let mut hits = Vec::new();
+    let mut budget = SUBDIVISION_BUDGET;
+    let mut depth_reached = false;
+
+    intersect_recursive(
+        (a.to_cubic(), 0.0, 1.0),
+        (b.to_cubic(), 0.0, 1.0),
+        MAX_SUBDIVISION_DEPTH.get(),
+        &mut budget,
+        &mut depth_reached,
+        &mut hits,
+    );
+
+    if depth_reached {
+        warn_depth_reached("Finding intersections");
+    }
+
+    hits.sort_by(|x, y| x.0.total_cmp(&y.0));
+    hits.dedup_by(|x, y| {
+        (x.0 - y.0).abs() < DUPLICATE_PARAMETER_DISTANCE
+            && (x.1 - y.1).abs() < DUPLICATE_PARAMETER_DISTANCE
+    });
+
+    hits
+}
+
+/// Each piece is a curve with the parameter range of the original it covers.
+fn intersect_recursive(
+    (a, a_start, a_end): (Curve, f64, f64),
+    (b, b_start, b_end): (Curve, f64, f64),
+    depth: u32,
+    budget: &mut usize,
+    depth_reached: &mut bool,
+    hits: &mut Vec<(f64, f64)>,
+) {
+    if *budget == 0 {
+        return;
+    }
+
+    *budget -= 1;
+
+    let (a_min, a_max) = a.control_bounds();
+    let (b_min, b_max) = b.control_bounds();
+
+    if a_max.x < b_min.x || b_max.x < a_min.x || a_max.y < b_min.y || b_max.y < a_min.y {
+        return;
+    }
+
+    let small = |min: Point, max: Point| {
+        max.x - min.x < INTERSECTION_TOLERANCE && max.y - min.y < INTERSECTION_TOLERANCE
+    };
+
+    if small(a_min, a_max) && small(b_min, b_max) {
+        hits.push(((a_start + a_end) / 2.0, (b_start + b_end) / 2.0));
+        return;
+    }
+
+    if depth == 0 {
+        *depth_reached = true;
+        hits.push(((a_start + a_end) / 2.0, (b_start + b_end) / 2.0));
+        return;
+    }
+
+    let a_middle = (a_start + a_end) / 2.0;
+    let b_middle = (b_start + b_end) / 2.0;
+    let (a_first, a_second) = a.split(0.5);
+    let (b_first, b_second) = b.split(0.5);
+
+    for a_piece in [(a_first, a_start, a_middle), (a_second, a_middle, a_end)] {
+        for b_piece in [
+            (b_first.clone(), b_start, b_middle),
+            (b_second.clone(), b_middle, b_end),
+        ] {
+            intersect_recursive(
+                a_piece.clone(),
+                b_piece,
+                depth - 1,
+                budget,
+                depth_reached,
+                hits,
+            );
+        }
+    }
+}
+
+/// Splits every curve wherever it crosses another one. Returns the pieces
+/// each curve was split into, by curve index, along with the number of
+/// crossings found. Curves that cross nothing come back as a single piece.
+pub fn split_at_intersections(curves: &[Curve]) -> (Vec<Vec<Curve>>, usize) {
+    let mut parameters = vec![Vec::new(); curves.len()];
+    let mut crossings = 0;
+
+    for i in 0..curves.len() {
+        for j in i + 1..curves.len() {
+            for (t, u) in intersections(&curves[i], &curves[j]) {
+                let inner =
+                    |t: f64| (END_PARAMETER_MARGIN..=1.0 - END_PARAMETER_MARGIN).contains(&t);
+
+                // Meeting at a shared endpoint is not a crossing, but an
+                // endpoint landing on the middle of another curve still
+                // splits that one.
+                if inner(t) || inner(u) {
+                    crossings += 1;
+                }
+
+                if inner(t) {
+                    parameters[i].push(t);
+                }
+
+                if inner(u) {
+                    parameters[j].push(u);
+                }
+            }
+        }
+    }
+
+    let pieces = curves
+        .iter()
+        .zip(parameters)
+        .map(|(curve, mut parameters)| {
+            parameters.sort_by(f64::total_cmp);
+
+            let mut pieces = Vec::new();
+            let mut rest = curve.clone();
+            let mut start = 0.0;
+
+            for t in parameters {
+                // Splitting `rest` at the original curve's `t` means
+                // rescaling `t` into the range `rest` still covers.
+                let local = (t - start) / (1.0 - start);
+
+                if local < END_PARAMETER_MARGIN {
+                    continue;
+                }
+
+                let (first, second) = rest.split(local);
+                pieces.push(first);
+                rest = second;
+                start = t;
+            }
+
+            pieces.push(rest);
+            pieces
+        })
+        .collect();
+
+    (pieces, crossings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crossing_curves_split_into_four_pieces() {
+        let curves = [
+            Curve::line(Point::new(0.0, 0.0), Point::new(10.0, 10.0)),
+            Curve::new(
+                Point::new(0.0, 10.0),
+                Point::new(3.0, 6.0),
+                Point::new(7.0, 4.0),
+                Point::new(10.0, 0.0),
+            ),
+            Curve::line(Point::new(20.0, 0.0), Point::new(30.0, 0.0)),
+        ];
+
+        let (pieces, crossings) = split_at_intersections(&curves);
+
+        assert_eq!(crossings, 1);
+        assert_eq!(pieces.iter().map(Vec::len).collect::<Vec<_>>(), [2, 2, 1]);
+
+        for (curve, pieces) in curves.iter().zip(&pieces) {
+            assert!(pieces[0].a == curve.a && pieces[pieces.len() - 1].d == curve.d);
+            assert!(pieces.windows(2).all(|pair| pair[0].d == pair[1].a));
+        }
+
+        let crossing = pieces[0][0].d;
+        assert!((crossing.x - 5.0).abs() < 1e-3 && (crossing.y - 5.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn curves_meeting_at_ends_are_not_split() {
+        let curves = [
+            Curve::line(Point::new(0.0, 0.0), Point::new(10.0, 0.0)),
+            Curve::line(Point::new(10.0, 0.0), Point::new(10.0, 10.0)),
+        ];
+
+        let (pieces, crossings) = split_at_intersections(&curves);
+
+        assert_eq!(crossings, 0);
+        assert!(pieces.iter().all(|pieces| pieces.len() == 1));
+    }
+
+    #[test]
+    fn subdivision_stops_at_the_depth_limit() {
+        // Pieces of these never shrink below the tolerance, so only the
+        // depth limit ends the search well within the budget.
+        let a = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(f64::INFINITY, 10.0),
+            Point::new(10.0, 10.0),
+            Point::new(10.0, 0.0),
+        );
+        let b = Curve::line(Point::new(0.0, 5.0), Point::new(10.0, 5.0));
+        let mut budget = SUBDIVISION_BUDGET;
+        let mut depth_reached = false;
+        let mut hits = Vec::new();
+
+        intersect_recursive(
+            (a, 0.0, 1.0),
+            (b, 0.0, 1.0),
+            4,
+            &mut budget,
+            &mut depth_reached,
+            &mut hits,
+        );
+
+        assert!(depth_reached);
+        assert!(budget > 0);
+        assert!(hits.len() <= 1 << 8);
+    }
+}
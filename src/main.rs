@@ -1,29 +1,288 @@
+mod api;
+mod binary;
+mod dimension;
+mod frames;
+mod geojson;
+mod help;
+mod history;
+mod intersect;
+mod json;
+mod outline;
+mod redistribute;
+mod sample;
+mod smooth;
+mod snap;
+mod svg;
+mod topology;
+
+use dimension::Dimension;
+use frames::Easing;
+use frames::Pacing;
+use history::History;
+use snap::resolve_snap;
+use snap::GridKind;
+use snap::SnapKind;
+use snap::SnapSettings;
+use std::array;
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::f64::consts::PI;
 use std::rc::Rc;
+use std::slice;
+use topology::Subpath;
+use wasm_bindgen::convert::FromWasmAbi;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsCast;
+use wasm_bindgen::JsValue;
+use web_sys::console;
+use web_sys::js_sys::Array;
+use web_sys::js_sys::Date;
 use web_sys::js_sys::JsString;
 use web_sys::window;
+use web_sys::Blob;
+use web_sys::BlobPropertyBag;
 use web_sys::CanvasRenderingContext2d;
+use web_sys::Element;
+use web_sys::Event;
 use web_sys::EventTarget;
+use web_sys::FileReader;
+use web_sys::HtmlAnchorElement;
 use web_sys::HtmlCanvasElement;
+use web_sys::HtmlElement;
+use web_sys::HtmlInputElement;
+use web_sys::KeyboardEvent;
 use web_sys::MouseEvent;
+use web_sys::Url;
+
+const POINT_RADIUS: f64 = 4.0;
+/// Stroke colors assigned with the number keys.
+const PALETTE: [Color; 6] = [
+    Color::new(0, 0, 0),
+    Color::new(220, 50, 47),
+    Color::new(38, 139, 210),
+    Color::new(133, 153, 0),
+    Color::new(181, 137, 0),
+    Color::new(108, 113, 196),
+];
+const STROKE_WIDTHS: [f64; 3] = [1.0, 2.0, 4.0];
+/// The canvas's and SVG's own default.
+const DEFAULT_MITER_LIMIT: f64 = 10.0;
+/// Size of end caps as a multiple of the stroke width.
+const CAP_SCALE: f64 = 5.0;
+/// Farthest, in screen pixels, a curve may stray from its chord to be
+/// straightened with `|`.
+const STRAIGHTEN_TOLERANCE: f64 = 2.0;
+/// Farthest, in world units, the path may stray from where it was when its
+/// anchors are spaced evenly with `8`.
+const REDISTRIBUTE_TOLERANCE: f64 = 1.0;
+/// Pieces a curve is stroked in when colored along its length.
+const COLOR_SCALE_SEGMENTS: usize = 64;
+/// How close, in screen pixels, a click must land to a curve to hit it.
+const CURVE_HIT_TOLERANCE: f64 = 4.0;
+const GRID_COLOR: &str = "rgba(0, 0, 0, 0.12)";
+const PAGE_COLOR: &str = "rgba(0, 0, 0, 0.3)";
+/// Junctions where the path turns by more than this many radians count as
+/// kinks.
+const KINK_ANGLE: f64 = 0.05;
+const KINK_COLOR: &str = "rgb(230, 120, 0)";
+/// Size of a kink's warning marker, and how far above the junction it sits
+/// so it doesn't cover the anchor, in screen pixels.
+const KINK_MARKER_SIZE: f64 = 10.0;
+const KINK_MARKER_OFFSET: f64 = 16.0;
+/// Grid lines closer than this many screen pixels are not drawn.
+const MIN_GRID_SPACING: f64 = 6.0;
+const GRID_LABEL_COLOR: &str = "rgba(0, 0, 0, 0.45)";
+/// Labelled grid points are at least this many screen pixels apart.
+const MIN_GRID_LABEL_SPACING: f64 = 80.0;
+const POINT_TABLE_BACKGROUND: &str = "rgba(255, 255, 255, 0.85)";
+/// Height of a row of the selected curve's point table, and the space
+/// between it and the curve's control points, in screen pixels.
+const POINT_TABLE_ROW_HEIGHT: f64 = 12.0;
+const POINT_TABLE_OFFSET: f64 = 12.0;
+/// Size and distance from the canvas edges of the arrows pointing at
+/// off-screen handles, in screen pixels.
+const OFFSCREEN_INDICATOR_SIZE: f64 = 8.0;
+const OFFSCREEN_INDICATOR_MARGIN: f64 = 12.0;
+/// Thickness of the rulers along the canvas edges, in screen pixels.
+const RULER_SIZE: f64 = 20.0;
+const RULER_BACKGROUND: &str = "rgba(255, 255, 255, 0.85)";
+const MIN_RULER_TICK_SPACING: f64 = 50.0;
+const CROSSHAIR_COLOR: &str = "rgba(0, 128, 128, 0.8)";
+const CROSSHAIR_LABEL_OFFSET: f64 = 6.0;
+/// Opacity of a dragged curve's outline at its original position, relative
+/// to its own.
+const GHOST_OPACITY: f64 = 0.25;
+/// Milliseconds a redraw may spend stroking curves before leaving the rest
+/// to following frames.
+const FRAME_BUDGET: f64 = 8.0;
+/// Milliseconds the dot takes along the selection when previewing easing.
+const DOT_PREVIEW_DURATION: f64 = 2000.0;
+/// Strokes drawn between checks of the clock against `FRAME_BUDGET`.
+const LAYER_CHUNK: usize = 32;
+const OSCULATING_CIRCLE_COLOR: &str = "rgba(128, 0, 128, 0.4)";
+/// Curvatures below this, in inverse world units, count as straight.
+const MIN_CURVATURE: f64 = 1e-6;
+/// Milliseconds of quiet after which the latest announcement is read out.
+const ANNOUNCE_DELAY: i32 = 300;
+/// Factor a control handle's length changes by per arrow key press when
+/// nudging relatively, and with Shift held.
+const RELATIVE_NUDGE_FACTOR: f64 = 1.1;
+const RELATIVE_NUDGE_FAR_FACTOR: f64 = 1.5;
+/// Stroke widths are multiplied by this in high-contrast mode.
+const HIGH_CONTRAST_STROKE_SCALE: f64 = 2.0;
+/// Samples taken by `Curve::nearest_t` before refining.
+const NEAREST_T_SAMPLES: usize = 64;
+const NEAREST_T_ITERATIONS: usize = 40;
+const TRIM_COLOR: &str = "rgba(255, 140, 0, 0.6)";
+const SCRUB_COLOR: &str = "rgb(0, 150, 70)";
+/// Trim handles can't come closer together than this in `t`.
+const MIN_TRIM_LENGTH: f64 = 0.01;
+/// Factor by which `+` lengthens and `-` shortens the selected curves'
+/// handles.
+const TENSION_STEP: f64 = 1.1;
+/// Scenes with more curves than this ask before being split at their
+/// intersections.
+const SPLIT_CONFIRM_THRESHOLD: usize = 200;
+/// Paths that turn by more than this many radians at an anchor keep a sharp
+/// corner there when smoothed.
+const SMOOTH_CORNER_ANGLE: f64 = PI / 4.0;
+/// Length of the direction arrowheads on selected curves, in screen pixels.
+const ARROWHEAD_SIZE: f64 = 10.0;
+/// How far apart, in screen pixels, breaking a path leaves the two ends.
+const BREAK_GAP: f64 = 6.0;
+/// How close, in screen pixels, the pointer must be to a curve to insert an
+/// anchor into it.
+const INSERT_ANCHOR_DISTANCE: f64 = 12.0;
+/// Parameters this close to an end of a curve are its existing anchors.
+const INSERT_ANCHOR_MARGIN: f64 = 1e-3;
+/// Curve ends closer than this, in world units, but not exactly together
+/// are offered for welding.
+const WELD_DISTANCE: f64 = 1.0;
+/// Gap between a duplicate and what it was copied from, in world units, and
+/// the offset used when no empty space is found nearby.
+const DUPLICATE_OFFSET: f64 = 20.0;
+/// How many copies' widths away from the original a duplicate may be placed.
+const DUPLICATE_SEARCH_DISTANCE: usize = 3;
+const PIN_COLOR: &str = "crimson";
+/// Half the size of a pin's cross, in screen pixels.
+const PIN_SIZE: f64 = 4.0;
+const SELECTION_COLOR: &str = "rgba(30, 144, 255, 0.35)";
+const SELECTION_HALO_WIDTH: f64 = 6.0;
+const TANGENT_EXTENSION_LENGTH: f64 = 40.0;
+const FLATTEN_TOLERANCE: f64 = 0.25;
+const ARC_LENGTH_TOLERANCE: f64 = 0.01;
+/// Deep enough for curves thousands of pixels long to reach every tolerance
+/// used, while a curve hitting it everywhere still makes only about a
+/// million pieces.
+const DEFAULT_MAX_SUBDIVISION_DEPTH: u32 = 20;
+/// Equal pieces of the parameter range that quadrature integrates the speed
+/// over separately, so sharp bends don't spoil the whole estimate.
+const ARC_LENGTH_INTERVALS: usize = 8;
+/// Nodes and weights of five-point Gauss-Legendre quadrature on `[-1, 1]`.
+const GAUSS_LEGENDRE: [(f64, f64); 5] = [
+    (0.0, 0.568_888_888_888_888_9),
+    (-0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+    (0.538_469_310_105_683_1, 0.478_628_670_499_366_5),
+    (-0.906_179_845_938_664, 0.236_926_885_056_189_08),
+    (0.906_179_845_938_664, 0.236_926_885_056_189_08),
+];
+const DEFAULT_EXPORT_PRECISION: usize = 2;
+/// How close, in screen pixels, a dragged endpoint must come to another
+/// endpoint to snap onto it.
+const SNAP_RADIUS: f64 = 8.0;
+const SNAP_INDICATOR_RADIUS: f64 = 8.0;
+const SNAP_COLOR: &str = "orange";
+/// Points along the selected curve that influence lines are drawn from.
+const INFLUENCE_SAMPLES: usize = 16;
+/// Opacity of an influence line to a control point with full weight.
+const INFLUENCE_OPACITY: f64 = 0.6;
+const PIVOT_COLOR: &str = "rgb(220, 50, 47)";
+const MIRROR_AXIS_COLOR: &str = "rgb(38, 139, 210)";
+const PIVOT_MARKER_RADIUS: f64 = 6.0;
+/// Angle that rotations snap to multiples of while Shift is held.
+const ROTATION_SNAP_DEGREES: f64 = 15.0;
+const GIZMO_COLOR: &str = "rgb(30, 144, 255)";
+/// Half the side of a transform gizmo's square scale handles.
+const GIZMO_HANDLE_SIZE: f64 = 4.0;
+/// Distance of the gizmo's rotation handle above the top of its box.
+const GIZMO_ROTATE_OFFSET: f64 = 24.0;
+/// Bounding box size, in world units, below which a curve counts as
+/// degenerate.
+const DEGENERATE_EXTENT: f64 = 1e-6;
+const STATUS_FONT: &str = "12px sans-serif";
+const HANDLE_LABEL_FONT: &str = "9px monospace";
+const STATUS_MARGIN: f64 = 8.0;
+/// Milliseconds a view change takes to ease into place.
+const VIEW_ANIMATION_DURATION: f64 = 200.0;
+/// Dash speed in pixels per second when marching dashes are on.
+const DASH_SPEED: f64 = 20.0;
+/// Fraction of the canvas left empty on each side by fit-to-view.
+const FIT_MARGIN: f64 = 0.05;
+/// Starts the URL fragment of a link to a scene, followed by the scene in
+/// `binary`'s encoding.
+const SHARE_PREFIX: &str = "#scene=";
+/// Commands too disruptive to trigger with a stray keystroke.
+const MENU_HTML: &str = r#"<button type="button" data-sample="replace">Load sample scene</button>
+<button type="button" data-sample="append">Add sample scene</button>"#;
+/// Fields of the handles panel, by handle and quantity.
+const HANDLES_PANEL_HTML: &str = r#"<div>
+    b: <input class="handle-b-length" type="number" step="any"> px at
+    <input class="handle-b-angle" type="number" step="any">°
+</div>
+<div>
+    c: <input class="handle-c-length" type="number" step="any"> px at
+    <input class="handle-c-angle" type="number" step="any">°
+</div>"#;
 
-const POINT_RADIUS: u32 = 4;
+thread_local! {
+    /// Number of editors set up so far, which is also the next one's id.
+    static EDITOR_COUNT: Cell<usize> = const { Cell::new(0) };
+    /// Id of the editor that keystrokes go to: the one last clicked.
+    static ACTIVE_EDITOR: Cell<usize> = const { Cell::new(0) };
+    /// How many times recursive subdivision may halve a curve before settling
+    /// for the approximation reached, so that pathological curves (huge,
+    /// nearly degenerate, or with non-finite points) can't hang the page.
+    /// Shared by every editor on the page.
+    static MAX_SUBDIVISION_DEPTH: Cell<u32> = const { Cell::new(DEFAULT_MAX_SUBDIVISION_DEPTH) };
+}
 
 fn main() {
     console_error_panic_hook::set_once();
 
+    let canvas: HtmlCanvasElement = window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .get_element_by_id("canvas")
+        .unwrap()
+        .dyn_into()
+        .unwrap();
+
+    let state = init(canvas.clone());
+
+    // Only the page's own editor answers to the link's scene.
+    load_shared_scene(&canvas, state.as_ref());
+    request_animation_frame(state.as_ref());
+    api::expose(canvas, state);
+}
+
+/// Sets up an editor on `canvas`, with its panels placed next to it. Editors
+/// are independent of each other, apart from keystrokes going to the one
+/// clicked last.
+fn init(canvas: HtmlCanvasElement) -> Rc<State> {
     let window = window().unwrap();
     let document = window.document().unwrap();
+    let id = EDITOR_COUNT.with(|count| count.replace(count.get() + 1));
 
-    let canvas: HtmlCanvasElement = document
-        .get_element_by_id("canvas")
+    // Wrap the canvas so the panels can be positioned over it.
+    let editor = document.create_element("div").unwrap();
+    editor.set_class_name("slipsole");
+    canvas
+        .parent_node()
         .unwrap()
-        .dyn_into()
+        .replace_child(&editor, &canvas)
         .unwrap();
+    editor.append_child(&canvas).unwrap();
 
     canvas
         .set_attribute("width", &canvas.client_width().to_string())
@@ -40,86 +299,546 @@ fn main() {
         .dyn_into()
         .unwrap();
 
-    let state = Rc::new(State {
-        curves: RefCell::new(Vec::new()),
-        drag_state: Cell::new(None),
-        new_curve: Cell::new(None),
-        mouse: Cell::new(Point::new(0, 0)),
-    });
+    let layers_panel = add_panel(&editor, "ol", "layers");
+
+    let help_panel = add_panel(&editor, "div", "help");
+    help_panel.set_hidden(true);
+
+    let handles_panel = add_panel(&editor, "form", "handles");
+    handles_panel.set_hidden(true);
+    handles_panel.set_inner_html(HANDLES_PANEL_HTML);
+
+    let menu = add_panel(&editor, "div", "menu");
+    menu.set_inner_html(MENU_HTML);
+
+    let announcer = add_panel(&editor, "div", "announcer");
+    announcer.set_attribute("aria-live", "polite").unwrap();
+
+    let state = Rc::new(State::new(
+        id,
+        layers_panel,
+        help_panel,
+        handles_panel,
+        announcer,
+        SceneLayer::new(),
+    ));
+
+    *state.announce_callback.borrow_mut() = Some(Closure::new({
+        let state = state.clone();
+
+        move || {
+            state.announce_timeout.set(None);
+            state
+                .announcer
+                .set_text_content(Some(&state.announcement.take()));
+        }
+    }));
+
+    *state.animation_frame.borrow_mut() = Some(Closure::new({
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |time: f64| {
+            state.frame_requested.set(false);
+
+            let viewing = step_view_animation(state.as_ref(), time);
+            let previewing = step_dot_preview(state.as_ref(), time);
+
+            if viewing || previewing {
+                request_animation_frame(state.as_ref());
+            }
+
+            step_dash_animation(state.as_ref(), time);
+
+            redraw(&canvas, &context, state.as_ref());
+        }
+    }));
 
     redraw(&canvas, &context, state.as_ref());
 
+    let import_input: HtmlInputElement = document
+        .create_element("input")
+        .unwrap()
+        .dyn_into()
+        .unwrap();
+    import_input.set_type("file");
+    import_input.set_accept(".geojson,.json,.svg,application/geo+json,image/svg+xml");
+
+    let import_reader = FileReader::new().unwrap();
+
+    add_event_listener(&import_input, "change", {
+        let import_input = import_input.clone();
+        let import_reader = import_reader.clone();
+
+        move |_: Event| {
+            if let Some(file) = import_input.files().and_then(|files| files.get(0)) {
+                import_reader.read_as_text(&file).unwrap();
+            }
+
+            // Clear the selection so picking the same file again still fires
+            // `change`.
+            import_input.set_value("");
+        }
+    });
+
+    add_event_listener(&import_reader, "load", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+        let import_reader = import_reader.clone();
+
+        move |_: Event| {
+            let text = import_reader.result().unwrap().as_string().unwrap();
+
+            // SVG is XML and GeoJSON is JSON, so the first character tells
+            // them apart regardless of the file name. A failed import adds
+            // nothing, so it leaves no history either.
+            let first = state.curves.borrow().len();
+            let result = transaction(state.as_ref(), || {
+                let result = if text.trim_start().starts_with('<') {
+                    svg::import_svg(state.as_ref(), &text)
+                }
+                else {
+                    geojson::import_geojson(state.as_ref(), &text)
+                };
+
+                let reversed = normalize_imported_winding(state.as_ref(), first);
+                result.map(|_| reversed)
+            });
+
+            match result {
+                Ok(reversed) => {
+                    if let Some(message) = describe_reversed(reversed) {
+                        announce(state.as_ref(), message);
+                    }
+
+                    fit_to_view(&canvas, state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                Err(error) => {
+                    console::error_1(&JsValue::from(format!("Import failed: {error}")));
+                }
+            }
+        }
+    });
+
+    add_event_listener(&state.layers_panel, "click", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |event: Event| {
+            if let Some(index) = layer_index(&event) {
+                *state.selection.borrow_mut() = vec![index];
+                recenter_on_selected(&canvas, state.as_ref());
+                redraw(&canvas, &context, state.as_ref());
+            }
+        }
+    });
+
+    add_event_listener(&menu, "click", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |event: MouseEvent| {
+            let sample = event
+                .target()
+                .and_then(|target| target.dyn_into::<Element>().ok())
+                .and_then(|target| target.get_attribute("data-sample"));
+
+            let Some(sample) = sample
+            else {
+                return;
+            };
+
+            load_sample_scene(&canvas, state.as_ref(), sample == "replace");
+            redraw(&canvas, &context, state.as_ref());
+        }
+    });
+
+    add_event_listener(&state.handles_panel, "submit", |event: Event| {
+        // Pressing Enter in a field would otherwise reload the page.
+        event.prevent_default();
+    });
+
+    add_event_listener(&state.handles_panel, "focusin", {
+        let state = state.clone();
+
+        move |_: Event| {
+            *state.numeric_origin.borrow_mut() = Some(state.curves.borrow().clone());
+        }
+    });
+
+    add_event_listener(&state.handles_panel, "focusout", {
+        let state = state.clone();
+
+        move |_: Event| {
+            if let Some(before) = state.numeric_origin.take() {
+                if *state.curves.borrow() != before {
+                    state.history.borrow_mut().record(before);
+                }
+            }
+        }
+    });
+
+    add_event_listener(&state.handles_panel, "input", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |event: Event| {
+            let Some(index) = state.selected()
+            else {
+                return;
+            };
+
+            let handle = match event.target().and_then(|target| {
+                target
+                    .dyn_into::<Element>()
+                    .ok()?
+                    .class_name()
+                    .strip_prefix("handle-")?
+                    .chars()
+                    .next()
+            }) {
+                Some('b') => PointHandle::B,
+                Some('c') => PointHandle::C,
+                _ => return,
+            };
+
+            let parse = |field| {
+                handle_input(&state, handle, field)
+                    .value()
+                    .parse::<f64>()
+                    .ok()
+            };
+
+            let (Some(length), Some(angle)) = (parse("length"), parse("angle"))
+            else {
+                return;
+            };
+
+            set_handle_polar(state.as_ref(), index, handle, length, angle.to_radians());
+            redraw(&canvas, &context, state.as_ref());
+        }
+    });
+
+    add_event_listener(&state.layers_panel, "dragstart", {
+        let state = state.clone();
+
+        move |event: Event| {
+            state.dragged_layer.set(layer_index(&event));
+        }
+    });
+
+    add_event_listener(&state.layers_panel, "dragover", |event: Event| {
+        // Dropping is only allowed where `dragover` is cancelled.
+        event.prevent_default();
+    });
+
+    add_event_listener(&state.layers_panel, "drop", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |event: Event| {
+            event.prevent_default();
+
+            if let (Some(from), Some(to)) = (state.dragged_layer.take(), layer_index(&event)) {
+                record_undo(state.as_ref());
+                move_curve(state.as_ref(), from, to);
+                redraw(&canvas, &context, state.as_ref());
+            }
+        }
+    });
+
     add_event_listener(&canvas, "mousedown", {
         let context = context.clone();
         let canvas = canvas.clone();
         let state = state.clone();
 
-        move |event| {
-            let mouse = Point::new(event.offset_x(), event.offset_y());
+        move |event: MouseEvent| {
+            ACTIVE_EDITOR.set(state.id);
+
+            let camera = state.camera.get();
+            let screen = Point::new(event.offset_x() as _, event.offset_y() as _);
+            let mouse = camera.to_world(screen);
             state.mouse.set(mouse);
 
-            for (index, &curve) in state.curves.borrow().iter().enumerate() {
-                if is_point_inside_circle(curve.a, POINT_RADIUS, mouse) {
-                    state
-                        .drag_state
-                        .set(Some(DragState::new(index, PointHandle::A)));
+            // The middle button pans in every mode.
+            if event.button() == 1 {
+                event.prevent_default();
+                state.pan_origin.set(Some(screen));
+                return;
+            }
 
-                    return;
+            if state.mode.get() == Mode::Preview {
+                return;
+            }
+
+            let indicator = offscreen_handle_indicators(
+                state.as_ref(),
+                canvas.client_width() as _,
+                canvas.client_height() as _,
+            )
+            .into_iter()
+            .find(|indicator| distance(indicator.position, screen) <= OFFSCREEN_INDICATOR_SIZE);
+
+            if let Some(indicator) = indicator {
+                center_on(&canvas, state.as_ref(), camera.to_world(indicator.handle));
+                redraw(&canvas, &context, state.as_ref());
+
+                return;
+            }
+
+            if state.mode.get() == Mode::InsertAnchor {
+                if let Some((index, t)) = state.anchor_preview.take() {
+                    insert_anchor(state.as_ref(), index, t);
+                    redraw(&canvas, &context, state.as_ref());
                 }
 
-                if is_point_inside_circle(curve.b, POINT_RADIUS, mouse) {
-                    state
-                        .drag_state
-                        .set(Some(DragState::new(index, PointHandle::B)));
+                return;
+            }
+
+            if state.mode.get() == Mode::Mirror {
+                let (start, _) = resolve_snap(state.as_ref(), mouse);
+
+                state.mirror_axis.set(Some((start, start)));
+                state.drawing_mirror_axis.set(true);
+                redraw(&canvas, &context, state.as_ref());
+
+                return;
+            }
+
+            if state.mode.get() == Mode::Rotate {
+                if event.alt_key() {
+                    // Here Alt places the pivot rather than holding snapping
+                    // off.
+                    state.snap_held_off.set(false);
 
+                    let (pivot, _) = resolve_snap(state.as_ref(), mouse);
+                    state.rotation_pivot.set(Some(pivot));
+                }
+                else if let Some(pivot) = rotation_pivot(state.as_ref()) {
+                    *state.drag_origin.borrow_mut() = state.curves.borrow().clone();
+                    state.rotation.set(Some(Rotation {
+                        pivot,
+                        start_angle: (mouse.y - pivot.y).atan2(mouse.x - pivot.x),
+                    }));
+                }
+
+                redraw(&canvas, &context, state.as_ref());
+
+                return;
+            }
+
+            if state.mode.get() == Mode::Eyedropper {
+                if let Some(index) = hit_test_curve(state.as_ref(), screen) {
+                    match state.picked_style.get() {
+                        Some(style) => {
+                            record_undo(state.as_ref());
+                            state.curves.borrow_mut()[index].style = style;
+                        }
+                        None => state
+                            .picked_style
+                            .set(Some(state.curves.borrow()[index].style)),
+                    }
+                }
+
+                redraw(&canvas, &context, state.as_ref());
+
+                return;
+            }
+
+            if let (Some(t), Some(index)) = (state.scrub.get(), state.selected()) {
+                let point = state.curves.borrow()[index].point_at(t);
+
+                if is_point_inside_circle(
+                    camera.to_screen(point),
+                    state.handle_style.get().radius,
+                    screen,
+                ) {
+                    state.scrubbing.set(true);
                     return;
                 }
+            }
 
-                if is_point_inside_circle(curve.c, POINT_RADIUS, mouse) {
-                    state
-                        .drag_state
-                        .set(Some(DragState::new(index, PointHandle::C)));
+            if let Some(trim) = state.trim.get() {
+                let curve = state.curves.borrow()[trim.curve_index].clone();
 
+                let grabbed = [(TrimEnd::Start, trim.start), (TrimEnd::End, trim.end)]
+                    .into_iter()
+                    .find(|&(_, t)| {
+                        is_point_inside_circle(
+                            camera.to_screen(curve.point_at(t)),
+                            state.handle_style.get().radius,
+                            screen,
+                        )
+                    });
+
+                if let Some((end, _)) = grabbed {
+                    state.trim_drag.set(Some(end));
                     return;
                 }
+            }
 
-                if is_point_inside_circle(curve.d, POINT_RADIUS, mouse) {
-                    state
-                        .drag_state
-                        .set(Some(DragState::new(index, PointHandle::D)));
+            if state.show_kinks.get() {
+                let kink = kinks(&state.curves.borrow()).into_iter().find(|&sides| {
+                    distance(kink_marker(state.as_ref(), sides), screen) <= KINK_MARKER_SIZE
+                });
+
+                if let Some(sides) = kink {
+                    smooth_kink(state.as_ref(), sides);
+                    redraw(&canvas, &context, state.as_ref());
 
                     return;
                 }
             }
 
+            // The gizmo is drawn over the curves' handles, so it is hit
+            // first.
+            let grabbed = gizmo_handles(state.as_ref())
+                .into_iter()
+                .find(|&(_, point)| {
+                    is_point_inside_circle(point, state.handle_style.get().radius, screen)
+                });
+
+            if let Some((handle, _)) = grabbed {
+                let bounds = selection_bounds(state.as_ref()).unwrap();
+                *state.drag_origin.borrow_mut() = state.curves.borrow().clone();
+
+                match handle {
+                    GizmoHandle::Scale(x, y) => state.scaling.set(Some(Scaling {
+                        sides: (x, y),
+                        bounds,
+                    })),
+                    GizmoHandle::Rotate => {
+                        let pivot = bounds.0.lerp(bounds.1, 0.5);
+
+                        state.rotation.set(Some(Rotation {
+                            pivot,
+                            start_angle: (mouse.y - pivot.y).atan2(mouse.x - pivot.x),
+                        }));
+                    }
+                }
+
+                return;
+            }
+
+            // The chain's first anchor is a handle too, but clicking it while
+            // drawing closes the chain.
+            let grabbed = handle_at(
+                &state.curves.borrow(),
+                camera,
+                screen,
+                state.handle_style.get().radius,
+            )
+            .filter(|_| !closes_chain(state.as_ref(), mouse));
+
+            if let Some((index, point)) = grabbed {
+                state.drag_state.set(Some(DragState::new(index, point)));
+                *state.drag_origin.borrow_mut() = state.curves.borrow().clone();
+
+                set_active_handle(state.as_ref(), index, point);
+
+                {
+                    let mut selection = state.selection.borrow_mut();
+
+                    if !event.shift_key() {
+                        selection.clear();
+                    }
+
+                    // Shift-clicking the primary selection deselects it;
+                    // any other curve becomes the new primary selection.
+                    let was_primary = selection.last() == Some(&index);
+                    selection.retain(|&selected| selected != index);
+
+                    if !was_primary {
+                        selection.push(index);
+                    }
+
+                    announce(
+                        state.as_ref(),
+                        format!(
+                            "curve {index} {}",
+                            if was_primary {
+                                "deselected"
+                            }
+                            else {
+                                "selected"
+                            }
+                        ),
+                    );
+                }
+
+                redraw(&canvas, &context, state.as_ref());
+
+                return;
+            }
+
+            state.snap_held_off.set(event.alt_key());
+
             match state.new_curve.get() {
+                // A curve once started is finished by any click.
+                None if !state.create_binding.get().allows(&event) => {
+                    if !event.shift_key() {
+                        state.selection.borrow_mut().clear();
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
                 None => {
+                    let (mouse, _) = resolve_snap(state.as_ref(), mouse);
                     state.new_curve.set(Some(mouse));
+                    state
+                        .continued_tangent
+                        .set(continued_tangent(state.as_ref(), mouse));
+
+                    if state.chain_curves.get() {
+                        state.pen_chain.set(Some(PenChain {
+                            start: mouse,
+                            drawn: 0,
+                        }));
+                    }
+
+                    if !event.shift_key() {
+                        state.selection.borrow_mut().clear();
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
                 }
                 Some(a) => {
                     state.new_curve.set(None);
 
-                    let ax = a.x as f64;
-                    let ay = a.y as f64;
-                    let dx = mouse.x as f64;
-                    let dy = mouse.y as f64;
+                    let curve = new_curve(state.as_ref(), a, mouse);
+                    let chain = state.pen_chain.get();
+
+                    // A whole chain is undone in one step.
+                    if chain.is_none_or(|chain| chain.drawn == 0) {
+                        record_undo(state.as_ref());
+                    }
 
-                    let adx = dx - ax;
-                    let ady = dy - ay;
-                    let abx = adx / 3.0;
-                    let aby = ady / 3.0;
-                    let bx = ax + abx;
-                    let by = ay + aby;
+                    state.curves.borrow_mut().push(curve.clone());
 
-                    let acx = adx / 3.0 * 2.0;
-                    let acy = ady / 3.0 * 2.0;
-                    let cx = ax + acx;
-                    let cy = ay + acy;
+                    let index = state.curves.borrow().len() - 1;
+                    announce(state.as_ref(), format!("curve {index} created"));
 
-                    let b = Point::new(bx as _, by as _);
-                    let c = Point::new(cx as _, cy as _);
+                    if let Some(chain) = chain {
+                        state.pen_chain.set(Some(PenChain {
+                            drawn: chain.drawn + 1,
+                            ..chain
+                        }));
 
-                    state.curves.borrow_mut().push(Curve::new(a, b, c, mouse));
+                        if curve.d == chain.start {
+                            finish_chain(state.as_ref());
+                        }
+                        else {
+                            state.new_curve.set(Some(curve.d));
+                            state.continued_tangent.set(None);
+                        }
+                    }
 
                     redraw(&canvas, &context, state.as_ref());
                 }
@@ -127,192 +846,7806 @@ fn main() {
         }
     });
 
+    add_event_listener(&window, "keyup", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |event: KeyboardEvent| {
+            if event.key() == "Alt" && state.snap_held_off.get() {
+                hold_snapping_off(state.as_ref(), false);
+                redraw(&canvas, &context, state.as_ref());
+            }
+        }
+    });
+
     add_event_listener(&canvas, "mousemove", {
         let context = context.clone();
         let canvas = canvas.clone();
         let state = state.clone();
 
-        move |event| {
-            let x = event.offset_x();
-            let y = event.offset_y();
+        move |event: MouseEvent| {
+            let screen = Point::new(event.offset_x() as _, event.offset_y() as _);
+
+            state.snap_held_off.set(event.alt_key());
+
+            if let Some(origin) = state.pan_origin.get() {
+                pan(state.as_ref(), screen.x - origin.x, screen.y - origin.y);
+                state.pan_origin.set(Some(screen));
+                redraw(&canvas, &context, state.as_ref());
+            }
+
+            let mouse = state.camera.get().to_world(screen);
+
+            state.cursor.set(Some(mouse));
+
+            if state.show_crosshair.get() {
+                redraw(&canvas, &context, state.as_ref());
+            }
+
+            if state.mode.get() == Mode::InsertAnchor {
+                state
+                    .anchor_preview
+                    .set(find_anchor_insertion(state.as_ref(), screen));
+                redraw(&canvas, &context, state.as_ref());
+            }
+
+            if let (true, Some(index)) = (state.scrubbing.get(), state.selected()) {
+                let t = state.curves.borrow()[index].nearest_t(mouse);
+
+                state.scrub.set(Some(t));
+                redraw(&canvas, &context, state.as_ref());
+            }
+
+            if let (Some(mut trim), Some(end)) = (state.trim.get(), state.trim_drag.get()) {
+                let t = state.curves.borrow()[trim.curve_index].nearest_t(mouse);
+
+                match end {
+                    TrimEnd::Start => trim.start = t.min(trim.end - MIN_TRIM_LENGTH),
+                    TrimEnd::End => trim.end = t.max(trim.start + MIN_TRIM_LENGTH),
+                }
+
+                state.trim.set(Some(trim));
+                redraw(&canvas, &context, state.as_ref());
+            }
+
+            if let (true, Some((start, _))) =
+                (state.drawing_mirror_axis.get(), state.mirror_axis.get())
+            {
+                let (end, _) = resolve_snap(state.as_ref(), mouse);
+
+                state.mirror_axis.set(Some((start, end)));
+                redraw(&canvas, &context, state.as_ref());
+            }
+
+            if let Some(rotation) = state.rotation.get() {
+                let pivot = rotation.pivot;
+                let mut angle = (mouse.y - pivot.y).atan2(mouse.x - pivot.x) - rotation.start_angle;
+
+                if event.shift_key() {
+                    let step = ROTATION_SNAP_DEGREES.to_radians();
+                    angle = (angle / step).round() * step;
+                }
+
+                rotate_selection(state.as_ref(), pivot, angle);
+                redraw(&canvas, &context, state.as_ref());
+            }
+
+            if let Some(scaling) = state.scaling.get() {
+                scale_selection(state.as_ref(), scaling, mouse, event.shift_key());
+                redraw(&canvas, &context, state.as_ref());
+            }
 
             if let Some(drag_state) = state.drag_state.get() {
-                let dx = x - state.mouse.get().x;
-                let dy = y - state.mouse.get().y;
+                let dx = mouse.x - state.mouse.get().x;
+                let dy = mouse.y - state.mouse.get().y;
 
                 {
                     let mut curves = state.curves.borrow_mut();
                     let curve = &mut curves[drag_state.curve_index];
+                    let lock = curve.locks[drag_state.point as usize];
+                    let point = curve.point_mut(drag_state.point);
 
-                    let point = match drag_state.point {
-                        PointHandle::A => &mut curve.a,
-                        PointHandle::B => &mut curve.b,
-                        PointHandle::C => &mut curve.c,
-                        PointHandle::D => &mut curve.d,
-                    };
+                    *point = lock.constrain(*point, Point::new(point.x + dx, point.y + dy));
+
+                    curve.satisfy_pin(drag_state.point);
+                    state.mouse.set(mouse);
 
-                    point.x += dx;
-                    point.y += dy;
+                    follow_across_junction(&mut curves, drag_state.curve_index, drag_state.point);
 
-                    state.mouse.set(Point::new(x, y));
+                    enforce_straight_locks(&mut curves);
                 }
 
                 state.drag_state.set(Some(drag_state));
+                state
+                    .snap_target
+                    .set(find_snap_target(state.as_ref(), drag_state));
 
                 redraw(&canvas, &context, state.as_ref());
             }
 
             if state.new_curve.get().is_some() {
-                state.mouse.set(Point::new(x, y));
+                state.mouse.set(mouse);
                 redraw(&canvas, &context, state.as_ref());
             }
         }
     });
 
-    add_event_listener(&canvas, "mouseup", {
+    add_event_listener(&canvas, "mouseleave", {
+        let context = context.clone();
+        let canvas = canvas.clone();
         let state = state.clone();
 
-        move |_| {
-            state.drag_state.set(None);
+        move |_: MouseEvent| {
+            state.cursor.set(None);
+            state.pan_origin.set(None);
+            state.anchor_preview.set(None);
+            redraw(&canvas, &context, state.as_ref());
         }
     });
-}
-
-fn redraw(canvas: &HtmlCanvasElement, context: &CanvasRenderingContext2d, state: &State) {
-    context.clear_rect(
-        0.0,
-        0.0,
-        canvas.client_width() as _,
-        canvas.client_height() as _,
-    );
 
-    for &curve in state.curves.borrow().iter() {
-        draw_curve(context, curve);
-    }
+    add_event_listener(&canvas, "mouseup", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |_: MouseEvent| {
+            state.trim_drag.set(None);
+            state.pan_origin.set(None);
+            state.scrubbing.set(false);
+
+            if state.drawing_mirror_axis.replace(false) {
+                // A click without a drag gives no direction, so the axis
+                // goes back to the default.
+                if let Some((start, end)) = state.mirror_axis.get() {
+                    if start == end {
+                        state
+                            .mirror_axis
+                            .set(default_mirror_axis(state.as_ref(), false));
+                    }
+                }
+
+                redraw(&canvas, &context, state.as_ref());
+                return;
+            }
+
+            if state.rotation.take().is_some() || state.scaling.take().is_some() {
+                finish_transform(state.as_ref());
+                redraw(&canvas, &context, state.as_ref());
+                return;
+            }
+
+            let Some(mut drag_state) = state.drag_state.take()
+            else {
+                return;
+            };
+
+            // The dragged point follows the cursor freely and only lands on
+            // the snap target here, so pulling away before release escapes it.
+            if let Some(target) = state.snap_target.take() {
+                let mut curves = state.curves.borrow_mut();
+                let curve = &mut curves[drag_state.curve_index];
+
+                let lock = curve.locks[drag_state.point as usize];
+                let point = curve.point_mut(drag_state.point);
+
+                *point = lock.constrain(*point, target.point);
+
+                if let Some((handle, point)) =
+                    target.aligned_handle(curve.clone(), drag_state.point)
+                {
+                    *curve.point_mut(handle) = point;
+                }
+
+                curve.satisfy_pin(drag_state.point);
+
+                enforce_straight_locks(&mut curves);
+                drop(curves);
+
+                if let SnapKind::Curve { curve_index, t } = target.kind {
+                    if state.snap.get().split_curves {
+                        split_at_junction(state.as_ref(), curve_index, t, &mut drag_state);
+                    }
+                }
+            }
+
+            // The whole drag is undone in one step, and a click that moved
+            // nothing leaves no history behind.
+            let before = state.drag_origin.take();
+
+            if *state.curves.borrow() != before {
+                state.history.borrow_mut().record(before);
+
+                let point = state.curves.borrow()[drag_state.curve_index].point(drag_state.point);
+                let precision = state.export_precision.get();
+
+                announce(
+                    state.as_ref(),
+                    format!(
+                        "{} moved to ({}, {})",
+                        match drag_state.point {
+                            PointHandle::A | PointHandle::D => "anchor",
+                            _ => "handle",
+                        },
+                        format_coordinate(point.x, precision),
+                        format_coordinate(point.y, precision)
+                    ),
+                );
+            }
+
+            // Also clears the ghost of the curve's pre-drag position.
+            redraw(&canvas, &context, state.as_ref());
+        }
+    });
+
+    add_event_listener(&window, "keydown", {
+        let context = context.clone();
+        let canvas = canvas.clone();
+        let state = state.clone();
+
+        move |event: KeyboardEvent| {
+            // Leave keystrokes alone while the user is typing somewhere, or
+            // when they are meant for another editor.
+            if is_editing_text() || ACTIVE_EDITOR.get() != state.id {
+                return;
+            }
+
+            match event.key().as_str() {
+                "?" => {
+                    state.help_panel.set_hidden(!state.help_panel.hidden());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "Alt" => {
+                    // Keeps the browser from moving focus to its menu bar.
+                    event.prevent_default();
+                    hold_snapping_off(state.as_ref(), true);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "!" => {
+                    let mut snap = state.snap.get();
+                    snap.enabled = !snap.enabled;
+                    state.snap.set(snap);
+
+                    if let Some(drag_state) = state.drag_state.get() {
+                        state
+                            .snap_target
+                            .set(find_snap_target(state.as_ref(), drag_state));
+                    }
+
+                    announce(
+                        state.as_ref(),
+                        format!("snapping {}", if snap.enabled { "on" } else { "off" }),
+                    );
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "t" => {
+                    let show = !state.show_tangent_extensions.get();
+                    state.show_tangent_extensions.set(show);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "g" => {
+                    let geojson = geojson::export_geojson(state.as_ref(), FLATTEN_TOLERANCE);
+                    download_text("scene.geojson", "application/geo+json", &geojson);
+                }
+                "v" if event.ctrl_key() || event.meta_key() => {
+                    event.prevent_default();
+                    paste(canvas.clone(), state.clone());
+                }
+                "v" => {
+                    let width = canvas.client_width() as f64;
+                    let height = canvas.client_height() as f64;
+                    let view_box = (Point::new(0.0, 0.0), Point::new(width, height));
+                    let svg = svg::export_svg(state.as_ref(), width, height, view_box);
+
+                    download_text("scene.svg", "image/svg+xml", &svg);
+                }
+                "V" => {
+                    export_fitted_svg(state.as_ref());
+                }
+                "p" => {
+                    export_path_frames(state.as_ref());
+                }
+                "H" => {
+                    share_scene(state.as_ref(), false);
+                }
+                "*" => {
+                    share_scene(state.as_ref(), true);
+                }
+                "P" => {
+                    export_png(state.as_ref());
+                }
+                "o" => {
+                    import_input.click();
+                }
+                ";" => {
+                    let binding = state.create_binding.get().next();
+
+                    state.create_binding.set(binding);
+                    announce(state.as_ref(), binding.describe().to_string());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "q" => {
+                    state.new_curve_kind.set(match state.new_curve_kind.get() {
+                        CurveKind::Cubic => CurveKind::Quadratic,
+                        CurveKind::Quadratic => CurveKind::Cubic,
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "C" if event.ctrl_key() || event.meta_key() => {
+                    event.prevent_default();
+                    copy_polylines(state.clone());
+                }
+                "z" | "Z" if event.ctrl_key() || event.meta_key() => {
+                    event.prevent_default();
+                    // Undoing part of a chain leaves nothing to continue.
+                    finish_chain(state.as_ref());
+
+                    if event.shift_key() {
+                        redo(state.as_ref());
+                    }
+                    else {
+                        undo(state.as_ref());
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "i" | "I" if event.ctrl_key() || event.meta_key() => {
+                    event.prevent_default();
+                    invert_selection(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "y" if event.ctrl_key() => {
+                    event.prevent_default();
+                    finish_chain(state.as_ref());
+                    redo(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "Q" => {
+                    let rational = transaction(state.as_ref(), || {
+                        let mut rational = 0;
+
+                        for &index in state.selection.borrow().iter() {
+                            let mut curves = state.curves.borrow_mut();
+
+                            curves[index] = match (curves[index].kind, curves[index].weights) {
+                                // No quadratic approximates a rational cubic
+                                // well, so those are left alone.
+                                (CurveKind::Cubic, Some(_)) => {
+                                    rational += 1;
+                                    continue;
+                                }
+                                (CurveKind::Cubic, None) => curves[index].to_quadratic(),
+                                (CurveKind::Quadratic, _) => curves[index].to_cubic(),
+                            };
+                        }
+
+                        rational
+                    });
+
+                    if rational > 0 {
+                        announce(
+                            state.as_ref(),
+                            format!(
+                                "{rational} rational curve{} left cubic",
+                                if rational == 1 { "" } else { "s" }
+                            ),
+                        );
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "f" => {
+                    fit_to_view(&canvas, state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "0" => {
+                    move_camera(state.as_ref(), Camera::default());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "c" => {
+                    recenter_on_selected(&canvas, state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "C" => {
+                    close_path(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "O" => {
+                    select_open_paths(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "s" | "S" | "e" => {
+                    let similarity = match event.key().as_str() {
+                        "s" => Similarity::Color,
+                        "S" => Similarity::Width,
+                        _ => Similarity::Closed,
+                    };
+
+                    select_similar(state.as_ref(), similarity);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "1" | "2" | "3" | "4" | "5" | "6" => {
+                    let color = PALETTE[event.key().parse::<usize>().unwrap() - 1];
+
+                    transaction(state.as_ref(), || {
+                        for &index in state.selection.borrow().iter() {
+                            state.curves.borrow_mut()[index].style.color = color;
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "w" => {
+                    transaction(state.as_ref(), || {
+                        for &index in state.selection.borrow().iter() {
+                            let style = &mut state.curves.borrow_mut()[index].style;
+                            let next = STROKE_WIDTHS
+                                .iter()
+                                .position(|&width| width == style.width)
+                                .map_or(0, |position| (position + 1) % STROKE_WIDTHS.len());
+
+                            style.width = STROKE_WIDTHS[next];
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "G" => {
+                    transaction(state.as_ref(), || {
+                        for &index in state.selection.borrow().iter() {
+                            let style = &mut state.curves.borrow_mut()[index].style;
+                            style.color_scale = ColorScale::next(style.color_scale);
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "^" => {
+                    transaction(state.as_ref(), || {
+                        let paths = selected_paths(state.as_ref());
+                        let mut curves = state.curves.borrow_mut();
+
+                        if let Some(&first) = paths.first() {
+                            let join = curves[first].style.join.next();
+
+                            for index in paths {
+                                curves[index].style.join = join;
+                            }
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "\"" => {
+                    set_weights(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "&" => {
+                    set_miter_limit(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "[" | "]" => {
+                    transaction(state.as_ref(), || {
+                        for &index in state.selection.borrow().iter() {
+                            let style = &mut state.curves.borrow_mut()[index].style;
+                            let cap = match event.key().as_str() {
+                                "[" => &mut style.start_cap,
+                                _ => &mut style.end_cap,
+                            };
+
+                            *cap = cap.next();
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "<" => {
+                    let mode = match state.mode.get() {
+                        Mode::Mirror => Mode::Edit,
+                        _ => Mode::Mirror,
+                    };
+
+                    state
+                        .mirror_axis
+                        .set(default_mirror_axis(state.as_ref(), false));
+                    set_mode(&canvas, state.as_ref(), mode);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                " " if state.mode.get() == Mode::Mirror => {
+                    event.prevent_default();
+
+                    // Vertical axes are flipped to horizontal ones, and any
+                    // other axis to vertical.
+                    let vertical = state
+                        .mirror_axis
+                        .get()
+                        .is_some_and(|(start, end)| start.x == end.x);
+
+                    state
+                        .mirror_axis
+                        .set(default_mirror_axis(state.as_ref(), vertical));
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "Enter" if state.pen_chain.get().is_some() => {
+                    finish_chain(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "Enter" if state.mode.get() == Mode::Mirror => {
+                    if let Some(axis) = state.mirror_axis.get() {
+                        mirror_selection(state.as_ref(), axis);
+                    }
+
+                    set_mode(&canvas, state.as_ref(), Mode::Edit);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "Z" => {
+                    let mode = match state.mode.get() {
+                        Mode::Rotate => Mode::Edit,
+                        _ => Mode::Rotate,
+                    };
+
+                    state.rotation_pivot.set(None);
+                    set_mode(&canvas, state.as_ref(), mode);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "N" => {
+                    let mode = match state.mode.get() {
+                        Mode::InsertAnchor => Mode::Edit,
+                        _ => Mode::InsertAnchor,
+                    };
+
+                    state.anchor_preview.set(None);
+                    set_mode(&canvas, state.as_ref(), mode);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "E" => {
+                    let mode = match state.mode.get() {
+                        Mode::Preview => Mode::Edit,
+                        _ => Mode::Preview,
+                    };
+
+                    finish_chain(state.as_ref());
+                    state.new_curve.set(None);
+                    state.trim.set(None);
+                    set_mode(&canvas, state.as_ref(), mode);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "i" => {
+                    let picked = state
+                        .selected()
+                        .map(|index| state.curves.borrow()[index].style);
+
+                    state.picked_style.set(picked);
+                    set_mode(&canvas, state.as_ref(), Mode::Eyedropper);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                // Shows trim handles on the primary selection, or commits the
+                // trim if they are already shown.
+                "T" | "Enter" => {
+                    match state.trim.take() {
+                        Some(trim) => {
+                            record_undo(state.as_ref());
+
+                            let curve = &mut state.curves.borrow_mut()[trim.curve_index];
+                            *curve = curve.clone().trim(trim.start, trim.end);
+                        }
+                        None if event.key() == "T" => {
+                            state.trim.set(state.selected().map(Trim::new));
+                        }
+                        None => {}
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "Escape" => {
+                    cancel_chain(state.as_ref());
+                    state.trim.set(None);
+                    set_mode(&canvas, state.as_ref(), Mode::Edit);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "#" => {
+                    let mut snap = state.snap.get();
+                    snap.grid = snap.grid.next();
+                    state.snap.set(snap);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "{" => {
+                    state.show_grid_labels.set(!state.show_grid_labels.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "9" => {
+                    state.show_point_table.set(!state.show_point_table.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "n" => {
+                    if let Some(index) = state.selected() {
+                        record_undo(state.as_ref());
+
+                        let curve = &mut state.curves.borrow_mut()[index];
+
+                        curve.pin = match curve.pin {
+                            Some(_) => None,
+                            None => Some(Pin {
+                                t: 0.5,
+                                point: curve.point_at(0.5),
+                            }),
+                        };
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "b" => {
+                    balance_junction_handles(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "d" => {
+                    state.dash_speed.set(match state.dash_speed.get() {
+                        0.0 => DASH_SPEED,
+                        _ => 0.0,
+                    });
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                // Locks the selected curves straight, or unlocks them if the
+                // primary selection already is.
+                "J" => {
+                    if let Some(selected) = state.selected() {
+                        let locked = !state.curves.borrow()[selected].locked_straight;
+
+                        transaction(state.as_ref(), || {
+                            for &index in state.selection.borrow().iter() {
+                                state.curves.borrow_mut()[index].locked_straight = locked;
+                            }
+                        });
+
+                        redraw(&canvas, &context, state.as_ref());
+                    }
+                }
+                "@" => {
+                    let scrub = match state.scrub.get() {
+                        Some(_) => None,
+                        None => Some(0.5),
+                    };
+
+                    state.scrub.set(scrub);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "|" => {
+                    straighten_nearly_straight(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "W" => {
+                    let shadow = match state.shadow.get() {
+                        Some(_) => None,
+                        None => Some(Shadow::default()),
+                    };
+
+                    state.shadow.set(shadow);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "D" => {
+                    duplicate_selection(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "K" => {
+                    weld_anchors(state.as_ref(), usize::MAX);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "%" => {
+                    join_selection(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "F" => {
+                    weld_anchors(state.as_ref(), 1);
+                    recenter_on_selected(&canvas, state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "B" => {
+                    break_path(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                // `r` flips each selected curve on its own; `R` flips every
+                // curve of the paths they belong to, so the paths stay
+                // continuous.
+                "r" | "R" => {
+                    let mut indices = state.selection.borrow().clone();
+
+                    if event.key() == "R" {
+                        let curves = state.curves.borrow();
+
+                        indices = indices
+                            .iter()
+                            .flat_map(|&index| connected_path(&curves, index))
+                            .collect();
+                        indices.sort_unstable();
+                        indices.dedup();
+                    }
+
+                    transaction(state.as_ref(), || {
+                        let mut curves = state.curves.borrow_mut();
+
+                        for &index in &indices {
+                            curves[index] = curves[index].clone().reversed();
+                        }
+                    });
+
+                    // Dimensions stay on the same points, which swapped ends.
+                    dimension::remap_dimensions(state.as_ref(), |index, handle| {
+                        if !indices.contains(&index) {
+                            Some((index, handle))
+                        }
+                        else if handle == PointHandle::A {
+                            Some((index, PointHandle::D))
+                        }
+                        else {
+                            Some((index, PointHandle::A))
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "ArrowLeft" | "ArrowRight" | "ArrowUp" | "ArrowDown" => {
+                    let (x, y) = match event.key().as_str() {
+                        "ArrowLeft" => (-1.0, 0.0),
+                        "ArrowRight" => (1.0, 0.0),
+                        "ArrowUp" => (0.0, -1.0),
+                        _ => (0.0, 1.0),
+                    };
+
+                    if state.active_handle.get().is_some() {
+                        event.prevent_default();
+                        nudge_handle(state.as_ref(), x, y, event.shift_key());
+                        redraw(&canvas, &context, state.as_ref());
+                    }
+                }
+                "." | "," => {
+                    let step = if event.key() == "." { 1 } else { -1 };
+
+                    if step_active_handle(state.as_ref(), step) {
+                        redraw(&canvas, &context, state.as_ref());
+                    }
+                }
+                "(" => {
+                    choose_easing(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "/" => {
+                    split_evenly(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "8" => {
+                    redistribute_anchors(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "~" => {
+                    state.show_influence.set(!state.show_influence.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "`" => {
+                    state
+                        .show_handle_labels
+                        .set(!state.show_handle_labels.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "k" => {
+                    let show = !state.show_osculating_circle.get();
+                    state.show_osculating_circle.set(show);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "I" => {
+                    split_at_intersections(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "m" => {
+                    smooth_paths(state.as_ref());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "a" => {
+                    if let Some((index, anchor)) = state.active_anchor.get() {
+                        record_undo(state.as_ref());
+
+                        let mut curves = state.curves.borrow_mut();
+                        let anchor_type = curves[index].anchor_type(anchor).next();
+
+                        *curves[index].anchor_type_mut(anchor) = anchor_type;
+
+                        // Both sides of a junction share its type.
+                        if let Some((other, other_anchor)) =
+                            junction_partner(&curves, index, anchor)
+                        {
+                            *curves[other].anchor_type_mut(other_anchor) = anchor_type;
+                        }
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "y" => {
+                    if align_tangents(state.as_ref()) {
+                        redraw(&canvas, &context, state.as_ref());
+                    }
+                }
+                // `=` is `+` without Shift on most layouts.
+                "+" | "=" | "-" => {
+                    let factor = match event.key().as_str() {
+                        "-" => 1.0 / TENSION_STEP,
+                        _ => TENSION_STEP,
+                    };
+
+                    transaction(state.as_ref(), || {
+                        for &index in state.selection.borrow().iter() {
+                            let curve = &mut state.curves.borrow_mut()[index];
+                            *curve = curve.clone().with_tension(factor);
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                ":" => {
+                    if let Some(index) = state.selected() {
+                        transaction(state.as_ref(), || {
+                            let curve = &mut state.curves.borrow_mut()[index];
+                            *curve = curve.clone().symmetric();
+                        });
+
+                        redraw(&canvas, &context, state.as_ref());
+                    }
+                }
+                // Handle appearance: `u` cycles anchor shapes, `U` switches
+                // between solid and hollow dots, `L` between solid and dashed
+                // handle lines.
+                "u" | "U" | "L" | "_" => {
+                    let mut handle_style = state.handle_style.get();
+
+                    match event.key().as_str() {
+                        "u" => handle_style.anchor_shape = handle_style.anchor_shape.next(),
+                        "U" => handle_style.filled = !handle_style.filled,
+                        "_" => handle_style.connected_polygon = !handle_style.connected_polygon,
+                        _ => handle_style.dashed_lines = !handle_style.dashed_lines,
+                    }
+
+                    state.handle_style.set(handle_style);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "A" => {
+                    let high_contrast = !state.high_contrast.get();
+
+                    state.high_contrast.set(high_contrast);
+                    state.handle_style.set(if high_contrast {
+                        HandleStyle::high_contrast()
+                    }
+                    else {
+                        HandleStyle::default()
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "M" => {
+                    state.show_rulers.set(!state.show_rulers.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                // Cycles the last grabbed handle through moving freely, only
+                // vertically and only horizontally.
+                "j" => {
+                    if let Some((index, handle)) = state.active_handle.get() {
+                        transaction(state.as_ref(), || {
+                            let lock = &mut state.curves.borrow_mut()[index].locks[handle as usize];
+                            *lock = lock.next();
+                        });
+                    }
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "l" => {
+                    let mut snap = state.snap.get();
+                    snap.length = snap.length.next();
+                    state.snap.set(snap);
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "X" => {
+                    if dimension::toggle_dimension(state.as_ref()) {
+                        redraw(&canvas, &context, state.as_ref());
+                    }
+                }
+                "Y" => {
+                    let mut snap = state.snap.get();
+                    snap.split_curves = !snap.split_curves;
+                    state.snap.set(snap);
+                }
+                "h" => {
+                    state.show_crosshair.set(!state.show_crosshair.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "'" => {
+                    state.show_page.set(!state.show_page.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                ">" => {
+                    finish_chain(state.as_ref());
+                    state.chain_curves.set(!state.chain_curves.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "7" => {
+                    state.normalize_winding.set(!state.normalize_winding.get());
+                    announce(
+                        state.as_ref(),
+                        format!(
+                            "imported winding {}",
+                            if state.normalize_winding.get() {
+                                "normalized"
+                            }
+                            else {
+                                "kept"
+                            }
+                        ),
+                    );
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                ")" => {
+                    transaction(state.as_ref(), || {
+                        for &index in state.selection.borrow().iter() {
+                            let style = &mut state.curves.borrow_mut()[index].style;
+                            style.align = style.align.next();
+                        }
+                    });
+
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "}" => {
+                    state.show_kinks.set(!state.show_kinks.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "\\" => {
+                    state.relative_nudge.set(!state.relative_nudge.get());
+                    announce(
+                        state.as_ref(),
+                        if state.relative_nudge.get() {
+                            "arrows scale handles"
+                        }
+                        else {
+                            "arrows move handles"
+                        }
+                        .to_string(),
+                    );
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "$" => {
+                    state.show_gizmo.set(!state.show_gizmo.get());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "x" => {
+                    remove_curves(state.as_ref(), |_, curve| curve.is_degenerate());
+                    redraw(&canvas, &context, state.as_ref());
+                }
+                "Backspace" => {
+                    // Drops the newest curve while drawing, independently of
+                    // what is selected.
+                    event.prevent_default();
+
+                    let count = state.curves.borrow().len();
+
+                    if count > 0 {
+                        remove_curves(state.as_ref(), |index, _| index == count - 1);
+                        redraw(&canvas, &context, state.as_ref());
+                    }
+                }
+                _ => {}
+            }
+        }
+    });
+
+    state
+}
+
+/// Whether keyboard focus is in a text field.
+fn is_editing_text() -> bool {
+    let Some(element) = window().unwrap().document().unwrap().active_element()
+    else {
+        return false;
+    };
+
+    matches!(element.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+        || element
+            .dyn_into::<HtmlElement>()
+            .is_ok_and(|element| element.is_content_editable())
+}
+
+fn redraw(canvas: &HtmlCanvasElement, context: &CanvasRenderingContext2d, state: &State) {
+    // Everything is drawn in screen pixels, with the camera applied to the
+    // points rather than the context, so start from the identity whatever
+    // earlier drawing left behind, and leave the context as it was found.
+    #[cfg(debug_assertions)]
+    let transform_before = transform_of(context);
+
+    context.save();
+    context.set_transform(1.0, 0.0, 0.0, 1.0, 0.0, 0.0).unwrap();
+
+    context.clear_rect(
+        0.0,
+        0.0,
+        canvas.client_width() as _,
+        canvas.client_height() as _,
+    );
+
+    let camera = state.camera.get();
+    let preview = state.mode.get() == Mode::Preview;
+
+    if !preview {
+        draw_grid(canvas, context, state);
+
+        if state.show_grid_labels.get() {
+            draw_grid_labels(canvas, context, state);
+        }
+
+        if state.show_page.get() {
+            draw_page(canvas, context, camera);
+        }
+    }
+
+    if let Some(drag_state) = state.drag_state.get() {
+        let original = state.drag_origin.borrow()[drag_state.curve_index].clone();
+
+        if state.curves.borrow()[drag_state.curve_index] != original {
+            // Where the dragged curve started, faintly.
+            stroke_curve(context, camera.curve_to_screen(original), GHOST_OPACITY);
+        }
+    }
+
+    render_scene(
+        context,
+        state,
+        RenderOptions {
+            camera,
+            width: canvas.client_width() as _,
+            height: canvas.client_height() as _,
+            background: None,
+            dash_offset: state.dash_offset.get(),
+            handles: (!preview).then(|| state.handle_style.get()),
+            shadow: state.shadow.get(),
+            stroke_scale: if state.high_contrast.get() && !preview {
+                HIGH_CONTRAST_STROKE_SCALE
+            }
+            else {
+                1.0
+            },
+            budgeted: true,
+        },
+    );
+
+    dimension::draw_dimensions(context, state, camera);
+
+    if !preview {
+        draw_editing_aids(canvas, context, state);
+    }
+
+    context.restore();
+
+    #[cfg(debug_assertions)]
+    assert_eq!(
+        transform_of(context),
+        transform_before,
+        "redraw left the context transformed"
+    );
+
+    update_layers_panel(state);
+    update_handles_panel(state);
+    help::update_help_panel(state);
+
+    if are_dashes_marching(state) {
+        request_animation_frame(state);
+    }
+}
+
+/// The context's current transform as `[a, b, c, d, e, f]`, to check that
+/// drawing leaves it as it was.
+#[cfg(debug_assertions)]
+fn transform_of(context: &CanvasRenderingContext2d) -> Option<[f64; 6]> {
+    let matrix = context.get_transform().ok()?;
+
+    Some([
+        matrix.a(),
+        matrix.b(),
+        matrix.c(),
+        matrix.d(),
+        matrix.e(),
+        matrix.f(),
+    ])
+}
+
+/// Draws what helps with editing over the curves: guides, overlays, the
+/// curve being created, the status line, rulers, and the crosshair.
+fn draw_editing_aids(
+    canvas: &HtmlCanvasElement,
+    context: &CanvasRenderingContext2d,
+    state: &State,
+) {
+    let camera = state.camera.get();
+
+    if state.show_tangent_extensions.get() {
+        if let Some(index) = state.selected() {
+            let curve = camera.curve_to_screen(state.curves.borrow()[index].clone());
+
+            match state.scrub.get() {
+                Some(t) => draw_tangent_and_normal(context, curve, t),
+                None => draw_tangent_extensions(context, curve),
+            }
+        }
+    }
+
+    let handle_style = state.handle_style.get();
+
+    for indicator in offscreen_handle_indicators(
+        state,
+        canvas.client_width() as _,
+        canvas.client_height() as _,
+    ) {
+        let color = match indicator.kind {
+            PointHandle::A | PointHandle::D => handle_style.anchor_color,
+            _ => handle_style.control_color,
+        };
+
+        draw_offscreen_indicator(context, indicator, color);
+    }
+
+    if let Some((index, t)) = state.anchor_preview.get() {
+        let point = camera.to_screen(state.curves.borrow()[index].point_at(t));
+
+        context.begin_path();
+        trace_dot(context, point, handle_style.radius, DotShape::Circle);
+        paint_dots(context, handle_style.anchor_color, handle_style.filled);
+    }
+
+    if let Some((index, handle)) = state.active_handle.get() {
+        let point = camera.to_screen(state.curves.borrow()[index].point(handle));
+
+        context.set_stroke_style(&JsString::from(SELECTION_COLOR));
+        context.set_line_width(2.0);
+        context.begin_path();
+        trace_dot(context, point, handle_style.radius + 3.0, DotShape::Circle);
+        context.stroke();
+        context.set_line_width(1.0);
+    }
+
+    if state.mode.get() == Mode::Rotate {
+        if let Some(pivot) = rotation_pivot(state) {
+            draw_pivot(context, camera.to_screen(pivot));
+        }
+    }
+
+    draw_gizmo(context, state);
+
+    if let (Mode::Mirror, Some(axis)) = (state.mode.get(), state.mirror_axis.get()) {
+        draw_mirror_axis(canvas, context, state, axis);
+    }
+
+    if let Some(trim) = state.trim.get() {
+        draw_trim(
+            context,
+            camera,
+            state.curves.borrow()[trim.curve_index].clone(),
+            trim,
+        );
+    }
+
+    if let Some(preview) = state.dot_preview.get() {
+        let path: Vec<Curve> = {
+            let curves = state.curves.borrow();
+
+            state
+                .selection
+                .borrow()
+                .iter()
+                .map(|&index| curves[index].clone())
+                .collect()
+        };
+
+        if !path.is_empty() {
+            let point = frames::eased_point(&path, state.easing.get(), preview.progress);
+            frames::draw_dot(context, camera.to_screen(point));
+        }
+    }
+
+    if state.show_kinks.get() {
+        draw_kinks(context, state);
+    }
+
+    if state.show_point_table.get() {
+        if let Some(index) = state.selected() {
+            draw_point_table(canvas, context, state, state.curves.borrow()[index].clone());
+        }
+    }
+
+    if state.show_influence.get() {
+        if let Some(index) = state.selected() {
+            draw_influence(
+                context,
+                camera.curve_to_screen(state.curves.borrow()[index].clone()),
+                handle_style,
+            );
+        }
+    }
+
+    if state.show_osculating_circle.get() {
+        if let Some(index) = state.selected() {
+            let t = state.scrub.get().unwrap_or(0.5);
+            draw_osculating_circle(context, camera, state.curves.borrow()[index].clone(), t);
+        }
+    }
+
+    if let (Some(t), Some(index)) = (state.scrub.get(), state.selected()) {
+        let point = state.curves.borrow()[index].point_at(t);
+        draw_scrub_marker(context, camera, point, t, state.handle_style.get().radius);
+    }
+
+    if let Some(target) = state.snap_target.get() {
+        let point = camera.to_screen(target.point);
+
+        context.set_stroke_style(&JsString::from(SNAP_COLOR));
+        context.set_line_width(2.0);
+        context.begin_path();
+
+        // Circled anchors, diamonds on curve bodies, boxed grid points.
+        let shape = match target.kind {
+            SnapKind::Grid => DotShape::Square,
+            SnapKind::Curve { .. } => DotShape::Diamond,
+            _ => DotShape::Circle,
+        };
+
+        trace_dot(context, point, SNAP_INDICATOR_RADIUS, shape);
+        context.stroke();
+
+        // The tangent the dragged curve will be aligned to on release.
+        if let Some((x, y)) = target.direction {
+            context.set_line_width(1.0);
+            context.begin_path();
+            context.move_to(
+                point.x - x * TANGENT_EXTENSION_LENGTH,
+                point.y - y * TANGENT_EXTENSION_LENGTH,
+            );
+            context.line_to(
+                point.x + x * TANGENT_EXTENSION_LENGTH,
+                point.y + y * TANGENT_EXTENSION_LENGTH,
+            );
+            context.stroke();
+        }
+
+        context.set_line_width(1.0);
+    }
+
+    if let Some(a) = state.new_curve.get() {
+        // A ring around the chain's first anchor says a click closes it.
+        if let (true, Some(chain)) = (
+            closes_chain(state, state.mouse.get()),
+            state.pen_chain.get(),
+        ) {
+            let start = camera.to_screen(chain.start);
+
+            context.set_stroke_style(&JsString::from(SNAP_COLOR));
+            context.set_line_width(2.0);
+            context.begin_path();
+            context
+                .arc(
+                    start.x,
+                    start.y,
+                    state.handle_style.get().radius * 2.0,
+                    0.0,
+                    2.0 * PI,
+                )
+                .unwrap();
+            context.stroke();
+            context.set_line_width(1.0);
+        }
+
+        let curve = new_curve(state, a, state.mouse.get());
+        let length = distance(a, curve.d);
+        let end = camera.to_screen(curve.d);
+
+        context.set_stroke_style(&JsString::from("black"));
+        context.begin_path();
+        trace_path(context, &[camera.curve_to_screen(curve)]);
+        context.stroke();
+
+        context.set_font(STATUS_FONT);
+        context.set_fill_style(&JsString::from("black"));
+        context
+            .fill_text(
+                &format!("{length:.1}"),
+                end.x + CROSSHAIR_LABEL_OFFSET,
+                end.y + CROSSHAIR_LABEL_OFFSET + 12.0,
+            )
+            .unwrap();
+    }
+
+    draw_status_line(canvas, context, state);
+
+    if state.show_rulers.get() {
+        draw_rulers(canvas, context, camera);
+    }
+
+    if state.show_crosshair.get() {
+        if let Some(cursor) = state.cursor.get() {
+            draw_crosshair(canvas, context, camera, cursor);
+        }
+    }
+}
+
+/// Draws the curves as `options` describes onto `context`, which may belong to
+/// any canvas, on screen or off. Selection halos go under all the strokes and
+/// handles over them, so no curve hides another's handles.
+fn render_scene(context: &CanvasRenderingContext2d, state: &State, options: RenderOptions) {
+    let RenderOptions {
+        camera,
+        width,
+        height,
+        ..
+    } = options;
+
+    if let Some(background) = options.background {
+        context.set_fill_style(&JsString::from(background));
+        context.fill_rect(0.0, 0.0, width, height);
+    }
+
+    let selection = state.selection.borrow();
+    let handle_radius = options.handles.map_or(0.0, |style| style.radius);
+
+    let curves: Vec<Curve> = state
+        .curves
+        .borrow()
+        .iter()
+        .map(|curve| {
+            let mut curve = camera.curve_to_screen(curve.clone());
+            curve.style.width *= options.stroke_scale;
+            curve
+        })
+        .collect();
+
+    // Skipping off-screen curves keeps large scenes responsive.
+    let visible: Vec<bool> = curves
+        .iter()
+        .map(|curve| is_on_screen(curve, width, height, handle_radius))
+        .collect();
+
+    if options.handles.is_some() {
+        for &index in selection.iter().filter(|&&index| visible[index]) {
+            draw_selection_halo(context, curves[index].clone());
+        }
+    }
+
+    // Curves following each other in a path are stroked together where they
+    // look alike, so the corners between them get joins.
+    let mut strokes = Vec::new();
+    let mut caps = Vec::new();
+    let subpaths = cached_subpaths(state);
+    // Only aligned strokes need to know their inside, which takes
+    // flattening whole chains.
+    let left_inside = if curves
+        .iter()
+        .any(|curve| curve.style.align != StrokeAlign::Center)
+    {
+        left_inside(&state.curves.borrow(), &subpaths)
+    }
+    else {
+        Vec::new()
+    };
+
+    for subpath in subpaths {
+        if subpath
+            .curves
+            .iter()
+            .any(|&index| curves[index].style.has_caps())
+        {
+            caps.extend(topology::loose_ends(
+                &state.curves.borrow(),
+                &subpath.curves,
+            ));
+        }
+
+        for chain in topology::chains(&state.curves.borrow(), &subpath.curves) {
+            if !chain.curves.iter().any(|&(index, _)| visible[index]) {
+                continue;
+            }
+
+            let mut runs: Vec<Vec<Curve>> = Vec::new();
+
+            for &(index, reversed) in &chain.curves {
+                let curve = curves[index].clone();
+
+                match runs.last_mut() {
+                    Some(run)
+                        if run[0].style == curve.style && curve.style.color_scale.is_none() =>
+                    {
+                        run.push(curve.reversed_if(reversed));
+                    }
+                    // Colors along a curve follow its own direction.
+                    _ if curve.style.color_scale.is_some() => runs.push(vec![curve]),
+                    _ => runs.push(vec![curve.reversed_if(reversed)]),
+                }
+            }
+
+            let closed = chain.closed && runs.len() == 1;
+            let (first, reversed) = chain.curves[0];
+            let chain_left_inside = left_inside.get(first).is_none_or(|&left| left != reversed);
+
+            strokes.extend(runs.into_iter().map(|curves| Stroke {
+                curves,
+                closed,
+                left_inside: chain_left_inside,
+            }));
+        }
+    }
+
+    if options.budgeted {
+        stroke_in_layer(context, state, strokes, options);
+    }
+    else {
+        stroke_all(context, &strokes, options);
+    }
+
+    for (index, end) in caps {
+        if visible[index] {
+            draw_cap(context, curves[index].clone(), end);
+        }
+    }
+
+    let Some(handle_style) = options.handles
+    else {
+        return;
+    };
+
+    for (index, curve) in curves.iter().enumerate() {
+        if visible[index] {
+            draw_handles(context, curve.clone(), handle_style);
+        }
+    }
+
+    if state.show_handle_labels.get() {
+        for (index, curve) in curves.iter().enumerate() {
+            if visible[index] {
+                draw_handle_labels(context, index, curve.clone(), handle_style.radius);
+            }
+        }
+    }
+
+    // Arrowheads show which way selected curves run.
+    for &index in selection.iter() {
+        draw_arrowhead(
+            context,
+            camera.curve_to_screen(state.curves.borrow()[index].clone()),
+        );
+    }
+
+    for curve in state.curves.borrow().iter() {
+        if let Some(pin) = curve.pin {
+            draw_pin(context, camera.to_screen(pin.point));
+        }
+    }
+}
+
+/// Strokes `strokes` with the dashes and shadow `options` asks for.
+fn stroke_all(context: &CanvasRenderingContext2d, strokes: &[Stroke], options: RenderOptions) {
+    context.set_line_dash_offset(options.dash_offset);
+    set_shadow(context, options.shadow);
+
+    for stroke in strokes {
+        stroke_path(
+            context,
+            &stroke.curves,
+            stroke.closed,
+            stroke.left_inside,
+            1.0,
+        );
+    }
+
+    set_shadow(context, None);
+    context.set_line_dash_offset(0.0);
+}
+
+/// Strokes into the scene layer for up to `FRAME_BUDGET`, carrying on from
+/// where the last frame left off unless what is to be drawn has changed,
+/// then copies the layer onto `context`. Another frame is requested while
+/// strokes are left, so a huge scene appears over a few frames instead of
+/// holding up input. Until a restarted layer is done, the last finished one
+/// is shown instead, so dragging or panning a huge scene doesn't blank it.
+fn stroke_in_layer(
+    context: &CanvasRenderingContext2d,
+    state: &State,
+    strokes: Vec<Stroke>,
+    options: RenderOptions,
+) {
+    let layer = &state.scene_layer;
+    let key = LayerKey {
+        strokes,
+        dash_offset: options.dash_offset,
+        shadow: options.shadow,
+        width: options.width as u32,
+        height: options.height as u32,
+    };
+
+    let mut current = layer.key.borrow_mut();
+    let restarted = current.as_ref() != Some(&key);
+
+    if restarted {
+        if (layer.canvas.width(), layer.canvas.height()) != (key.width, key.height) {
+            layer.canvas.set_width(key.width);
+            layer.canvas.set_height(key.height);
+        }
+
+        layer
+            .context
+            .clear_rect(0.0, 0.0, options.width, options.height);
+        layer.progress.set(0);
+        *current = Some(key);
+    }
+
+    let strokes = &current.as_ref().unwrap().strokes;
+    let deadline = Date::now() + FRAME_BUDGET;
+    let mut progress = layer.progress.get();
+
+    while progress < strokes.len() && Date::now() < deadline {
+        let end = (progress + LAYER_CHUNK).min(strokes.len());
+
+        stroke_all(&layer.context, &strokes[progress..end], options);
+        progress = end;
+    }
+
+    let started = layer.progress.replace(progress);
+
+    if progress < strokes.len() {
+        request_animation_frame(state);
+    }
+    else if restarted || started < progress {
+        layer.keep_finished();
+    }
+
+    let shown = if progress < strokes.len() && layer.has_finished.get() {
+        &layer.finished
+    }
+    else {
+        &layer.canvas
+    };
+
+    context
+        .draw_image_with_html_canvas_element(shown, 0.0, 0.0)
+        .unwrap();
+}
+
+/// An arrow at the edge of the canvas pointing at a handle beyond it.
+struct OffscreenIndicator {
+    kind: PointHandle,
+    /// Where the arrow is drawn, in screen pixels.
+    position: Point,
+    /// Where the handle is, in screen pixels.
+    handle: Point,
+}
+
+/// Arrows for the handles of the primary selection that are off the canvas,
+/// each placed where the line from the canvas center to the handle crosses
+/// a border `OFFSCREEN_INDICATOR_MARGIN` inside the edges.
+fn offscreen_handle_indicators(state: &State, width: f64, height: f64) -> Vec<OffscreenIndicator> {
+    let Some(index) = state.selected()
+    else {
+        return Vec::new();
+    };
+
+    let curve = state
+        .camera
+        .get()
+        .curve_to_screen(state.curves.borrow()[index].clone());
+    let center = Point::new(width / 2.0, height / 2.0);
+    let half_width = width / 2.0 - OFFSCREEN_INDICATOR_MARGIN;
+    let half_height = height / 2.0 - OFFSCREEN_INDICATOR_MARGIN;
+
+    [
+        PointHandle::A,
+        PointHandle::B,
+        PointHandle::C,
+        PointHandle::D,
+    ]
+    .into_iter()
+    .filter(|&kind| match kind {
+        PointHandle::A | PointHandle::D => true,
+        PointHandle::B => !curve.locked_straight,
+        PointHandle::C => !curve.locked_straight && curve.kind == CurveKind::Cubic,
+    })
+    .filter_map(|kind| {
+        let handle = curve.point(kind);
+
+        if (0.0..=width).contains(&handle.x) && (0.0..=height).contains(&handle.y) {
+            return None;
+        }
+
+        let (x, y) = (handle.x - center.x, handle.y - center.y);
+        let scale = (half_width / x.abs()).min(half_height / y.abs());
+
+        Some(OffscreenIndicator {
+            kind,
+            position: Point::new(center.x + x * scale, center.y + y * scale),
+            handle,
+        })
+    })
+    .collect()
+}
+
+/// Fills a triangle at the indicator's position pointing at its handle.
+fn draw_offscreen_indicator(
+    context: &CanvasRenderingContext2d,
+    indicator: OffscreenIndicator,
+    color: &str,
+) {
+    let OffscreenIndicator {
+        position, handle, ..
+    } = indicator;
+
+    let length = distance(position, handle);
+    let (x, y) = (
+        (handle.x - position.x) / length,
+        (handle.y - position.y) / length,
+    );
+    let size = OFFSCREEN_INDICATOR_SIZE;
+
+    context.set_fill_style(&JsString::from(color));
+    context.begin_path();
+    context.move_to(position.x + x * size, position.y + y * size);
+    context.line_to(
+        position.x - x * size / 2.0 - y * size * 0.6,
+        position.y - y * size / 2.0 + x * size * 0.6,
+    );
+    context.line_to(
+        position.x - x * size / 2.0 + y * size * 0.6,
+        position.y - y * size / 2.0 - x * size * 0.6,
+    );
+    context.close_path();
+    context.fill();
+}
+
+/// Draws rulers along the top and left edges and a scale bar in the bottom
+/// right corner, ticked at the round world distance that keeps ticks at
+/// least `MIN_RULER_TICK_SPACING` pixels apart.
+fn draw_rulers(canvas: &HtmlCanvasElement, context: &CanvasRenderingContext2d, camera: Camera) {
+    let width = canvas.client_width() as f64;
+    let height = canvas.client_height() as f64;
+    let step = ruler_step(camera.zoom);
+    // Enough decimals to tell ticks apart when zoomed in past a unit.
+    let precision = (-step.log10().floor()).max(0.0) as usize;
+
+    context.set_fill_style(&JsString::from(RULER_BACKGROUND));
+    context.fill_rect(0.0, 0.0, width, RULER_SIZE);
+    context.fill_rect(0.0, 0.0, RULER_SIZE, height);
+
+    context.set_font(STATUS_FONT);
+    context.set_stroke_style(&JsString::from("black"));
+    context.set_fill_style(&JsString::from("black"));
+    context.begin_path();
+
+    let min = camera.to_world(Point::new(0.0, 0.0));
+    let max = camera.to_world(Point::new(width, height));
+
+    let mut x = (min.x / step).ceil() * step;
+
+    while x <= max.x {
+        let screen = camera.to_screen(Point::new(x, 0.0)).x;
+
+        context.move_to(screen, RULER_SIZE / 2.0);
+        context.line_to(screen, RULER_SIZE);
+        context
+            .fill_text(
+                &format_coordinate(x, precision),
+                screen + 2.0,
+                RULER_SIZE / 2.0,
+            )
+            .unwrap();
+
+        x += step;
+    }
+
+    let mut y = (min.y / step).ceil() * step;
+
+    while y <= max.y {
+        let screen = camera.to_screen(Point::new(0.0, y)).y;
+
+        context.move_to(RULER_SIZE / 2.0, screen);
+        context.line_to(RULER_SIZE, screen);
+        context
+            .fill_text(&format_coordinate(y, precision), 2.0, screen - 2.0)
+            .unwrap();
+
+        y += step;
+    }
+
+    // One step long, sitting on the bottom margin.
+    let bar = step * camera.zoom;
+    let right = width - STATUS_MARGIN;
+    let bottom = height - STATUS_MARGIN;
+
+    context.move_to(right - bar, bottom - 4.0);
+    context.line_to(right - bar, bottom);
+    context.line_to(right, bottom);
+    context.line_to(right, bottom - 4.0);
+    context.stroke();
+
+    context.set_text_align("center");
+    context
+        .fill_text(
+            &format_coordinate(step, precision),
+            right - bar / 2.0,
+            bottom - 6.0,
+        )
+        .unwrap();
+    context.set_text_align("start");
+}
+
+/// The smallest world distance of the form 1, 2 or 5 times a power of ten
+/// that spans at least `MIN_RULER_TICK_SPACING` screen pixels.
+fn ruler_step(zoom: f64) -> f64 {
+    round_step(MIN_RULER_TICK_SPACING / zoom)
+}
+
+/// The smallest number of the form 1, 2 or 5 times a power of ten that is at
+/// least `minimum`.
+fn round_step(minimum: f64) -> f64 {
+    let power = 10f64.powf(minimum.log10().floor());
+
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|factor| factor * power)
+        .find(|&step| step >= minimum)
+        .unwrap()
+}
+
+/// Draws full-canvas lines through `cursor`, labeled with its world
+/// coordinates.
+fn draw_crosshair(
+    canvas: &HtmlCanvasElement,
+    context: &CanvasRenderingContext2d,
+    camera: Camera,
+    cursor: Point,
+) {
+    let screen = camera.to_screen(cursor);
+
+    context.set_stroke_style(&JsString::from(CROSSHAIR_COLOR));
+    context.begin_path();
+    context.move_to(screen.x, 0.0);
+    context.line_to(screen.x, canvas.client_height() as _);
+    context.move_to(0.0, screen.y);
+    context.line_to(canvas.client_width() as _, screen.y);
+    context.stroke();
+
+    context.set_font(STATUS_FONT);
+    context.set_fill_style(&JsString::from(CROSSHAIR_COLOR));
+    context
+        .fill_text(
+            &format!("{:.1}, {:.1}", cursor.x, cursor.y),
+            screen.x + CROSSHAIR_LABEL_OFFSET,
+            screen.y - CROSSHAIR_LABEL_OFFSET,
+        )
+        .unwrap();
+}
+
+/// Lists the curves in draw order, highlighting the selected ones. The list is
+/// only rebuilt when its contents change, so redrawing every frame is cheap.
+fn update_layers_panel(state: &State) {
+    let selection = state.selection.borrow();
+
+    let html: String = (0..state.curves.borrow().len())
+        .map(|index| {
+            let class = if selection.contains(&index) {
+                r#" class="selected""#
+            }
+            else {
+                ""
+            };
+
+            format!(r#"<li draggable="true" data-index="{index}"{class}>Curve {index}</li>"#)
+        })
+        .collect();
+
+    if state.layers_panel.inner_html() != html {
+        state.layers_panel.set_inner_html(&html);
+    }
+}
+
+/// The anchor a handle's length and angle are measured from: `a` for `b`,
+/// and `d` for `c`.
+fn handle_anchor(handle: PointHandle) -> PointHandle {
+    match handle {
+        PointHandle::B => PointHandle::A,
+        _ => PointHandle::D,
+    }
+}
+
+/// Creates an element of the editor's user interface, styled by `class`.
+fn add_panel(editor: &Element, tag: &str, class: &str) -> HtmlElement {
+    let panel: HtmlElement = window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element(tag)
+        .unwrap()
+        .dyn_into()
+        .unwrap();
+
+    panel.set_class_name(class);
+    editor.append_child(&panel).unwrap();
+
+    panel
+}
+
+/// The length or angle field of a handle in the handles panel.
+fn handle_input(state: &State, handle: PointHandle, field: &str) -> HtmlInputElement {
+    let name = match handle {
+        PointHandle::B => "b",
+        _ => "c",
+    };
+
+    state
+        .handles_panel
+        .query_selector(&format!(".handle-{name}-{field}"))
+        .unwrap()
+        .unwrap()
+        .dyn_into()
+        .unwrap()
+}
+
+/// Shows the primary selection's handles as lengths and angles from their
+/// anchors. Angles are in degrees clockwise from the x axis, since y points
+/// down. The field being typed into is left alone.
+fn update_handles_panel(state: &State) {
+    let Some(index) = state.selected()
+    else {
+        state.handles_panel.set_hidden(true);
+        return;
+    };
+
+    state.handles_panel.set_hidden(false);
+
+    let curve = state.curves.borrow()[index].clone();
+    let focused = window().unwrap().document().unwrap().active_element();
+    let precision = state.export_precision.get();
+
+    for handle in [PointHandle::B, PointHandle::C] {
+        let anchor = curve.point(handle_anchor(handle));
+        let point = curve.point(handle);
+        let length = distance(anchor, point);
+        let angle = (point.y - anchor.y).atan2(point.x - anchor.x).to_degrees();
+
+        // A quadratic has a single control point, shown as `b`.
+        let row = handle_input(state, handle, "length")
+            .parent_element()
+            .unwrap();
+
+        row.dyn_into::<HtmlElement>()
+            .unwrap()
+            .set_hidden(handle == PointHandle::C && curve.kind == CurveKind::Quadratic);
+
+        for (field, value) in [("length", length), ("angle", angle)] {
+            let input = handle_input(state, handle, field);
+
+            // Handles of curves locked straight can't be moved.
+            input.set_disabled(curve.locked_straight);
+
+            if focused.as_ref() != Some(input.as_ref()) {
+                input.set_value(&format_coordinate(value, precision));
+            }
+        }
+    }
+}
+
+/// Places `handle` of the curve at `index` `length` away from its anchor at
+/// `angle` radians, clockwise from the x axis. A smooth junction's other
+/// handle follows.
+fn set_handle_polar(state: &State, index: usize, handle: PointHandle, length: f64, angle: f64) {
+    let mut curves = state.curves.borrow_mut();
+    let curve = &mut curves[index];
+    let anchor = curve.point(handle_anchor(handle));
+
+    *curve.point_mut(handle) = Point::new(
+        anchor.x + length * angle.cos(),
+        anchor.y + length * angle.sin(),
+    );
+    curve.satisfy_pin(handle);
+
+    follow_across_junction(&mut curves, index, handle);
+}
+
+/// The curve index of the layers panel item an event happened on.
+fn layer_index(event: &Event) -> Option<usize> {
+    event
+        .target()?
+        .dyn_into::<Element>()
+        .ok()?
+        .closest("li")
+        .ok()??
+        .get_attribute("data-index")?
+        .parse()
+        .ok()
+}
+
+/// Moves the curve at `from` to `to` in draw order, shifting the curves in
+/// between and keeping the selection on the same curves.
+fn move_curve(state: &State, from: usize, to: usize) {
+    state.trim.set(None);
+    state.anchor_preview.set(None);
+    state.active_anchor.set(None);
+    state.previous_anchor.set(None);
+    state.active_handle.set(None);
+
+    let mut curves = state.curves.borrow_mut();
+    let curve = curves.remove(from);
+    curves.insert(to, curve);
+
+    let new_index = |index: usize| {
+        if index == from {
+            to
+        }
+        else if from < index && index <= to {
+            index - 1
+        }
+        else if to <= index && index < from {
+            index + 1
+        }
+        else {
+            index
+        }
+    };
+
+    for index in state.selection.borrow_mut().iter_mut() {
+        *index = new_index(*index);
+    }
+
+    dimension::remap_dimensions(state, |index, handle| Some((new_index(index), handle)));
+}
+
+/// Outlines the world rectangle the canvas shows at the default view, from
+/// the origin to the canvas's size.
+fn draw_page(canvas: &HtmlCanvasElement, context: &CanvasRenderingContext2d, camera: Camera) {
+    let min = camera.to_screen(Point::new(0.0, 0.0));
+    let max = camera.to_screen(Point::new(canvas.width() as _, canvas.height() as _));
+
+    context.set_stroke_style(&JsString::from(PAGE_COLOR));
+    context.stroke_rect(min.x, min.y, max.x - min.x, max.y - min.y);
+}
+
+/// Draws the snapping grid, unless it is off or too dense to be useful at
+/// the current zoom.
+fn draw_grid(canvas: &HtmlCanvasElement, context: &CanvasRenderingContext2d, state: &State) {
+    let snap = state.snap.get();
+    let camera = state.camera.get();
+
+    if snap.grid == GridKind::Off || snap.grid_size * camera.zoom < MIN_GRID_SPACING {
+        return;
+    }
+
+    let width = canvas.client_width() as f64;
+    let height = canvas.client_height() as f64;
+    let min = camera.to_world(Point::new(0.0, 0.0));
+    let max = camera.to_world(Point::new(width, height));
+
+    context.set_stroke_style(&JsString::from(GRID_COLOR));
+    context.begin_path();
+
+    let line = |from: Point, to: Point| {
+        let from = camera.to_screen(from);
+        let to = camera.to_screen(to);
+
+        context.move_to(from.x, from.y);
+        context.line_to(to.x, to.y);
+    };
+
+    let size = snap.grid_size;
+
+    match snap.grid {
+        GridKind::Off => {}
+        GridKind::Square => {
+            for i in (min.x / size).ceil() as i64..=(max.x / size).floor() as i64 {
+                let x = i as f64 * size;
+                line(Point::new(x, min.y), Point::new(x, max.y));
+            }
+
+            for i in (min.y / size).ceil() as i64..=(max.y / size).floor() as i64 {
+                let y = i as f64 * size;
+                line(Point::new(min.x, y), Point::new(max.x, y));
+            }
+        }
+        GridKind::Isometric => {
+            let (w, _) = snap::isometric_steps(size);
+            let slope = 30f64.to_radians().tan();
+
+            for i in (min.x / w).ceil() as i64..=(max.x / w).floor() as i64 {
+                let x = i as f64 * w;
+                line(Point::new(x, min.y), Point::new(x, max.y));
+            }
+
+            // Sloped lines are `y = ±slope * x + k * size`; find the range of
+            // `k` that crosses the view from its corners.
+            for sign in [1.0, -1.0] {
+                let offsets = [min.x, max.x].map(|x| [min.y, max.y].map(|y| y - sign * slope * x));
+                let offsets = offsets.as_flattened();
+                let low = offsets.iter().copied().fold(f64::INFINITY, f64::min);
+                let high = offsets.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+                for k in (low / size).ceil() as i64..=(high / size).floor() as i64 {
+                    let offset = k as f64 * size;
+
+                    line(
+                        Point::new(min.x, sign * slope * min.x + offset),
+                        Point::new(max.x, sign * slope * max.x + offset),
+                    );
+                }
+            }
+        }
+    }
+
+    context.stroke();
+}
+
+/// Labels grid points with their world coordinates. Only every so many
+/// points along each axis are labelled, a round number of grid steps apart,
+/// so labels stay readable at any zoom.
+fn draw_grid_labels(canvas: &HtmlCanvasElement, context: &CanvasRenderingContext2d, state: &State) {
+    let snap = state.snap.get();
+    let camera = state.camera.get();
+
+    if snap.grid == GridKind::Off {
+        return;
+    }
+
+    let (step_x, step_y) = snap.node_steps();
+    let multiple = round_step(MIN_GRID_LABEL_SPACING / (step_x.min(step_y) * camera.zoom)).max(1.0);
+    let (step_x, step_y) = (step_x * multiple, step_y * multiple);
+    // Enough decimals to tell labels apart when zoomed in past a unit.
+    let precision = (-step_x.min(step_y).log10().floor()).max(0.0) as usize;
+
+    let width = canvas.client_width() as f64;
+    let height = canvas.client_height() as f64;
+    let min = camera.to_world(Point::new(0.0, 0.0));
+    let max = camera.to_world(Point::new(width, height));
+
+    context.set_font(STATUS_FONT);
+    context.set_fill_style(&JsString::from(GRID_LABEL_COLOR));
+
+    for i in (min.x / step_x).ceil() as i64..=(max.x / step_x).floor() as i64 {
+        for j in (min.y / step_y).ceil() as i64..=(max.y / step_y).floor() as i64 {
+            let point = Point::new(i as f64 * step_x, j as f64 * step_y);
+            let screen = camera.to_screen(point);
+
+            context.fill_rect(screen.x - 1.0, screen.y - 1.0, 2.0, 2.0);
+            context
+                .fill_text(
+                    &format!(
+                        "{}, {}",
+                        format_coordinate(point.x, precision),
+                        format_coordinate(point.y, precision)
+                    ),
+                    screen.x + 3.0,
+                    screen.y - 3.0,
+                )
+                .unwrap();
+        }
+    }
+}
+
+/// Draws a line of scene diagnostics along the bottom of the canvas. Draws
+/// nothing when there is nothing to report.
+fn draw_status_line(canvas: &HtmlCanvasElement, context: &CanvasRenderingContext2d, state: &State) {
+    let mut parts = Vec::new();
+
+    if state.new_curve_kind.get() == CurveKind::Quadratic {
+        parts.push("Drawing quadratic curves (Q to switch)".to_string());
+    }
+
+    if state.mode.get() == Mode::Eyedropper {
+        parts.push(match state.picked_style.get() {
+            None => "Eyedropper: click a curve to pick its style (Esc to exit)".to_string(),
+            Some(_) => "Eyedropper: click curves to apply the style (Esc to exit)".to_string(),
+        });
+    }
+
+    if state.create_binding.get() != CreateBinding::Click {
+        parts.push(format!(
+            "{} (; to change)",
+            state.create_binding.get().describe()
+        ));
+    }
+
+    if state.pen_chain.get().is_some() {
+        parts.push("Drawing a path: Enter finishes, Esc cancels".to_string());
+    }
+    else if state.chain_curves.get() {
+        parts.push("Drawing curves end to end (> for separate curves)".to_string());
+    }
+
+    if state.normalize_winding.get() {
+        parts.push("Normalizing imported winding (7 to keep it)".to_string());
+    }
+
+    if state.relative_nudge.get() {
+        parts.push("Arrows scale control handles (\\ to move them)".to_string());
+    }
+
+    if !state.snap.get().enabled {
+        parts.push("Snapping off (! turns it on)".to_string());
+    }
+    else if state.snap_held_off.get() {
+        parts.push("Snapping held off by Alt".to_string());
+    }
+
+    if state.mode.get() == Mode::InsertAnchor {
+        parts.push("Insert anchor: click a curve to split it (Esc to exit)".to_string());
+    }
+
+    if state.mode.get() == Mode::Mirror {
+        parts.push(
+            "Mirror: drag to draw the axis, Space for vertical or horizontal, Enter to copy (Esc to exit)"
+                .to_string(),
+        );
+    }
+
+    if state.mode.get() == Mode::Rotate {
+        parts.push(
+            "Rotate: drag to rotate the selection, Alt+click to place the pivot (Esc to exit)"
+                .to_string(),
+        );
+    }
+
+    let degenerate = state
+        .curves
+        .borrow()
+        .iter()
+        .filter(|curve| curve.is_degenerate())
+        .count();
+
+    if let Some(index) = state.selected() {
+        let length = cached_arc_length(state, index);
+        parts.push(format!("curve {index}: length {length:.1}"));
+    }
+
+    let subpaths = cached_subpaths(state);
+    let selection = state.selection.borrow();
+
+    // The subpaths touching the selection, or all of them.
+    let (closed, open): (Vec<_>, Vec<_>) = subpaths
+        .iter()
+        .filter(|subpath| {
+            selection.is_empty() || subpath.curves.iter().any(|index| selection.contains(index))
+        })
+        .partition(|subpath| subpath.closed);
+
+    if !open.is_empty() || !closed.is_empty() {
+        parts.push(format!(
+            "{} open, {} closed path{} (O selects open)",
+            open.len(),
+            closed.len(),
+            if closed.len() == 1 { "" } else { "s" }
+        ));
+    }
+
+    let welds = topology::weld_candidates(&state.curves.borrow(), WELD_DISTANCE).len();
+
+    if welds > 0 {
+        parts.push(format!(
+            "{welds} near-miss junction{} (K welds all, F the next)",
+            if welds == 1 { "" } else { "s" }
+        ));
+    }
+
+    if degenerate > 0 {
+        parts.push(format!(
+            "{degenerate} degenerate curve{} (press X to remove)",
+            if degenerate == 1 { "" } else { "s" }
+        ));
+    }
+
+    if let Some((left, total)) = state.scene_layer.pending() {
+        parts.push(format!("Drawing: {left} of {total} strokes to go"));
+    }
+
+    if parts.is_empty() {
+        return;
+    }
+
+    context.set_font(STATUS_FONT);
+    context.set_fill_style(&JsString::from("black"));
+    context
+        .fill_text(
+            &parts.join(" · "),
+            STATUS_MARGIN,
+            canvas.client_height() as f64 - STATUS_MARGIN,
+        )
+        .unwrap();
+}
+
+fn set_mode(canvas: &HtmlCanvasElement, state: &State, mode: Mode) {
+    let cursor = match mode {
+        Mode::Edit | Mode::Preview => "default",
+        Mode::Eyedropper => "copy",
+        Mode::InsertAnchor => "crosshair",
+        Mode::Rotate => "grab",
+        Mode::Mirror => "crosshair",
+    };
+
+    canvas.style().set_property("cursor", cursor).unwrap();
+    state.mode.set(mode);
+}
+
+/// Removes every curve matching `predicate`, keeping surviving curves
+/// selected.
+fn remove_curves(state: &State, predicate: impl Fn(usize, &Curve) -> bool) {
+    let removed: Vec<bool> = state
+        .curves
+        .borrow()
+        .iter()
+        .enumerate()
+        .map(|(index, curve)| predicate(index, curve))
+        .collect();
+
+    if !removed.contains(&true) {
+        return;
+    }
+
+    record_undo(state);
+
+    let mut curves = state.curves.borrow_mut();
+
+    // New index of every curve, or `None` for removed ones.
+    let mut remaining = 0;
+    let new_indices: Vec<Option<usize>> = removed
+        .iter()
+        .map(|&removed| {
+            (!removed).then(|| {
+                remaining += 1;
+                remaining - 1
+            })
+        })
+        .collect();
+
+    let mut index = 0;
+
+    curves.retain(|_| {
+        index += 1;
+        !removed[index - 1]
+    });
+
+    state
+        .selection
+        .borrow_mut()
+        .retain_mut(|index| match new_indices[*index] {
+            Some(new_index) => {
+                *index = new_index;
+                true
+            }
+            None => false,
+        });
+
+    dimension::remap_dimensions(state, |index, handle| {
+        new_indices[index].map(|index| (index, handle))
+    });
+
+    let count = removed.iter().filter(|&&removed| removed).count();
+
+    announce(
+        state,
+        match count {
+            1 => "curve deleted".to_string(),
+            _ => format!("{count} curves deleted"),
+        },
+    );
+
+    // A drag in progress may refer to a curve that is gone or has moved.
+    state.drag_state.set(None);
+    state.snap_target.set(None);
+    state.active_anchor.set(None);
+    state.previous_anchor.set(None);
+    state.active_handle.set(None);
+    state.trim.set(None);
+    state.anchor_preview.set(None);
+}
+
+/// Runs `edit` as a single undoable step, however many curves it touches:
+/// the curves are saved once before, and kept in the history only if `edit`
+/// changed them. Curves locked straight are straightened afterwards.
+fn transaction<R>(state: &State, edit: impl FnOnce() -> R) -> R {
+    let before = state.curves.borrow().clone();
+    let result = edit();
+
+    enforce_straight_locks(&mut state.curves.borrow_mut());
+
+    if *state.curves.borrow() != before {
+        state.history.borrow_mut().record(before);
+    }
+
+    result
+}
+
+/// Puts the control points of curves locked straight back on their chords.
+fn enforce_straight_locks(curves: &mut [Curve]) {
+    for curve in curves.iter_mut().filter(|curve| curve.locked_straight) {
+        curve.straighten();
+    }
+}
+
+/// Saves the current curves so the edit about to be made can be undone.
+fn record_undo(state: &State) {
+    let curves = state.curves.borrow().clone();
+    state.history.borrow_mut().record(curves);
+}
+
+fn undo(state: &State) {
+    if state
+        .history
+        .borrow_mut()
+        .undo(&mut state.curves.borrow_mut())
+    {
+        forget_stale_indices(state);
+        announce(state, "undone".to_string());
+    }
+}
+
+/// Whether there is an edit to undo. A transaction or drag counts as one
+/// edit, and only once it changed something.
+fn can_undo(state: &State) -> bool {
+    state.history.borrow().can_undo()
+}
+
+fn can_redo(state: &State) -> bool {
+    state.history.borrow().can_redo()
+}
+
+fn redo(state: &State) {
+    if state
+        .history
+        .borrow_mut()
+        .redo(&mut state.curves.borrow_mut())
+    {
+        forget_stale_indices(state);
+        announce(state, "redone".to_string());
+    }
+}
+
+/// Drops state that refers to curves by index after the whole curve list
+/// has been replaced.
+fn forget_stale_indices(state: &State) {
+    let count = state.curves.borrow().len();
+
+    state.selection.borrow_mut().retain(|&index| index < count);
+    dimension::remap_dimensions(state, |index, handle| {
+        (index < count).then_some((index, handle))
+    });
+    state.drag_state.set(None);
+    state.snap_target.set(None);
+    state.active_anchor.set(None);
+    state.previous_anchor.set(None);
+    state.active_handle.set(None);
+    state.trim.set(None);
+    state.anchor_preview.set(None);
+}
+
+/// The other curve, and its anchor, meeting the curve at `index` at its
+/// `anchor`.
+fn junction_partner(
+    curves: &[Curve],
+    index: usize,
+    anchor: PointHandle,
+) -> Option<(usize, PointHandle)> {
+    let point = curves[index].point(anchor);
+
+    curves.iter().enumerate().find_map(|(other, curve)| {
+        let other_anchor = [PointHandle::A, PointHandle::D]
+            .into_iter()
+            .find(|&handle| curve.point(handle) == point)?;
+
+        (other != index).then_some((other, other_anchor))
+    })
+}
+
+/// After a control handle was dragged, moves the handle across the junction
+/// at its anchor to match, as the anchor's type asks. Returns the index of
+/// the curve that changed, if any.
+fn follow_across_junction(
+    curves: &mut [Curve],
+    index: usize,
+    handle: PointHandle,
+) -> Option<usize> {
+    let curve = &curves[index];
+    let anchor = curve.anchor_of(handle)?;
+    let anchor_type = curve.anchor_type(anchor);
+
+    if anchor_type == AnchorType::Corner {
+        return None;
+    }
+
+    let (other, other_anchor) = junction_partner(curves, index, anchor)?;
+    let other_handle = curves[other].handle_of(other_anchor);
+
+    let point = curve.point(anchor);
+    let dragged = curve.point(handle);
+    let length = distance(dragged, point);
+
+    if length == 0.0 {
+        return None;
+    }
+
+    let scale = match anchor_type {
+        AnchorType::Smooth => distance(curves[other].point(other_handle), point) / length,
+        _ => 1.0,
+    };
+
+    let partner = &mut curves[other];
+
+    *partner.point_mut(other_handle) = Point::new(
+        point.x - (dragged.x - point.x) * scale,
+        point.y - (dragged.y - point.y) * scale,
+    );
+    partner.satisfy_pin(other_handle);
+
+    Some(other)
+}
+
+/// Gives the two control handles meeting at each junction of the primary
+/// selection the same length, their average, without changing their
+/// directions. A junction is an endpoint shared with another curve's
+/// endpoint; handles of zero length have no direction and are left alone.
+fn balance_junction_handles(state: &State) {
+    let Some(selected) = state.selected()
+    else {
+        return;
+    };
+
+    let curves = state.curves.borrow();
+    let curve = &curves[selected];
+    let mut moves = Vec::new();
+
+    for anchor in [PointHandle::A, PointHandle::D] {
+        let point = curve.point(anchor);
+
+        let Some((other, other_anchor)) = junction_partner(&curves, selected, anchor)
+        else {
+            continue;
+        };
+
+        let sides = [
+            (selected, curve.handle_of(anchor)),
+            (other, curves[other].handle_of(other_anchor)),
+        ];
+        let lengths = sides.map(|(index, handle)| distance(curves[index].point(handle), point));
+
+        if lengths.contains(&0.0) {
+            continue;
+        }
+
+        let average = (lengths[0] + lengths[1]) / 2.0;
+
+        for ((index, handle), length) in sides.into_iter().zip(lengths) {
+            moves.push((
+                index,
+                handle,
+                point.lerp(curves[index].point(handle), average / length),
+            ));
+        }
+    }
+
+    drop(curves);
+
+    transaction(state, || {
+        for (index, handle, position) in moves {
+            let curve = &mut state.curves.borrow_mut()[index];
+
+            *curve.point_mut(handle) = position;
+            curve.satisfy_pin(handle);
+        }
+    });
+}
+
+/// The junctions where the path turns by more than `KINK_ANGLE`, as the
+/// two curve ends meeting there. Each junction is listed once.
+fn kinks(curves: &[Curve]) -> Vec<[(usize, PointHandle); 2]> {
+    (0..curves.len())
+        .flat_map(|index| [(index, PointHandle::A), (index, PointHandle::D)])
+        .filter_map(|(index, anchor)| {
+            let (other, other_anchor) = junction_partner(curves, index, anchor)?;
+            let turn = leaving_angle(&curves[other], other_anchor)
+                - leaving_angle(&curves[index], anchor)
+                - PI;
+
+            (other > index && turn.sin().atan2(turn.cos()).abs() > KINK_ANGLE)
+                .then_some([(index, anchor), (other, other_anchor)])
+        })
+        .collect()
+}
+
+/// Direction the curve leaves its `anchor` in, into its body.
+fn leaving_angle(curve: &Curve, anchor: PointHandle) -> f64 {
+    match anchor {
+        PointHandle::A => curve.start_angle(),
+        _ => curve.end_angle() + PI,
+    }
+}
+
+/// Where the warning marker for the kink between `sides` goes, on screen.
+fn kink_marker(state: &State, [(index, anchor), _]: [(usize, PointHandle); 2]) -> Point {
+    let point = state
+        .camera
+        .get()
+        .to_screen(state.curves.borrow()[index].point(anchor));
+
+    Point::new(point.x, point.y - KINK_MARKER_OFFSET)
+}
+
+/// Lists `curve`'s points with their world coordinates in a small table
+/// beside its control points, on whichever side has room, so it covers
+/// neither the curve nor its handles.
+fn draw_point_table(
+    canvas: &HtmlCanvasElement,
+    context: &CanvasRenderingContext2d,
+    state: &State,
+    curve: Curve,
+) {
+    let precision = state.export_precision.get();
+    let handles: &[(PointHandle, &str)] = match curve.kind {
+        CurveKind::Cubic => &[
+            (PointHandle::A, "A"),
+            (PointHandle::B, "B"),
+            (PointHandle::C, "C"),
+            (PointHandle::D, "D"),
+        ],
+        CurveKind::Quadratic => &[
+            (PointHandle::A, "A"),
+            (PointHandle::B, "B"),
+            (PointHandle::D, "D"),
+        ],
+    };
+
+    let rows: Vec<String> = handles
+        .iter()
+        .map(|&(handle, letter)| {
+            let point = curve.point(handle);
+
+            format!(
+                "{letter} {}, {}",
+                format_coordinate(point.x, precision),
+                format_coordinate(point.y, precision)
+            )
+        })
+        .collect();
+
+    context.set_font(HANDLE_LABEL_FONT);
+
+    let width = rows
+        .iter()
+        .map(|row| context.measure_text(row).unwrap().width())
+        .fold(0.0, f64::max)
+        + 4.0;
+    let height = rows.len() as f64 * POINT_TABLE_ROW_HEIGHT + 4.0;
+
+    let camera = state.camera.get();
+    let points = camera.curve_to_screen(curve).cubic_points();
+    let min_x = points
+        .iter()
+        .map(|point| point.x)
+        .fold(f64::INFINITY, f64::min);
+    let max_x = points
+        .iter()
+        .map(|point| point.x)
+        .fold(f64::NEG_INFINITY, f64::max);
+    let min_y = points
+        .iter()
+        .map(|point| point.y)
+        .fold(f64::INFINITY, f64::min);
+
+    let x = if max_x + POINT_TABLE_OFFSET + width <= canvas.client_width() as f64 {
+        max_x + POINT_TABLE_OFFSET
+    }
+    else {
+        min_x - POINT_TABLE_OFFSET - width
+    };
+    let y = min_y.clamp(0.0, (canvas.client_height() as f64 - height).max(0.0));
+
+    context.set_fill_style(&JsString::from(POINT_TABLE_BACKGROUND));
+    context.fill_rect(x, y, width, height);
+    context.set_fill_style(&JsString::from("black"));
+
+    for (row, text) in rows.iter().enumerate() {
+        context
+            .fill_text(
+                text,
+                x + 2.0,
+                y + 2.0 + (row + 1) as f64 * POINT_TABLE_ROW_HEIGHT - 3.0,
+            )
+            .unwrap();
+    }
+}
+
+/// Draws a warning triangle above every kink.
+fn draw_kinks(context: &CanvasRenderingContext2d, state: &State) {
+    let kinks = kinks(&state.curves.borrow());
+
+    context.set_fill_style(&JsString::from(KINK_COLOR));
+    context.begin_path();
+
+    for &sides in &kinks {
+        let marker = kink_marker(state, sides);
+        let half = KINK_MARKER_SIZE / 2.0;
+
+        context.move_to(marker.x, marker.y - half);
+        context.line_to(marker.x + half, marker.y + half);
+        context.line_to(marker.x - half, marker.y + half);
+        context.close_path();
+    }
+
+    context.fill();
+
+    context.set_font(STATUS_FONT);
+    context.set_fill_style(&JsString::from("white"));
+    context.set_text_align("center");
+
+    for &sides in &kinks {
+        let marker = kink_marker(state, sides);
+
+        context
+            .fill_text("!", marker.x, marker.y + KINK_MARKER_SIZE / 2.0 - 1.0)
+            .unwrap();
+    }
+
+    context.set_text_align("start");
+}
+
+/// Makes the junction between `sides` smooth with `align_handles`, and
+/// makes the anchors smooth so it stays that way when they are dragged.
+fn smooth_kink(state: &State, sides: [(usize, PointHandle); 2]) {
+    let [(index, _), (other, _)] = sides;
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+        align_handles(&mut curves, sides);
+
+        for (index, anchor) in sides {
+            *curves[index].anchor_type_mut(anchor) = AnchorType::Smooth;
+        }
+    });
+
+    announce(
+        state,
+        format!("junction of curves {index} and {other} smoothed"),
+    );
+}
+
+/// Turns the handles at the junction between `sides` halfway between their
+/// directions, keeping their lengths, so the path passes through it without
+/// a corner. The anchor stays put.
+fn align_handles(curves: &mut [Curve], sides: [(usize, PointHandle); 2]) {
+    let [(index, anchor), (other, other_anchor)] = sides;
+
+    let leaving = leaving_angle(&curves[index], anchor);
+    let turn = leaving_angle(&curves[other], other_anchor) - leaving - PI;
+    let angle = leaving + turn.sin().atan2(turn.cos()) / 2.0;
+    let point = curves[index].point(anchor);
+
+    for ((index, anchor), angle) in sides.into_iter().zip([angle, angle + PI]) {
+        let curve = &mut curves[index];
+        let handle = curve.handle_of(anchor);
+        let length = match distance(curve.point(handle), point) {
+            // A handle on its anchor has no length to keep; give it a third
+            // of the chord, as straight curves have.
+            0.0 => distance(curve.a, curve.d) / 3.0,
+            length => length,
+        };
+
+        *curve.point_mut(handle) = Point::new(
+            point.x + length * angle.cos(),
+            point.y + length * angle.sin(),
+        );
+        curve.satisfy_pin(handle);
+    }
+}
+
+/// Lines up the handles where the two selected curves meet, as
+/// `align_handles` does, leaving the anchors' types alone. Where they meet
+/// at both ends, the junction at the last grabbed anchor is aligned, or
+/// else the one at the first curve's start.
+fn align_tangents(state: &State) -> bool {
+    let &[index, other] = &state.selection.borrow()[..]
+    else {
+        return false;
+    };
+
+    let junctions: Vec<[(usize, PointHandle); 2]> = {
+        let curves = state.curves.borrow();
+
+        [PointHandle::A, PointHandle::D]
+            .into_iter()
+            .flat_map(|anchor| [PointHandle::A, PointHandle::D].map(|end| (anchor, end)))
+            .filter(|&(anchor, end)| curves[index].point(anchor) == curves[other].point(end))
+            .map(|(anchor, end)| [(index, anchor), (other, end)])
+            .collect()
+    };
+
+    let active = state.active_anchor.get();
+
+    let Some(&sides) = junctions
+        .iter()
+        .find(|sides| active.is_some_and(|active| sides.contains(&active)))
+        .or(junctions.first())
+    else {
+        announce(state, format!("curves {index} and {other} don't meet"));
+        return false;
+    };
+
+    transaction(state, || {
+        align_handles(&mut state.curves.borrow_mut(), sides);
+    });
+
+    announce(
+        state,
+        format!("tangents of curves {index} and {other} aligned"),
+    );
+    true
+}
+
+/// Replaces every curve by the pieces between its crossings with other
+/// curves, and reports how many pieces resulted. Selected curves stay
+/// selected through all of their pieces. Asks first in large scenes.
+fn split_at_intersections(state: &State) {
+    let count = state.curves.borrow().len();
+
+    // Every pair of curves is tested, so large scenes take a while.
+    if count > SPLIT_CONFIRM_THRESHOLD
+        && !window()
+            .unwrap()
+            .confirm_with_message(&format!(
+                "Splitting {count} curves at their intersections may take a while. Continue?"
+            ))
+            .unwrap()
+    {
+        return;
+    }
+
+    let (pieces, crossings) = intersect::split_at_intersections(&state.curves.borrow());
+
+    if crossings == 0 {
+        announce(state, "no intersections found".to_string());
+        return;
+    }
+
+    record_undo(state);
+
+    let mut curves = Vec::new();
+    let mut selection = Vec::new();
+    // Where each curve's pieces start and end in the new list.
+    let mut ranges = Vec::new();
+    let selected = state.selection.borrow();
+
+    for (index, pieces) in pieces.into_iter().enumerate() {
+        if selected.contains(&index) {
+            selection.extend(curves.len()..curves.len() + pieces.len());
+        }
+
+        ranges.push((curves.len(), curves.len() + pieces.len() - 1));
+        curves.extend(pieces);
+    }
+
+    drop(selected);
+
+    dimension::remap_dimensions(state, |index, handle| match handle {
+        PointHandle::A => Some((ranges[index].0, handle)),
+        _ => Some((ranges[index].1, handle)),
+    });
+
+    let pieces = curves.len();
+
+    *state.curves.borrow_mut() = curves;
+    *state.selection.borrow_mut() = selection;
+
+    announce(
+        state,
+        format!("{count} curves split into {pieces} pieces at {crossings} intersections"),
+    );
+}
+
+/// Asks for padding, then downloads the scene as SVG cropped to the drawing.
+/// The view box is the bounds of the curves and dimensions, grown by the
+/// padding and by half the widest stroke so that nothing is clipped. The
+/// document's size matches the view box in world units, and it scales to
+/// whatever size it is shown at.
+fn export_fitted_svg(state: &State) {
+    let bounds = [scene_bounds(state), dimension::dimensions_bounds(state)]
+        .into_iter()
+        .flatten()
+        .reduce(|(min, max), (other_min, other_max)| {
+            (
+                Point::new(min.x.min(other_min.x), min.y.min(other_min.y)),
+                Point::new(max.x.max(other_max.x), max.y.max(other_max.y)),
+            )
+        });
+
+    let Some((min, max)) = bounds
+    else {
+        return;
+    };
+
+    let Some(answer) = window()
+        .unwrap()
+        .prompt_with_message_and_default("Padding around the drawing", "10")
+        .unwrap()
+    else {
+        return;
+    };
+
+    let Some(padding) = answer
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|padding| *padding >= 0.0)
+    else {
+        console::warn_1(&JsValue::from(format!(
+            "Expected a padding of at least 0, got \"{answer}\""
+        )));
+        return;
+    };
+
+    let widest = state
+        .curves
+        .borrow()
+        .iter()
+        .map(|curve| curve.style.width)
+        .fold(0.0, f64::max);
+    let margin = padding + widest / 2.0;
+
+    let view_box = (
+        Point::new(min.x - margin, min.y - margin),
+        Point::new(max.x + margin, max.y + margin),
+    );
+    let width = view_box.1.x - view_box.0.x;
+    let height = view_box.1.y - view_box.0.y;
+
+    let svg = svg::export_svg(state, width, height, view_box);
+    download_text("scene.svg", "image/svg+xml", &svg);
+}
+
+/// Adds what the clipboard holds to the scene: an SVG document, SVG path
+/// data, or a scene in the encoding of shared links.
+fn paste(canvas: HtmlCanvasElement, state: Rc<State>) {
+    let promise = window().unwrap().navigator().clipboard().read_text();
+
+    let read = Closure::<dyn FnMut(JsValue)>::new({
+        let state = state.clone();
+
+        move |text: JsValue| {
+            let text = text.as_string().unwrap_or_default();
+
+            if let Err(error) = paste_text(&canvas, &state, &text) {
+                console::warn_1(&JsValue::from(format!("Pasting failed: {error}")));
+                announce(&state, "nothing to paste".to_string());
+            }
+        }
+    });
+
+    // Reading fails when the page isn't focused or the permission is denied.
+    let failed = Closure::<dyn FnMut(JsValue)>::new(move |error: JsValue| {
+        console::error_2(&JsValue::from("Reading the clipboard failed:"), &error);
+        announce(&state, "pasting failed".to_string());
+    });
+
+    let _ = promise.then2(&read, &failed);
+
+    read.forget();
+    failed.forget();
+}
+
+/// Makes the closed paths among the curves from `first` on wind the same
+/// way, if imports are to be normalized. Returns how many were reversed.
+fn normalize_imported_winding(state: &State, first: usize) -> usize {
+    if !state.normalize_winding.get() {
+        return 0;
+    }
+
+    let mut curves = state.curves.borrow_mut();
+    let imported: Vec<usize> = (first..curves.len()).collect();
+
+    topology::normalize_winding(&mut curves, &imported)
+}
+
+/// Tells how many paths `normalize_imported_winding` reversed, if any.
+fn describe_reversed(reversed: usize) -> Option<String> {
+    (reversed > 0).then(|| {
+        format!(
+            "{reversed} path{} reversed to normalize winding",
+            if reversed == 1 { "" } else { "s" }
+        )
+    })
+}
+
+/// Adds `text` to the scene, telling SVG from path data from a shared
+/// scene by how it starts: SVG is XML, and path data starts with a move.
+fn paste_text(canvas: &HtmlCanvasElement, state: &State, text: &str) -> Result<(), String> {
+    let text = text.trim();
+
+    if text.starts_with('<') {
+        let first = state.curves.borrow().len();
+        let (count, reversed) = transaction(state, || {
+            let count = svg::import_svg(state, text);
+            let reversed = normalize_imported_winding(state, first);
+            count.map(|count| (count, reversed))
+        })?;
+
+        if count == 0 {
+            return Err("the SVG has no paths".to_string());
+        }
+
+        let mut message = format!("{count} path{} pasted", if count == 1 { "" } else { "s" });
+
+        if let Some(reversed) = describe_reversed(reversed) {
+            message = format!("{message}, {reversed}");
+        }
+
+        announce(state, message);
+        request_animation_frame(state);
+    }
+    else if text.starts_with(['M', 'm']) {
+        let curves = svg::parse_path(text)?;
+        let count = curves.len();
+
+        if count == 0 {
+            return Err("the path has no curves".to_string());
+        }
+
+        add_scene(state, curves, false);
+        announce(
+            state,
+            format!("{count} curve{} pasted", if count == 1 { "" } else { "s" }),
+        );
+        request_animation_frame(state);
+    }
+    else {
+        load_scene(canvas, state, text, false, false)?;
+    }
+
+    Ok(())
+}
+
+/// Copies the selected curves to the clipboard as polylines flattened to
+/// within `FLATTEN_TOLERANCE`, one `x,y` per line and a blank line between
+/// curves, for pasting into spreadsheets and plotting tools.
+fn copy_polylines(state: Rc<State>) {
+    let precision = state.export_precision.get();
+    let curves = state.curves.borrow();
+
+    let text = state
+        .selection
+        .borrow()
+        .iter()
+        .map(|&index| {
+            curves[index]
+                .flatten(FLATTEN_TOLERANCE)
+                .into_iter()
+                .map(|(x, y)| {
+                    format!(
+                        "{},{}\n",
+                        format_coordinate(x, precision),
+                        format_coordinate(y, precision)
+                    )
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    drop(curves);
+
+    if text.is_empty() {
+        return;
+    }
+
+    let count = state.selection.borrow().len();
+    let promise = window().unwrap().navigator().clipboard().write_text(&text);
+
+    let copied = Closure::<dyn FnMut(JsValue)>::new({
+        let state = state.clone();
+
+        move |_| {
+            announce(
+                &state,
+                match count {
+                    1 => "curve copied as points".to_string(),
+                    _ => format!("{count} curves copied as points"),
+                },
+            );
+        }
+    });
+
+    // Writing fails when the page isn't focused or the permission is denied.
+    let failed = Closure::<dyn FnMut(JsValue)>::new(move |error: JsValue| {
+        console::error_2(&JsValue::from("Copying to the clipboard failed:"), &error);
+        announce(&state, "copying failed".to_string());
+    });
+
+    let _ = promise.then2(&copied, &failed);
+
+    copied.forget();
+    failed.forget();
+}
+
+/// Puts the scene into the page's URL, so that copying the link shares it.
+/// With `include_selection`, reloading the page also restores the selection.
+fn share_scene(state: &State, include_selection: bool) {
+    let selection = state.selection.borrow();
+    let encoded = binary::export_binary(
+        &state.curves.borrow(),
+        include_selection.then_some(&selection[..]),
+    );
+
+    window()
+        .unwrap()
+        .location()
+        .set_hash(&format!("{SHARE_PREFIX}{encoded}"))
+        .unwrap();
+
+    announce(state, "link to scene updated".to_string());
+}
+
+/// Loads the scene from the page's URL, if it holds one put there by
+/// `share_scene`.
+fn load_shared_scene(canvas: &HtmlCanvasElement, state: &State) {
+    let hash = window().unwrap().location().hash().unwrap();
+
+    let Some(encoded) = hash.strip_prefix(SHARE_PREFIX)
+    else {
+        return;
+    };
+
+    match binary::import_binary(encoded) {
+        Ok((curves, selection)) => {
+            let next_group = curves.iter().filter_map(|curve| curve.group).max();
+            state
+                .next_group
+                .set(next_group.map_or(0, |group| group + 1));
+            *state.curves.borrow_mut() = curves;
+            *state.selection.borrow_mut() = selection;
+
+            fit_to_view(canvas, state);
+        }
+        Err(error) => {
+            console::error_1(&JsValue::from(format!(
+                "Loading shared scene failed: {error}"
+            )));
+        }
+    }
+}
+
+/// Puts `sample::sample_scene` in place of the scene, or adds it, and
+/// brings it into view.
+fn load_sample_scene(canvas: &HtmlCanvasElement, state: &State, replace: bool) {
+    add_scene(state, sample::sample_scene(), replace);
+    fit_to_view(canvas, state);
+    announce(state, "sample scene loaded".to_string());
+}
+
+/// Loads a scene saved in `binary`'s encoding in place of the scene, or adds
+/// it, optionally bringing it into view. Nothing changes if it doesn't
+/// decode.
+fn load_scene(
+    canvas: &HtmlCanvasElement,
+    state: &State,
+    encoded: &str,
+    replace: bool,
+    fit: bool,
+) -> Result<(), String> {
+    let (scene, _) = binary::import_binary(encoded)?;
+    let count = scene.len();
+
+    add_scene(state, scene, replace);
+
+    if fit {
+        fit_to_view(canvas, state);
+    }
+
+    announce(
+        state,
+        format!(
+            "{count} curve{} {}",
+            if count == 1 { "" } else { "s" },
+            if replace { "loaded" } else { "added" }
+        ),
+    );
+    request_animation_frame(state);
+
+    Ok(())
+}
+
+/// Gives the curve at `index` the points and kind of `shape`, for host pages
+/// driving curves from outside, such as from an animation or a solver. It
+/// keeps its other properties, but still passes through its pin and stays
+/// straight if locked so, and drops its weights if its kind changed. Being
+/// meant for many updates in a row, it isn't recorded for undoing. Fails
+/// when there is no curve at `index` or a point isn't finite.
+fn set_curve(state: &State, index: usize, shape: Curve) -> Result<(), ()> {
+    let finite = |point: Point| point.x.is_finite() && point.y.is_finite();
+
+    if !shape.cubic_points().into_iter().all(finite) {
+        return Err(());
+    }
+
+    let mut curves = state.curves.borrow_mut();
+    let curve = curves.get_mut(index).ok_or(())?;
+
+    *curve = Curve {
+        a: shape.a,
+        b: shape.b,
+        c: shape.c,
+        d: shape.d,
+        kind: shape.kind,
+        weights: curve.weights.filter(|_| curve.kind == shape.kind),
+        ..curve.clone()
+    };
+    curve.satisfy_pin(PointHandle::A);
+
+    enforce_straight_locks(&mut curves[index..=index]);
+    drop(curves);
+
+    request_animation_frame(state);
+
+    Ok(())
+}
+
+/// Puts `scene` in place of the scene, or adds it after the curves already
+/// there as one new group, moved clear of them if it would overlap. Either
+/// way it is one undoable step, and the new curves end up selected.
+fn add_scene(state: &State, scene: Vec<Curve>, replace: bool) {
+    let count = scene.len();
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+
+        if replace {
+            let next_group = scene.iter().filter_map(|curve| curve.group).max();
+            state
+                .next_group
+                .set(next_group.map_or(0, |group| group + 1));
+
+            *curves = scene;
+            return;
+        }
+
+        let Some(bounds) = curves_bounds(&scene)
+        else {
+            return;
+        };
+
+        let offset = clear_offset(&curves, bounds, true);
+        let shift = |point: Point| Point::new(point.x + offset.x, point.y + offset.y);
+        let group = state.next_group.get();
+        state.next_group.set(group + 1);
+
+        curves.extend(scene.into_iter().map(|curve| Curve {
+            group: Some(group),
+            pin: curve.pin.map(|pin| Pin {
+                point: shift(pin.point),
+                ..pin
+            }),
+            ..curve.map(shift)
+        }));
+    });
+
+    if replace {
+        state.dimensions.borrow_mut().clear();
+    }
+
+    forget_stale_indices(state);
+
+    let len = state.curves.borrow().len();
+    *state.selection.borrow_mut() = (len - count..len).collect();
+}
+
+/// Asks for a size in pixels, suggesting `default`. Returns `None`, after
+/// warning about anything malformed, unless a positive size is given.
+fn prompt_size(default: &str) -> Option<(f64, f64)> {
+    let answer = window()
+        .unwrap()
+        .prompt_with_message_and_default("Size (widthxheight)", default)
+        .unwrap()?;
+
+    let size = answer
+        .trim()
+        .split_once('x')
+        .and_then(|(width, height)| Some((width.parse::<f64>().ok()?, height.parse::<f64>().ok()?)))
+        .filter(|&(width, height)| width >= 1.0 && height >= 1.0);
+
+    if size.is_none() {
+        console::warn_1(&JsValue::from(format!(
+            "Expected a size like \"{default}\", got \"{answer}\""
+        )));
+    }
+
+    size
+}
+
+/// Asks for an image size, then downloads the scene as a PNG of that size,
+/// fitted with a margin on a white background, without handles. Sizes
+/// larger than the canvas give high-resolution renders.
+fn export_png(state: &State) {
+    let Some(bounds) = scene_bounds(state)
+    else {
+        return;
+    };
+
+    let Some((width, height)) = prompt_size("1600x1200")
+    else {
+        return;
+    };
+
+    let (canvas, context) = offscreen_canvas(width as _, height as _);
+
+    render_scene(
+        &context,
+        state,
+        RenderOptions {
+            camera: Camera::fitting(bounds, width, height),
+            width,
+            height,
+            background: Some("white"),
+            dash_offset: 0.0,
+            handles: None,
+            stroke_scale: 1.0,
+            shadow: state.shadow.get(),
+            budgeted: false,
+        },
+    );
+
+    download_url("scene.png", &canvas.to_data_url().unwrap());
+}
+
+/// Asks for a frame count or a speed in pixels per second, and a size, then
+/// downloads frames of a dot travelling along the selected curves, in
+/// selection order, as PNG data URLs, one per line. A frame count paces the
+/// dot by curve parameter, a speed by length, and either is eased by the
+/// chosen easing. The dot starts at the scrub marker when it is shown, which
+/// is on the last curve.
+fn export_path_frames(state: &State) {
+    let path: Vec<Curve> = {
+        let curves = state.curves.borrow();
+        state
+            .selection
+            .borrow()
+            .iter()
+            .map(|&index| curves[index].clone())
+            .collect()
+    };
+
+    if path.is_empty() {
+        return;
+    }
+
+    let Some(answer) = window()
+        .unwrap()
+        .prompt_with_message_and_default(
+            "Frame count or speed, and size (e.g. \"60 400x300\" or \"120px/s 400x300\")",
+            "60 400x300",
+        )
+        .unwrap()
+    else {
+        return;
+    };
+
+    let parsed = answer.split_once(' ').and_then(|(pacing, size)| {
+        let (width, height) = size.trim().split_once('x')?;
+
+        let pacing = match pacing.trim().strip_suffix("px/s") {
+            Some(speed) => Pacing::Speed(speed.parse::<f64>().ok().filter(|&speed| speed > 0.0)?),
+            None => Pacing::Parameter(
+                pacing
+                    .trim()
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&frames| frames > 0)?,
+            ),
+        };
+
+        Some((
+            pacing,
+            width.parse::<u32>().ok()?,
+            height.parse::<u32>().ok()?,
+        ))
+    });
+
+    let Some((pacing, width, height)) =
+        parsed.filter(|&(_, width, height)| width > 0 && height > 0)
+    else {
+        console::warn_1(&JsValue::from(format!(
+            "Expected a frame count or speed and a size like \"60 400x300\" or \"120px/s 400x300\", got \"{answer}\""
+        )));
+
+        return;
+    };
+
+    let start = state
+        .scrub
+        .get()
+        .map_or(0.0, |t| (path.len() - 1) as f64 + t);
+
+    let frames =
+        frames::render_path_frames(&path, pacing, state.easing.get(), start, width, height);
+    download_text("path-frames.txt", "text/plain", &frames.join("\n"));
+}
+
+/// Rounds off gentle corners of the straight-segment paths the selection
+/// belongs to, or of all paths if nothing is selected, as left by importing
+/// polylines.
+fn smooth_paths(state: &State) {
+    let mut indices: Vec<usize> = {
+        let curves = state.curves.borrow();
+        let selection = state.selection.borrow();
+
+        if selection.is_empty() {
+            (0..curves.len()).collect()
+        }
+        else {
+            selection
+                .iter()
+                .flat_map(|&index| connected_path(&curves, index))
+                .collect()
+        }
+    };
+
+    indices.sort_unstable();
+    indices.dedup();
+
+    if indices.is_empty() {
+        return;
+    }
+
+    transaction(state, || {
+        smooth::smooth_corners(
+            &mut state.curves.borrow_mut(),
+            &indices,
+            SMOOTH_CORNER_ANGLE,
+        );
+    });
+}
+
+/// Indices of every curve reachable from the one at `index` through shared
+/// endpoints, in ascending order. Includes `index` itself.
+fn connected_path(curves: &[Curve], index: usize) -> Vec<usize> {
+    let mut path = vec![index];
+    let mut pending = vec![index];
+
+    while let Some(current) = pending.pop() {
+        let ends = [curves[current].a, curves[current].d];
+
+        for (other, curve) in curves.iter().enumerate() {
+            if !path.contains(&other) && (ends.contains(&curve.a) || ends.contains(&curve.d)) {
+                path.push(other);
+                pending.push(other);
+            }
+        }
+    }
+
+    path.sort_unstable();
+    path
+}
+
+/// Selects every curve that matches the primary selection in the given
+/// respect, keeping the primary selection primary.
+/// Closes the primary selection's subpath with a straight curve from its loose
+/// end to its loose start, then selects the closed path. Nothing happens
+/// unless the subpath has exactly two loose ends.
+fn close_path(state: &State) {
+    let Some(selected) = state.selected()
+    else {
+        return;
+    };
+
+    let Some(subpath) = cached_subpaths(state)
+        .into_iter()
+        .find(|subpath| subpath.curves.contains(&selected))
+    else {
+        return;
+    };
+
+    let curve = {
+        let curves = state.curves.borrow();
+
+        let [first, second] = topology::loose_ends(&curves, &subpath.curves)[..]
+        else {
+            return;
+        };
+
+        // A path running from A to D ends at a D; start the closing curve
+        // there so it runs the same way.
+        let (end, start) = match first.1 {
+            PointHandle::D => (first, second),
+            _ => (second, first),
+        };
+
+        Curve {
+            style: curves[end.0].style,
+            ..Curve::line(curves[end.0].point(end.1), curves[start.0].point(start.1))
+        }
+    };
+
+    transaction(state, || state.curves.borrow_mut().push(curve));
+
+    let index = state.curves.borrow().len() - 1;
+    let mut selection = subpath.curves;
+    selection.push(index);
+    *state.selection.borrow_mut() = selection;
+
+    announce(state, format!("path closed with curve {index}"));
+}
+
+/// Disconnects the path at the active anchor by pulling the other curve's end
+/// there back along that curve, `BREAK_GAP` pixels towards its handle. Both
+/// ends become corners, free to be dragged apart.
+fn break_path(state: &State) {
+    let Some((index, anchor)) = state.active_anchor.get()
+    else {
+        return;
+    };
+
+    let (other, other_anchor) = {
+        let curves = state.curves.borrow();
+
+        let Some(partner) = junction_partner(&curves, index, anchor)
+        else {
+            return;
+        };
+
+        partner
+    };
+
+    let gap = BREAK_GAP / state.camera.get().zoom;
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+
+        *curves[index].anchor_type_mut(anchor) = AnchorType::Corner;
+
+        let curve = &mut curves[other];
+        let point = curve.point(other_anchor);
+        let handle = curve.point(curve.handle_of(other_anchor));
+
+        // A handle sitting on its anchor gives no direction; head for the
+        // far end instead.
+        let towards = if handle != point {
+            handle
+        }
+        else if other_anchor == PointHandle::A {
+            curve.d
+        }
+        else {
+            curve.a
+        };
+        let length = distance(point, towards);
+
+        if length > 0.0 {
+            *curve.point_mut(other_anchor) = point.lerp(towards, (gap / length).min(0.5));
+            curve.satisfy_pin(other_anchor);
+        }
+
+        *curve.anchor_type_mut(other_anchor) = AnchorType::Corner;
+    });
+
+    announce(state, format!("path broken at curve {index}"));
+}
+
+/// Copies the selected curves next to the originals, into empty space if
+/// there is some nearby, and selects the copies. Grouped curves are copied
+/// into new groups.
+fn duplicate_selection(state: &State) {
+    let selection = state.selection.borrow().clone();
+
+    if selection.is_empty() {
+        return;
+    }
+
+    let offset = duplicate_offset(&state.curves.borrow(), &selection);
+    let mut groups = Vec::new();
+
+    let copies: Vec<Curve> = selection
+        .iter()
+        .map(|&index| {
+            let curve = state.curves.borrow()[index].clone();
+            let shift = |point: Point| Point::new(point.x + offset.x, point.y + offset.y);
+
+            let group = curve.group.map(|group| {
+                match groups.iter().find(|&&(original, _)| original == group) {
+                    Some(&(_, copy)) => copy,
+                    None => {
+                        let copy = state.next_group.get();
+                        state.next_group.set(copy + 1);
+                        groups.push((group, copy));
+                        copy
+                    }
+                }
+            });
+
+            Curve {
+                group,
+                pin: curve.pin.map(|pin| Pin {
+                    point: shift(pin.point),
+                    ..pin
+                }),
+                ..curve.map(shift)
+            }
+        })
+        .collect();
+
+    let count = copies.len();
+
+    transaction(state, || state.curves.borrow_mut().extend(copies));
+
+    let len = state.curves.borrow().len();
+    *state.selection.borrow_mut() = (len - count..len).collect();
+
+    announce(
+        state,
+        format!(
+            "{count} curve{} duplicated",
+            if count == 1 { "" } else { "s" }
+        ),
+    );
+}
+
+/// How far to move copies of the curves at `indices` so they land clear of
+/// every curve's bounding box, originals included.
+fn duplicate_offset(curves: &[Curve], indices: &[usize]) -> Point {
+    let selected: Vec<Curve> = indices.iter().map(|&index| curves[index].clone()).collect();
+
+    match curves_bounds(&selected) {
+        Some(bounds) => clear_offset(curves, bounds, false),
+        None => Point::new(DUPLICATE_OFFSET, DUPLICATE_OFFSET),
+    }
+}
+
+/// How far to move the `(min, max)` box so it lands clear of every curve's
+/// bounding box. With `in_place`, a box already clear stays put. Otherwise
+/// tries spots beside, above, below, and diagonally next to it, moving
+/// further out until `DUPLICATE_SEARCH_DISTANCE`, then falls back to a small
+/// diagonal offset.
+fn clear_offset(curves: &[Curve], (min, max): (Point, Point), in_place: bool) -> Point {
+    let fallback = Point::new(DUPLICATE_OFFSET, DUPLICATE_OFFSET);
+    let boxes: Vec<_> = curves.iter().map(Curve::bounding_box).collect();
+    let step_x = max.x - min.x + DUPLICATE_OFFSET;
+    let step_y = max.y - min.y + DUPLICATE_OFFSET;
+
+    let directions = [
+        (1.0, 0.0),
+        (0.0, 1.0),
+        (-1.0, 0.0),
+        (0.0, -1.0),
+        (1.0, 1.0),
+        (-1.0, 1.0),
+        (1.0, -1.0),
+        (-1.0, -1.0),
+    ];
+
+    in_place
+        .then_some(Point::new(0.0, 0.0))
+        .into_iter()
+        .chain((1..=DUPLICATE_SEARCH_DISTANCE).flat_map(|distance| {
+            let distance = distance as f64;
+            directions.map(|(x, y)| Point::new(x * step_x * distance, y * step_y * distance))
+        }))
+        .find(|offset| {
+            let (new_min, new_max) = (
+                Point::new(min.x + offset.x, min.y + offset.y),
+                Point::new(max.x + offset.x, max.y + offset.y),
+            );
+
+            boxes.iter().all(|&(box_min, box_max)| {
+                new_max.x < box_min.x
+                    || box_max.x < new_min.x
+                    || new_max.y < box_min.y
+                    || box_max.y < new_min.y
+            })
+        })
+        .unwrap_or(fallback)
+}
+
+/// Joins up to `limit` pairs of curve ends that nearly meet, moving both ends
+/// of each pair to their midpoint, handles along. The last junction made
+/// becomes the active anchor, with the curve selected, so `a` can set its
+/// continuity right away.
+fn weld_anchors(state: &State, limit: usize) {
+    let mut candidates = topology::weld_candidates(&state.curves.borrow(), WELD_DISTANCE);
+    candidates.truncate(limit);
+
+    let Some(&[(last, last_anchor), _]) = candidates.last()
+    else {
+        return;
+    };
+
+    let count = candidates.len();
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+
+        for &pair in &candidates {
+            weld_pair(&mut curves, pair);
+        }
+    });
+
+    state.active_anchor.set(Some((last, last_anchor)));
+    *state.selection.borrow_mut() = vec![last];
+
+    announce(
+        state,
+        format!(
+            "{count} junction{} welded",
+            if count == 1 { "" } else { "s" }
+        ),
+    );
+}
+
+/// Moves both ends of `pair` to their midpoint, handles along.
+fn weld_pair(curves: &mut [Curve], pair: [(usize, PointHandle); 2]) {
+    let [first, second] = pair.map(|(index, handle)| curves[index].point(handle));
+    let middle = first.lerp(second, 0.5);
+
+    for (index, anchor) in pair {
+        let curve = &mut curves[index];
+        let point = curve.point(anchor);
+        let handle = curve.point_mut(curve.handle_of(anchor));
+
+        handle.x += middle.x - point.x;
+        handle.y += middle.y - point.y;
+
+        *curve.point_mut(anchor) = middle;
+        curve.satisfy_pin(anchor);
+    }
+}
+
+/// Joins the selected curves into one path as one undoable step: ends among
+/// them that nearly meet are welded, and they all go into one new group so
+/// they are selected and moved together. Curves with no end meeting another
+/// selected curve's are reported, since they stay apart.
+fn join_selection(state: &State) {
+    let selection = state.selection.borrow().clone();
+
+    if selection.len() < 2 {
+        announce(state, "Select the curves to join".to_string());
+        return;
+    }
+
+    let selected: Vec<Curve> = {
+        let curves = state.curves.borrow();
+        selection
+            .iter()
+            .map(|&index| curves[index].clone())
+            .collect()
+    };
+    let candidates: Vec<[(usize, PointHandle); 2]> =
+        topology::weld_candidates(&selected, WELD_DISTANCE)
+            .into_iter()
+            .map(|pair| pair.map(|(position, handle)| (selection[position], handle)))
+            .collect();
+
+    let group = state.next_group.get();
+    state.next_group.set(group + 1);
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+
+        for &pair in &candidates {
+            weld_pair(&mut curves, pair);
+        }
+
+        for &index in &selection {
+            curves[index].group = Some(group);
+        }
+    });
+
+    let curves = state.curves.borrow();
+    let ends = |index: usize| [curves[index].a, curves[index].d];
+    let apart: Vec<String> = selection
+        .iter()
+        .filter(|&&index| {
+            selection.iter().all(|&other| {
+                other == index || ends(index).iter().all(|end| !ends(other).contains(end))
+            })
+        })
+        .map(|index| index.to_string())
+        .collect();
+
+    let paths: Vec<Subpath> = cached_subpaths(state)
+        .into_iter()
+        .filter(|subpath| subpath.curves.iter().any(|index| selection.contains(index)))
+        .collect();
+    let closed = paths.iter().filter(|subpath| subpath.closed).count();
+
+    if !apart.is_empty() {
+        console::warn_1(
+            &format!(
+                "Curves {} share no end with the rest of the selection",
+                apart.join(", ")
+            )
+            .into(),
+        );
+    }
+
+    announce(
+        state,
+        format!(
+            "{} junction{} welded, {} path{} ({closed} closed){}",
+            candidates.len(),
+            if candidates.len() == 1 { "" } else { "s" },
+            paths.len(),
+            if paths.len() == 1 { "" } else { "s" },
+            if apart.is_empty() {
+                String::new()
+            }
+            else {
+                format!("; curves {} could not be joined", apart.join(", "))
+            }
+        ),
+    );
+}
+
+/// The point on the curve nearest to `screen`, as a curve index and
+/// parameter, if one is within `INSERT_ANCHOR_DISTANCE` and not at an end.
+fn find_anchor_insertion(state: &State, screen: Point) -> Option<(usize, f64)> {
+    let camera = state.camera.get();
+
+    state
+        .curves
+        .borrow()
+        .iter()
+        .enumerate()
+        .filter_map(|(index, curve)| {
+            let curve = camera.curve_to_screen(curve.clone());
+
+            if !is_near_bounds(curve.control_bounds(), screen, INSERT_ANCHOR_DISTANCE) {
+                return None;
+            }
+
+            let t = curve.nearest_t(screen);
+            let gap = distance(curve.point_at(t), screen);
+
+            (gap <= INSERT_ANCHOR_DISTANCE).then_some((gap, index, t))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+        .map(|(_, index, t)| (index, t))
+        .filter(|&(_, t)| (INSERT_ANCHOR_MARGIN..=1.0 - INSERT_ANCHOR_MARGIN).contains(&t))
+}
+
+/// Splits the curve at `index` in two at `t`, the second half right after
+/// the first, and makes the new anchor the active one.
+fn insert_anchor(state: &State, index: usize, t: f64) {
+    let (first, second) = state.curves.borrow()[index].clone().split(t);
+
+    transaction(state, || {
+        state
+            .curves
+            .borrow_mut()
+            .splice(index..=index, [first, second]);
+    });
+
+    forget_stale_indices(state);
+    shift_past_split(state, index, 2);
+
+    state.active_anchor.set(Some((index, PointHandle::D)));
+    announce(state, format!("anchor inserted into curve {index}"));
+}
+
+/// Every curve of the subpaths the selection touches, in order. Joins are
+/// set for whole paths at once, so they look the same at every corner.
+fn selected_paths(state: &State) -> Vec<usize> {
+    let selection = state.selection.borrow();
+
+    cached_subpaths(state)
+        .into_iter()
+        .filter(|subpath| subpath.curves.iter().any(|index| selection.contains(index)))
+        .flat_map(|subpath| subpath.curves)
+        .collect()
+}
+
+/// Asks for the miter limit of the selected paths' joins.
+fn set_miter_limit(state: &State) {
+    let paths = selected_paths(state);
+
+    let Some(&first) = paths.first()
+    else {
+        return;
+    };
+
+    let current = state.curves.borrow()[first].style.miter_limit;
+
+    let Some(answer) = window()
+        .unwrap()
+        .prompt_with_message_and_default(
+            "Miter limit, as a multiple of the stroke width",
+            &current.to_string(),
+        )
+        .unwrap()
+    else {
+        return;
+    };
+
+    let Some(limit) = answer
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|limit| *limit >= 1.0 && limit.is_finite())
+    else {
+        console::warn_1(&JsValue::from(format!(
+            "Expected a miter limit of at least 1, got \"{answer}\""
+        )));
+        return;
+    };
+
+    transaction(state, || {
+        for index in paths {
+            state.curves.borrow_mut()[index].style.miter_limit = limit;
+        }
+    });
+}
+
+/// Asks for the weights of the selected curves' control points, turning
+/// them into rational curves, or back into ordinary ones with weights of 1.
+fn set_weights(state: &State) {
+    let selection = state.selection.borrow().clone();
+
+    let Some(&primary) = selection.last()
+    else {
+        return;
+    };
+
+    let current = state.curves.borrow()[primary].weights.unwrap_or([1.0; 4]);
+
+    let Some(answer) = window()
+        .unwrap()
+        .prompt_with_message_and_default(
+            "Weights of the control points b and c",
+            &format!("{} {}", current[1], current[2]),
+        )
+        .unwrap()
+    else {
+        return;
+    };
+
+    let weights: Vec<f64> = answer
+        .split_whitespace()
+        .filter_map(|weight| weight.parse().ok())
+        .filter(|weight: &f64| *weight > 0.0 && weight.is_finite())
+        .collect();
+
+    let [b, c] = weights[..]
+    else {
+        console::warn_1(&JsValue::from(format!(
+            "Expected two positive weights like \"1 0.7\", got \"{answer}\""
+        )));
+        return;
+    };
+
+    transaction(state, || {
+        for &index in &selection {
+            state.curves.borrow_mut()[index].weights =
+                (b != 1.0 || c != 1.0).then_some([1.0, b, c, 1.0]);
+        }
+    });
+}
+
+/// Selects every curve of every subpath that has a loose end.
+fn select_open_paths(state: &State) {
+    let selection = cached_subpaths(state)
+        .into_iter()
+        .filter(|subpath| !subpath.closed)
+        .flat_map(|subpath| subpath.curves)
+        .collect();
+
+    *state.selection.borrow_mut() = selection;
+}
+
+/// The scene's subpaths, only worked out again when a curve end has moved or
+/// curves were added or removed since last time.
+fn cached_subpaths(state: &State) -> Vec<Subpath> {
+    let curves = state.curves.borrow();
+    let ends: Vec<_> = curves.iter().map(|curve| (curve.a, curve.d)).collect();
+    let mut cache = state.subpaths.borrow_mut();
+
+    if cache.0 != ends {
+        *cache = (ends, topology::subpaths(&curves));
+    }
+
+    cache.1.clone()
+}
+
+/// Selects exactly the curves that are not selected.
+fn invert_selection(state: &State) {
+    let count = state.curves.borrow().len();
+    let mut selection = state.selection.borrow_mut();
+
+    *selection = (0..count)
+        .filter(|index| !selection.contains(index))
+        .collect();
+
+    let selected = selection.len();
+    drop(selection);
+
+    announce(
+        state,
+        format!(
+            "{selected} curve{} selected",
+            if selected == 1 { "" } else { "s" }
+        ),
+    );
+}
+
+fn select_similar(state: &State, similarity: Similarity) {
+    let Some(selected) = state.selected()
+    else {
+        return;
+    };
+
+    let curves = state.curves.borrow();
+    let reference = &curves[selected];
+
+    let mut selection: Vec<usize> = (0..curves.len())
+        .filter(|&index| index != selected && similarity.matches(&curves[index], reference))
+        .collect();
+
+    selection.push(selected);
+    *state.selection.borrow_mut() = selection;
+}
+
+/// Arc length of the curve at `index`, only measured again once the curve
+/// has changed.
+fn cached_arc_length(state: &State, index: usize) -> f64 {
+    let curve = state.curves.borrow()[index].clone();
+    state.arc_lengths.borrow_mut().length(index, curve)
+}
+
+/// Arc lengths by curve index, each kept with the curve it was measured on.
+/// A curve that was edited, or replaced at its index, no longer matches and
+/// is measured again, so no edit needs to remember to clear anything.
+#[derive(Default)]
+struct ArcLengthCache(Vec<Option<(Curve, f64)>>);
+
+impl ArcLengthCache {
+    fn length(&mut self, index: usize, curve: Curve) -> f64 {
+        if self.0.len() <= index {
+            self.0.resize(index + 1, None);
+        }
+
+        match &self.0[index] {
+            Some((measured, length)) if *measured == curve => *length,
+            _ => {
+                let length = curve.arc_length();
+                self.0[index] = Some((curve, length));
+                length
+            }
+        }
+    }
+}
+
+/// Zooms and pans so the whole scene fits the canvas with a small margin.
+/// Does nothing for an empty scene.
+fn fit_to_view(canvas: &HtmlCanvasElement, state: &State) {
+    let Some((min, max)) = scene_bounds(state)
+    else {
+        return;
+    };
+
+    move_camera(
+        state,
+        Camera::fitting(
+            (min, max),
+            canvas.client_width() as _,
+            canvas.client_height() as _,
+        ),
+    );
+}
+
+/// Pans so the selected curve's bounding box is centered on the canvas,
+/// keeping the zoom. Does nothing when no curve is selected.
+fn recenter_on_selected(canvas: &HtmlCanvasElement, state: &State) {
+    let Some(index) = state.selected()
+    else {
+        return;
+    };
+
+    let (min, max) = state.curves.borrow()[index].bounding_box();
+    let center = Point::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+
+    center_on(canvas, state, center);
+}
+
+/// Moves the last grabbed handle one step in the direction `(x, y)`, a unit
+/// vector along an axis. With a grid shown the step is to the next grid
+/// node that way, so a handle on a node stays on nodes; otherwise it is a
+/// screen pixel, or ten with `far`. Nudging relatively scales a cubic's
+/// control handle about its anchor instead.
+fn nudge_handle(state: &State, x: f64, y: f64, far: bool) {
+    let Some((index, handle)) = state.active_handle.get()
+    else {
+        return;
+    };
+
+    let snap = state.snap.get();
+    let curve = state.curves.borrow()[index].clone();
+    let point = curve.point(handle);
+    let anchor = curve.anchor_of(handle).map(|anchor| curve.point(anchor));
+
+    let target = if let (true, Some(anchor)) = (state.relative_nudge.get(), anchor) {
+        // Up and right lengthen the handle, down and left shorten it, along
+        // its own direction.
+        let factor = if far {
+            RELATIVE_NUDGE_FAR_FACTOR
+        }
+        else {
+            RELATIVE_NUDGE_FACTOR
+        };
+        let scale = if x - y > 0.0 { factor } else { factor.recip() };
+
+        Point::new(
+            anchor.x + (point.x - anchor.x) * scale,
+            anchor.y + (point.y - anchor.y) * scale,
+        )
+    }
+    else if snap.enabled && snap.grid != GridKind::Off {
+        let (step_x, step_y) = snap.node_steps();
+
+        snap.snap_to_grid(Point::new(point.x + x * step_x, point.y + y * step_y))
+    }
+    else {
+        let step = if far { 10.0 } else { 1.0 } / state.camera.get().zoom;
+
+        Point::new(point.x + x * step, point.y + y * step)
+    };
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+        let curve = &mut curves[index];
+        let lock = curve.locks[handle as usize];
+        let point = curve.point_mut(handle);
+
+        *point = lock.constrain(*point, target);
+        curve.satisfy_pin(handle);
+
+        follow_across_junction(&mut curves, index, handle);
+    });
+}
+
+/// Where the selection rotates about: the placed pivot, or else the centroid
+/// of the selected curves' points. `None` when nothing is selected.
+fn rotation_pivot(state: &State) -> Option<Point> {
+    state
+        .rotation_pivot
+        .get()
+        .or_else(|| selection_centroid(state))
+}
+
+/// The centroid of the selected curves' points, or `None` when nothing is
+/// selected.
+fn selection_centroid(state: &State) -> Option<Point> {
+    let curves = state.curves.borrow();
+    let points: Vec<Point> = state
+        .selection
+        .borrow()
+        .iter()
+        .flat_map(|&index| curves[index].cubic_points())
+        .collect();
+
+    if points.is_empty() {
+        return None;
+    }
+
+    let count = points.len() as f64;
+
+    Some(Point::new(
+        points.iter().map(|point| point.x).sum::<f64>() / count,
+        points.iter().map(|point| point.y).sum::<f64>() / count,
+    ))
+}
+
+/// A vertical axis through the centroid of the selection, or a horizontal
+/// one, or `None` when nothing is selected.
+fn default_mirror_axis(state: &State, horizontal: bool) -> Option<(Point, Point)> {
+    let center = selection_centroid(state)?;
+    let end = match horizontal {
+        true => Point::new(center.x + 1.0, center.y),
+        false => Point::new(center.x, center.y + 1.0),
+    };
+
+    Some((center, end))
+}
+
+/// `point` reflected across the line through `axis`.
+fn reflect(point: Point, (start, end): (Point, Point)) -> Point {
+    let length = distance(start, end);
+    let (dx, dy) = ((end.x - start.x) / length, (end.y - start.y) / length);
+    let along = (point.x - start.x) * dx + (point.y - start.y) * dy;
+    let foot = Point::new(start.x + dx * along, start.y + dy * along);
+
+    Point::new(2.0 * foot.x - point.x, 2.0 * foot.y - point.y)
+}
+
+/// The selected curves reflected across `axis`, each reversed since
+/// reflecting turns clockwise into counterclockwise: closed shapes keep
+/// their winding, and a half drawn up to the axis runs on into its mirror
+/// image.
+fn mirrored_selection(state: &State, axis: (Point, Point)) -> Vec<Curve> {
+    let curves = state.curves.borrow();
+
+    state
+        .selection
+        .borrow()
+        .iter()
+        .map(|&index| {
+            let curve = &curves[index];
+
+            Curve {
+                pin: curve.pin.map(|pin| Pin {
+                    point: reflect(pin.point, axis),
+                    ..pin
+                }),
+                ..curve.clone().map(|point| reflect(point, axis))
+            }
+            .reversed()
+        })
+        .collect()
+}
+
+/// Adds a mirrored copy of the selection as one undoable step, puts it in
+/// one new group with the originals, and selects it.
+fn mirror_selection(state: &State, axis: (Point, Point)) {
+    let selection = state.selection.borrow().clone();
+
+    if selection.is_empty() || axis.0 == axis.1 {
+        return;
+    }
+
+    let group = state.next_group.get();
+    state.next_group.set(group + 1);
+
+    let copies = mirrored_selection(state, axis);
+    let count = copies.len();
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+
+        for &index in &selection {
+            curves[index].group = Some(group);
+        }
+
+        curves.extend(copies.into_iter().map(|curve| Curve {
+            group: Some(group),
+            ..curve
+        }));
+    });
+
+    let len = state.curves.borrow().len();
+    *state.selection.borrow_mut() = (len - count..len).collect();
+
+    announce(
+        state,
+        format!(
+            "{count} curve{} mirrored",
+            if count == 1 { "" } else { "s" }
+        ),
+    );
+}
+
+/// Draws the mirror axis across the whole canvas, with the copy it would
+/// make faintly.
+fn draw_mirror_axis(
+    canvas: &HtmlCanvasElement,
+    context: &CanvasRenderingContext2d,
+    state: &State,
+    axis: (Point, Point),
+) {
+    let camera = state.camera.get();
+
+    if axis.0 != axis.1 {
+        for curve in mirrored_selection(state, axis) {
+            stroke_curve(context, camera.curve_to_screen(curve), GHOST_OPACITY);
+        }
+    }
+
+    let (start, end) = (camera.to_screen(axis.0), camera.to_screen(axis.1));
+    let length = distance(start, end);
+
+    if length == 0.0 {
+        return;
+    }
+
+    // Long enough to cross the canvas from any point on it.
+    let reach = (canvas.client_width() as f64).hypot(canvas.client_height() as f64) * 2.0;
+    let (dx, dy) = (
+        (end.x - start.x) / length * reach,
+        (end.y - start.y) / length * reach,
+    );
+    let dash = Array::of2(&JsValue::from(8.0), &JsValue::from(4.0));
+
+    context.set_stroke_style(&JsString::from(MIRROR_AXIS_COLOR));
+    context.set_line_dash(&dash).unwrap();
+    context.begin_path();
+    context.move_to(start.x - dx, start.y - dy);
+    context.line_to(start.x + dx, start.y + dy);
+    context.stroke();
+    context.set_line_dash(&Array::new()).unwrap();
+}
+
+/// Sets the selected curves to their pre-drag positions rotated by `angle`
+/// radians about `pivot`, clockwise since y points down.
+fn rotate_selection(state: &State, pivot: Point, angle: f64) {
+    let (sin, cos) = angle.sin_cos();
+
+    transform_selection(state, |point| {
+        let (x, y) = (point.x - pivot.x, point.y - pivot.y);
+        Point::new(pivot.x + x * cos - y * sin, pivot.y + x * sin + y * cos)
+    });
+}
+
+/// Sets the selected curves to their pre-drag positions scaled so the
+/// gizmo handle being dragged follows `mouse`, away from the opposite side
+/// of the box. With `uniform`, both axes scale alike.
+fn scale_selection(state: &State, scaling: Scaling, mouse: Point, uniform: bool) {
+    let (min, max) = scaling.bounds;
+    let center = min.lerp(max, 0.5);
+    let (x, y) = scaling.sides;
+
+    // Where the handle started, and the point that stays put.
+    let handle = Point::new(
+        center.x + x * (max.x - min.x) / 2.0,
+        center.y + y * (max.y - min.y) / 2.0,
+    );
+    let fixed = Point::new(
+        center.x - x * (max.x - min.x) / 2.0,
+        center.y - y * (max.y - min.y) / 2.0,
+    );
+
+    let factor = |side: f64, mouse: f64, handle: f64, fixed: f64| match side {
+        0.0 => None,
+        _ if (handle - fixed).abs() < DEGENERATE_EXTENT => None,
+        _ => Some((mouse - fixed) / (handle - fixed)),
+    };
+
+    let (mut sx, mut sy) = (
+        factor(x, mouse.x, handle.x, fixed.x),
+        factor(y, mouse.y, handle.y, fixed.y),
+    );
+
+    if uniform {
+        let larger = match (sx, sy) {
+            (Some(sx), Some(sy)) if sx.abs() >= sy.abs() => Some(sx),
+            (sx, sy) => sy.or(sx),
+        };
+
+        (sx, sy) = (larger, larger);
+    }
+
+    let (sx, sy) = (sx.unwrap_or(1.0), sy.unwrap_or(1.0));
+
+    transform_selection(state, |point| {
+        Point::new(
+            fixed.x + (point.x - fixed.x) * sx,
+            fixed.y + (point.y - fixed.y) * sy,
+        )
+    });
+}
+
+/// Ends rotating or scaling the selection, keeping the whole drag as one
+/// undoable edit if it changed anything.
+fn finish_transform(state: &State) {
+    let before = state.drag_origin.take();
+
+    if *state.curves.borrow() != before {
+        state.history.borrow_mut().record(before);
+    }
+}
+
+/// Sets the selected curves to their pre-drag positions mapped through
+/// `transform`, pins included.
+fn transform_selection(state: &State, transform: impl Fn(Point) -> Point) {
+    let origin = state.drag_origin.borrow();
+    let mut curves = state.curves.borrow_mut();
+
+    for &index in state.selection.borrow().iter() {
+        let mut curve = origin[index].clone().map(&transform);
+
+        if let Some(pin) = &mut curve.pin {
+            pin.point = transform(pin.point);
+        }
+
+        curves[index] = curve;
+    }
+
+    enforce_straight_locks(&mut curves);
+}
+
+/// The smallest box holding the selected curves, or `None` when nothing is
+/// selected.
+fn selection_bounds(state: &State) -> Option<(Point, Point)> {
+    let curves = state.curves.borrow();
+    let selected: Vec<Curve> = state
+        .selection
+        .borrow()
+        .iter()
+        .map(|&index| curves[index].clone())
+        .collect();
+
+    curves_bounds(&selected)
+}
+
+/// The transform gizmo's handles with their screen positions, or none when
+/// the gizmo is hidden.
+fn gizmo_handles(state: &State) -> Vec<(GizmoHandle, Point)> {
+    if !state.show_gizmo.get() || state.mode.get() != Mode::Edit {
+        return Vec::new();
+    }
+
+    let Some((min, max)) = selection_bounds(state)
+    else {
+        return Vec::new();
+    };
+
+    let camera = state.camera.get();
+    let (min, max) = (camera.to_screen(min), camera.to_screen(max));
+    let center = min.lerp(max, 0.5);
+
+    let mut handles: Vec<(GizmoHandle, Point)> = [-1.0, 0.0, 1.0]
+        .into_iter()
+        .flat_map(|y| [-1.0, 0.0, 1.0].map(|x| (x, y)))
+        .filter(|&side| side != (0.0, 0.0))
+        .map(|(x, y)| {
+            (
+                GizmoHandle::Scale(x, y),
+                Point::new(
+                    center.x + x * (max.x - min.x) / 2.0,
+                    center.y + y * (max.y - min.y) / 2.0,
+                ),
+            )
+        })
+        .collect();
+
+    handles.push((
+        GizmoHandle::Rotate,
+        Point::new(center.x, min.y - GIZMO_ROTATE_OFFSET),
+    ));
+
+    handles
+}
+
+/// Draws the transform gizmo around the selection: its box, square scale
+/// handles, and a round rotation handle on a stem above the box.
+fn draw_gizmo(context: &CanvasRenderingContext2d, state: &State) {
+    let handles = gizmo_handles(state);
+
+    let Some((min, max)) = selection_bounds(state).filter(|_| !handles.is_empty())
+    else {
+        return;
+    };
+
+    let camera = state.camera.get();
+    let (min, max) = (camera.to_screen(min), camera.to_screen(max));
+
+    context.set_stroke_style(&JsString::from(GIZMO_COLOR));
+    context.set_fill_style(&JsString::from("white"));
+    context.begin_path();
+    context.rect(min.x, min.y, max.x - min.x, max.y - min.y);
+    context.move_to((min.x + max.x) / 2.0, min.y);
+    context.line_to((min.x + max.x) / 2.0, min.y - GIZMO_ROTATE_OFFSET);
+    context.stroke();
+
+    context.begin_path();
+
+    for (handle, point) in handles {
+        let shape = match handle {
+            GizmoHandle::Scale(..) => DotShape::Square,
+            GizmoHandle::Rotate => DotShape::Circle,
+        };
+
+        trace_dot(context, point, GIZMO_HANDLE_SIZE, shape);
+    }
+
+    context.fill();
+    context.stroke();
+}
+
+/// Shifts the view by `dx` and `dy` screen pixels. World coordinates are
+/// unbounded, so there is always more canvas beyond the edges.
+fn pan(state: &State, dx: f64, dy: f64) {
+    let camera = state.camera.get();
+
+    state.view_animation.set(None);
+    state.camera.set(Camera {
+        offset: Point::new(camera.offset.x + dx, camera.offset.y + dy),
+        ..camera
+    });
+}
+
+/// Pans so that `center`, in world coordinates, is in the middle of the
+/// canvas.
+fn center_on(canvas: &HtmlCanvasElement, state: &State, center: Point) {
+    let camera = state.camera.get();
+
+    move_camera(
+        state,
+        Camera {
+            offset: Point::new(
+                canvas.client_width() as f64 / 2.0 - center.x * camera.zoom,
+                canvas.client_height() as f64 / 2.0 - center.y * camera.zoom,
+            ),
+            ..camera
+        },
+    );
+}
+
+/// Moves the view to `target`, easing there over `VIEW_ANIMATION_DURATION`
+/// unless `state.animate_view` is off. A move that interrupts another one
+/// starts from wherever the view currently is.
+fn move_camera(state: &State, target: Camera) {
+    if !state.animate_view.get() {
+        state.view_animation.set(None);
+        state.camera.set(target);
+        return;
+    }
+
+    let camera = state.camera.get();
+    let target_camera = state.view_animation.get().map(|animation| animation.to);
+
+    // Zooming or recentering on a view the animation is already heading for
+    // would otherwise restart its easing.
+    if target_camera != Some(target) {
+        state.view_animation.set(Some(ViewAnimation {
+            from: camera,
+            to: target,
+            start: None,
+        }));
+    }
+
+    request_animation_frame(state);
+}
+
+/// Advances the view animation to `time`. Returns whether it needs more
+/// frames.
+fn step_view_animation(state: &State, time: f64) -> bool {
+    let Some(mut animation) = state.view_animation.get()
+    else {
+        return false;
+    };
+
+    let start = *animation.start.get_or_insert(time);
+    let progress = ((time - start) / VIEW_ANIMATION_DURATION).min(1.0);
+
+    state
+        .camera
+        .set(animation.from.lerp(animation.to, ease_in_out(progress)));
+
+    if progress < 1.0 {
+        state.view_animation.set(Some(animation));
+        true
+    }
+    else {
+        state.view_animation.set(None);
+        false
+    }
+}
+
+/// Advances the dot preview to `time`. Returns whether it needs more frames.
+fn step_dot_preview(state: &State, time: f64) -> bool {
+    let Some(mut preview) = state.dot_preview.get()
+    else {
+        return false;
+    };
+
+    let start = *preview.start.get_or_insert(time);
+    preview.progress = (time - start) / DOT_PREVIEW_DURATION;
+
+    if preview.progress < 1.0 {
+        state.dot_preview.set(Some(preview));
+        true
+    }
+    else {
+        state.dot_preview.set(None);
+        false
+    }
+}
+
+/// Asks for the easing of path animations, then previews it with a dot
+/// travelling along the selected curves, in selection order.
+fn choose_easing(state: &State) {
+    let Some(answer) = window()
+        .unwrap()
+        .prompt_with_message_and_default(
+            "Easing: linear, ease-in-out or cubic-bezier(x1, y1, x2, y2)",
+            &state.easing.get().css(),
+        )
+        .unwrap()
+    else {
+        return;
+    };
+
+    let Some(easing) = Easing::parse(&answer)
+    else {
+        console::warn_1(&JsValue::from(format!(
+            "Expected linear, ease-in-out or cubic-bezier(x1, y1, x2, y2) with both x from 0 to 1, got \"{answer}\""
+        )));
+        return;
+    };
+
+    state.easing.set(easing);
+
+    if !state.selection.borrow().is_empty() {
+        state.dot_preview.set(Some(DotPreview {
+            start: None,
+            progress: 0.0,
+        }));
+        request_animation_frame(state);
+    }
+}
+
+/// Whether any dashes are drawn and set to march.
+fn are_dashes_marching(state: &State) -> bool {
+    state.dash_speed.get() > 0.0 && state.curves.borrow().iter().any(|curve| curve.style.dashed)
+}
+
+/// Moves the dashes on by the time since the last frame. Frames keep coming
+/// from `redraw` only while the dashes are marching.
+fn step_dash_animation(state: &State, time: f64) {
+    if !are_dashes_marching(state) {
+        state.dash_time.set(None);
+        return;
+    }
+
+    if let Some(last) = state.dash_time.replace(Some(time)) {
+        // A negative offset moves dashes from `a` towards `d`.
+        let offset = state.dash_offset.get() - state.dash_speed.get() * (time - last) / 1000.0;
+        state.dash_offset.set(offset);
+    }
+}
+
+fn ease_in_out(t: f64) -> f64 {
+    if t < 0.5 {
+        4.0 * t * t * t
+    }
+    else {
+        1.0 - (-2.0 * t + 2.0).powi(3) / 2.0
+    }
+}
+
+/// Tells screen readers about `message` once edits pause for
+/// `ANNOUNCE_DELAY` milliseconds. Only the latest message is read, so rapid
+/// edits don't queue up a flood of them.
+fn announce(state: &State, message: String) {
+    let window = window().unwrap();
+
+    if let Some(timeout) = state.announce_timeout.take() {
+        window.clear_timeout_with_handle(timeout);
+    }
+
+    *state.announcement.borrow_mut() = message;
+
+    let callback = state.announce_callback.borrow();
+    let timeout = window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(
+            callback.as_ref().unwrap().as_ref().unchecked_ref(),
+            ANNOUNCE_DELAY,
+        )
+        .unwrap();
+
+    state.announce_timeout.set(Some(timeout));
+}
+
+/// Schedules the frame callback, unless a frame is already pending.
+fn request_animation_frame(state: &State) {
+    if state.frame_requested.replace(true) {
+        return;
+    }
+
+    let frame = state.animation_frame.borrow();
+
+    window()
+        .unwrap()
+        .request_animation_frame(frame.as_ref().unwrap().as_ref().unchecked_ref())
+        .unwrap();
+}
+
+/// Curves being drawn end to end.
+#[derive(Clone, Copy)]
+struct PenChain {
+    /// The chain's first anchor, where clicking closes it.
+    start: Point,
+    /// Curves drawn so far.
+    drawn: usize,
+}
+
+/// Whether a click at `mouse` would close the chain being drawn, by landing
+/// on its first anchor.
+fn closes_chain(state: &State, mouse: Point) -> bool {
+    let camera = state.camera.get();
+
+    state.pen_chain.get().is_some_and(|chain| {
+        chain.drawn > 0
+            && is_point_inside_circle(
+                camera.to_screen(chain.start),
+                state.handle_style.get().radius,
+                camera.to_screen(mouse),
+            )
+    })
+}
+
+/// Ends the chain being drawn, keeping its curves, and says whether the
+/// path they make is closed.
+fn finish_chain(state: &State) {
+    let Some(chain) = state.pen_chain.take()
+    else {
+        return;
+    };
+
+    state.new_curve.set(None);
+
+    if chain.drawn == 0 {
+        return;
+    }
+
+    let last = state.curves.borrow().len() - 1;
+    let closed = cached_subpaths(state)
+        .iter()
+        .any(|subpath| subpath.closed && subpath.curves.contains(&last));
+
+    announce(
+        state,
+        format!(
+            "{} path of {} curve{} finished",
+            if closed { "closed" } else { "open" },
+            chain.drawn,
+            if chain.drawn == 1 { "" } else { "s" }
+        ),
+    );
+}
+
+/// Ends the chain being drawn and removes its curves. They were added as
+/// one undoable step, so this is undoing it.
+fn cancel_chain(state: &State) {
+    let Some(chain) = state.pen_chain.take()
+    else {
+        return;
+    };
+
+    state.new_curve.set(None);
+
+    if chain.drawn > 0 {
+        undo(state);
+        announce(state, "path cancelled".to_string());
+    }
+}
+
+/// The curve the second click at `mouse` creates from `a`. It is straight,
+/// unless it continues a selected curve, in which case it leaves `a` along
+/// that curve's tangent. Holding snapping off makes a corner instead. It
+/// ends on the first anchor of the chain being drawn when that closes it.
+fn new_curve(state: &State, a: Point, mouse: Point) -> Curve {
+    let d = match state.pen_chain.get() {
+        Some(chain) if closes_chain(state, mouse) => chain.start,
+        _ => new_curve_end(state, a, mouse),
+    };
+    let tangent = state
+        .continued_tangent
+        .get()
+        .filter(|_| state.snap_settings().enabled);
+
+    match (state.new_curve_kind.get(), tangent) {
+        (CurveKind::Cubic, None) => Curve::line(a, d),
+        (CurveKind::Quadratic, None) => Curve::quadratic(a, a.lerp(d, 0.5), d),
+        (kind, Some((x, y))) => {
+            let length = distance(a, d);
+            let along = |fraction: f64| {
+                Point::new(a.x + x * length * fraction, a.y + y * length * fraction)
+            };
+
+            match kind {
+                CurveKind::Cubic => Curve::new(a, along(1.0 / 3.0), a.lerp(d, 2.0 / 3.0), d),
+                CurveKind::Quadratic => Curve::quadratic(a, along(0.5), d),
+            }
+        }
+    }
+}
+
+/// Which way a curve drawn from `point` should leave it to continue a
+/// selected curve ending there, the primary selection first.
+fn continued_tangent(state: &State, point: Point) -> Option<(f64, f64)> {
+    let curves = state.curves.borrow();
+
+    state.selection.borrow().iter().rev().find_map(|&index| {
+        let curve = &curves[index];
+        let angle = if curve.d == point {
+            curve.end_angle()
+        }
+        else if curve.a == point {
+            curve.start_angle() + PI
+        }
+        else {
+            return None;
+        };
+
+        Some((angle.cos(), angle.sin()))
+    })
+}
+
+/// Where a new curve starting at `a` ends when the second click is at
+/// `mouse`. Snapping onto an anchor wins; otherwise length snapping, when on,
+/// rounds the length and leaves the angle free, and otherwise the grid
+/// applies.
+fn new_curve_end(state: &State, a: Point, mouse: Point) -> Point {
+    match resolve_snap(state, mouse) {
+        (point, SnapKind::Anchor { .. }) => point,
+        (point, _) => state.snap_settings().snap_length(a, mouse).unwrap_or(point),
+    }
+}
+
+/// Splits the curve at `index` at `t`, where another curve's end was dropped
+/// onto it, so the two meet at a junction. Nothing happens too close to the
+/// curve's own ends. `dropped` is updated to the dropped end's new index.
+fn split_at_junction(state: &State, index: usize, t: f64, dropped: &mut DragState) {
+    if t <= INSERT_ANCHOR_MARGIN || t >= 1.0 - INSERT_ANCHOR_MARGIN {
+        return;
+    }
+
+    let (first, second) = state.curves.borrow()[index].clone().split(t);
+    let mut curves = state.curves.borrow_mut();
+
+    curves.splice(index..=index, [first.clone(), second]);
+
+    if dropped.curve_index > index {
+        dropped.curve_index += 1;
+    }
+
+    // Exactly on the split point, so the ends count as meeting.
+    *curves[dropped.curve_index].point_mut(dropped.point) = first.d;
+    enforce_straight_locks(&mut curves);
+    drop(curves);
+
+    forget_stale_indices(state);
+    shift_past_split(state, index, 2);
+}
+
+/// Makes the selected curves exactly straight, but only those already within
+/// `STRAIGHTEN_TOLERANCE` of their chord, so a curve with a visible bend keeps
+/// its shape. The others are reported.
+fn straighten_nearly_straight(state: &State) {
+    let tolerance = STRAIGHTEN_TOLERANCE / state.camera.get().zoom;
+    let selection = state.selection.borrow().clone();
+    let mut straightened = 0;
+
+    transaction(state, || {
+        for &index in &selection {
+            let deviation = state.curves.borrow()[index].chord_deviation(tolerance / 10.0);
+
+            if deviation > tolerance {
+                console::warn_1(&JsValue::from(format!(
+                    "Curve {index} strays {deviation:.2} from straight, more than {tolerance:.2}"
+                )));
+                continue;
+            }
+
+            state.curves.borrow_mut()[index].straighten();
+            straightened += 1;
+        }
+    });
+
+    if !selection.is_empty() {
+        announce(
+            state,
+            format!("{straightened} of {} curves straightened", selection.len()),
+        );
+    }
+}
+
+/// Makes `handle` of the curve at `index` the one arrow keys and handle
+/// commands act on. Anchors are also remembered for commands that take two.
+fn set_active_handle(state: &State, index: usize, handle: PointHandle) {
+    state.active_handle.set(Some((index, handle)));
+
+    if matches!(handle, PointHandle::A | PointHandle::D)
+        && state.active_anchor.get() != Some((index, handle))
+    {
+        state.previous_anchor.set(state.active_anchor.get());
+        state.active_anchor.set(Some((index, handle)));
+    }
+}
+
+/// Moves the active handle `step` places along its curve's points, in the
+/// order `a`, `b`, `c`, `d` and round again, skipping control points that
+/// aren't shown. Returns whether there was an active handle.
+fn step_active_handle(state: &State, step: isize) -> bool {
+    let Some((index, handle)) = state.active_handle.get()
+    else {
+        return false;
+    };
+
+    let curve = state.curves.borrow()[index].clone();
+    let handles: Vec<PointHandle> = [
+        PointHandle::A,
+        PointHandle::B,
+        PointHandle::C,
+        PointHandle::D,
+    ]
+    .into_iter()
+    .filter(|&handle| match handle {
+        PointHandle::A | PointHandle::D => true,
+        PointHandle::B => !curve.locked_straight,
+        PointHandle::C => !curve.locked_straight && curve.kind == CurveKind::Cubic,
+    })
+    .collect();
+
+    let position = handles
+        .iter()
+        .position(|&other| other == handle)
+        .unwrap_or(0);
+    let next = (position as isize + step).rem_euclid(handles.len() as isize) as usize;
+
+    set_active_handle(state, index, handles[next]);
+    true
+}
+
+/// Asks how many pieces of equal length to cut the primary selection into,
+/// and cuts it.
+fn split_evenly(state: &State) {
+    let Some(index) = state.selected()
+    else {
+        return;
+    };
+
+    let Some(answer) = window()
+        .unwrap()
+        .prompt_with_message_and_default("Split into how many pieces of equal length?", "2")
+        .unwrap()
+    else {
+        return;
+    };
+
+    let Some(n) = answer.trim().parse::<usize>().ok().filter(|&n| n >= 2)
+    else {
+        console::warn_1(&JsValue::from(format!(
+            "Expected a number of pieces of at least 2, got \"{answer}\""
+        )));
+        return;
+    };
+
+    let pieces = state.curves.borrow()[index].split_by_arclen(n);
+
+    transaction(state, || {
+        state.curves.borrow_mut().splice(index..=index, pieces);
+    });
+
+    forget_stale_indices(state);
+    shift_past_split(state, index, n);
+    announce(state, format!("curve {index} split into {n} pieces"));
+}
+
+/// Moves the anchors inside the primary selection's path so they are evenly
+/// spaced along it, fitting each curve again to the part of the path it now
+/// covers. Refuses when the refitted path would strain further than
+/// `REDISTRIBUTE_TOLERANCE` from the old one, or the path branches.
+fn redistribute_anchors(state: &State) {
+    let Some(selected) = state.selected()
+    else {
+        return;
+    };
+
+    let Some(subpath) = cached_subpaths(state)
+        .into_iter()
+        .find(|subpath| subpath.curves.contains(&selected))
+    else {
+        return;
+    };
+
+    let curves = state.curves.borrow();
+
+    let [chain] = &topology::chains(&curves, &subpath.curves)[..]
+    else {
+        announce(state, "path branches, anchors not moved".to_string());
+        return;
+    };
+
+    if chain.curves.len() < 2 {
+        return;
+    }
+
+    let path: Vec<Curve> = chain
+        .curves
+        .iter()
+        .map(|&(index, reversed)| curves[index].clone().reversed_if(reversed))
+        .collect();
+    let refitted = redistribute::redistribute(&path);
+    let deviation = redistribute::deviation(&path, &refitted);
+    drop(curves);
+
+    if deviation > REDISTRIBUTE_TOLERANCE {
+        announce(
+            state,
+            format!("anchors not moved, the path would change shape by {deviation:.1}"),
+        );
+        return;
+    }
+
+    transaction(state, || {
+        let mut curves = state.curves.borrow_mut();
+
+        for (&(index, reversed), new) in chain.curves.iter().zip(refitted) {
+            let old = curves[index].clone();
+            let new = new.reversed_if(reversed);
+
+            curves[index] = Curve {
+                a: new.a,
+                b: new.b,
+                c: new.c,
+                d: new.d,
+                kind: new.kind,
+                pin: None,
+                locks: [AxisLock::None; 4],
+                locked_straight: false,
+                weights: None,
+                ..old
+            };
+        }
+    });
+
+    announce(
+        state,
+        format!("{} anchors spaced evenly", chain.curves.len() - 1),
+    );
+}
+
+/// Keeps the selection and dimensions on the same curves after the curve at
+/// `index` was split into `pieces`. Its end now belongs to the last piece.
+fn shift_past_split(state: &State, index: usize, pieces: usize) {
+    let added = pieces - 1;
+
+    for selected in state.selection.borrow_mut().iter_mut() {
+        if *selected > index {
+            *selected += added;
+        }
+    }
+
+    dimension::remap_dimensions(state, |curve, handle| {
+        if curve > index || curve == index && handle == PointHandle::D {
+            Some((curve + added, handle))
+        }
+        else {
+            Some((curve, handle))
+        }
+    });
+}
+
+/// Suspends or restores snapping for as long as Alt is held, updating where a
+/// dragged point would land.
+fn hold_snapping_off(state: &State, held: bool) {
+    state.snap_held_off.set(held);
+
+    if let Some(drag_state) = state.drag_state.get() {
+        state.snap_target.set(find_snap_target(state, drag_state));
+    }
+}
+
+/// Where the dragged point will land when released, as decided by
+/// `resolve_snap`. A dragged endpoint landing on another one also lines up
+/// with its tangent.
+fn find_snap_target(state: &State, drag_state: DragState) -> Option<SnapTarget> {
+    let dragged = state.curves.borrow()[drag_state.curve_index].point(drag_state.point);
+
+    let (point, kind) = resolve_snap(state, dragged);
+
+    let direction = match kind {
+        SnapKind::None => return None,
+        SnapKind::Grid | SnapKind::Curve { .. } => None,
+        SnapKind::Anchor { .. } if !matches!(drag_state.point, PointHandle::A | PointHandle::D) => {
+            None
+        }
+        SnapKind::Anchor {
+            curve_index,
+            anchor,
+        } => {
+            let curve = state.curves.borrow()[curve_index].clone();
+
+            match anchor {
+                PointHandle::A => curve.tangent_at(0.0),
+                _ => curve.tangent_at(1.0).map(|(x, y)| (-x, -y)),
+            }
+        }
+    };
+
+    Some(SnapTarget {
+        point,
+        direction,
+        kind,
+    })
+}
+
+/// The smallest box holding every curve, or `None` if there are none.
+fn scene_bounds(state: &State) -> Option<(Point, Point)> {
+    curves_bounds(&state.curves.borrow())
+}
+
+/// Bounds of all of `curves` as `(min, max)`, if there are any.
+fn curves_bounds(curves: &[Curve]) -> Option<(Point, Point)> {
+    curves
+        .iter()
+        .map(Curve::bounding_box)
+        .reduce(|(min, max), (curve_min, curve_max)| {
+            (
+                Point::new(min.x.min(curve_min.x), min.y.min(curve_min.y)),
+                Point::new(max.x.max(curve_max.x), max.y.max(curve_max.y)),
+            )
+        })
+}
+
+struct State {
+    /// Tells editors on the same page apart.
+    id: usize,
+    curves: RefCell<Vec<Curve>>,
+    drag_state: Cell<Option<DragState>>,
+    new_curve: Cell<Option<Point>>,
+    /// Whether clicks draw curves end to end, each starting where the last
+    /// ended, rather than one curve per pair of clicks.
+    chain_curves: Cell<bool>,
+    /// The chain being drawn, while `chain_curves` is on.
+    pen_chain: Cell<Option<PenChain>>,
+    /// Direction the curve being created leaves its start in, when it
+    /// starts at a selected curve's end and so continues it smoothly.
+    continued_tangent: Cell<Option<(f64, f64)>>,
+    mouse: Cell<Point>,
+    mode: Cell<Mode>,
+    snap: Cell<SnapSettings>,
+    /// Whether Alt is held, suspending snapping while dragging or drawing.
+    snap_held_off: Cell<bool>,
+    /// Kind of curve the next pair of clicks creates.
+    new_curve_kind: Cell<CurveKind>,
+    create_binding: Cell<CreateBinding>,
+    /// Whether the arrow keys lengthen and shorten the grabbed control
+    /// handle rather than move it by pixels or grid steps.
+    relative_nudge: Cell<bool>,
+    /// Whether imported closed paths are reversed as needed so outer
+    /// contours run counterclockwise and holes clockwise.
+    normalize_winding: Cell<bool>,
+    /// Style the eyedropper applies to clicked curves, once one is picked.
+    picked_style: Cell<Option<Style>>,
+    /// Selected curve indices, without duplicates. The last one is the
+    /// primary selection that single-curve commands act on.
+    selection: RefCell<Vec<usize>>,
+    show_tangent_extensions: Cell<bool>,
+    show_crosshair: Cell<bool>,
+    /// Whether the primary selection's osculating circle is drawn, at its
+    /// midpoint or at the scrub marker.
+    show_osculating_circle: Cell<bool>,
+    /// Whether lines from points along the primary selection to its control
+    /// points show how much each control point pulls on each part of it.
+    show_influence: Cell<bool>,
+    /// Whether every handle is labelled with its curve's index and its
+    /// letter, for matching what is drawn to `curves` when debugging.
+    show_handle_labels: Cell<bool>,
+    /// Whether a box with handles for scaling and rotating is drawn around
+    /// the selection.
+    show_gizmo: Cell<bool>,
+    /// Whether the canvas's own rectangle at the default view is outlined,
+    /// to frame exports by.
+    show_page: Cell<bool>,
+    /// Whether junctions where the path turns sharply are marked, to find
+    /// corners that were meant to be smooth.
+    show_kinks: Cell<bool>,
+    /// Whether grid points are labelled with their coordinates.
+    show_grid_labels: Cell<bool>,
+    /// Whether the primary selection's points are listed beside it.
+    show_point_table: Cell<bool>,
+    show_rulers: Cell<bool>,
+    /// World position of the pointer while it is over the canvas.
+    cursor: Cell<Option<Point>>,
+    camera: Cell<Camera>,
+    next_group: Cell<u32>,
+    /// Where the dragged point will snap to, tangent included, when
+    /// released.
+    snap_target: Cell<Option<SnapTarget>>,
+    /// Decimal places kept for coordinates in text exports.
+    export_precision: Cell<usize>,
+    /// Whether view changes ease into place instead of jumping.
+    animate_view: Cell<bool>,
+    view_animation: Cell<Option<ViewAnimation>>,
+    /// Screen position the view was last panned to, while panning.
+    pan_origin: Cell<Option<Point>>,
+    /// Where the selection rotates about, when placed by the user rather than
+    /// at the selection's centroid.
+    rotation_pivot: Cell<Option<Point>>,
+    /// The rotation being dragged.
+    rotation: Cell<Option<Rotation>>,
+    /// The scaling being dragged with the gizmo.
+    scaling: Cell<Option<Scaling>>,
+    /// Two points on the line the selection is mirrored across, while
+    /// mirroring.
+    mirror_axis: Cell<Option<(Point, Point)>>,
+    /// Whether the mirror axis is being dragged out.
+    drawing_mirror_axis: Cell<bool>,
+    /// Runs once per requested animation frame; set up right after the state
+    /// is created since it needs a handle to it.
+    animation_frame: RefCell<Option<FrameCallback>>,
+    frame_requested: Cell<bool>,
+    /// How the dot moves in path animations and their preview.
+    easing: Cell<Easing>,
+    /// The preview of the dot travelling along the selection, while it plays.
+    dot_preview: Cell<Option<DotPreview>>,
+    scene_layer: SceneLayer,
+    /// How fast dashes march along dashed curves, in pixels per second; 0
+    /// keeps them still.
+    dash_speed: Cell<f64>,
+    dash_offset: Cell<f64>,
+    /// When the dashes last moved, while they are marching.
+    dash_time: Cell<Option<f64>>,
+    /// Arc lengths by curve index, filled in lazily by `cached_arc_length`.
+    arc_lengths: RefCell<ArcLengthCache>,
+    /// Subpaths along with the curve ends they were found from, refreshed by
+    /// `cached_subpaths` when the ends change.
+    subpaths: RefCell<SubpathCache>,
+    /// List of curves in draw order, kept in sync by `redraw`.
+    layers_panel: HtmlElement,
+    /// Shortcuts for the current mode, shown on demand.
+    help_panel: HtmlElement,
+    /// Lengths and angles of the primary selection's handles, editable.
+    handles_panel: HtmlElement,
+    /// The curves as they were when a field of `handles_panel` got focus, so
+    /// that typing into it is undone in one step.
+    numeric_origin: RefCell<Option<Vec<Curve>>>,
+    /// Where clicking would insert an anchor, by curve index and parameter,
+    /// while inserting anchors.
+    anchor_preview: Cell<Option<(usize, f64)>>,
+    /// Curve index of the layers panel item being dragged.
+    dragged_layer: Cell<Option<usize>>,
+    history: RefCell<History>,
+    /// The curves as they were when the current drag started.
+    drag_origin: RefCell<Vec<Curve>>,
+    /// The anchor last grabbed, by curve index, which anchor commands act on.
+    active_anchor: Cell<Option<(usize, PointHandle)>>,
+    /// The anchor grabbed before `active_anchor`, for commands that take two.
+    previous_anchor: Cell<Option<(usize, PointHandle)>>,
+    /// Measurements between anchors, kept pointing at the same anchors as
+    /// curves are removed, reordered and split.
+    dimensions: RefCell<Vec<Dimension>>,
+    /// The handle last grabbed, of any kind.
+    active_handle: Cell<Option<(usize, PointHandle)>>,
+    /// The range of a curve being trimmed, while trim handles are shown.
+    trim: Cell<Option<Trim>>,
+    /// The trim handle being dragged.
+    trim_drag: Cell<Option<TrimEnd>>,
+    /// Parameter of the marker on the primary selection, while shown. The
+    /// curve overlays are drawn there, and exported frames start there.
+    scrub: Cell<Option<f64>>,
+    /// Whether the scrub marker is being dragged.
+    scrubbing: Cell<bool>,
+    handle_style: Cell<HandleStyle>,
+    /// Shadow cast by curves, on screen and in PNG exports. Off by default,
+    /// since shadows are slow to draw.
+    shadow: Cell<Option<Shadow>>,
+    /// Accessibility mode: thicker strokes and high-contrast handles.
+    high_contrast: Cell<bool>,
+    /// Live region that screen readers read out.
+    announcer: HtmlElement,
+    /// Text waiting to be put into `announcer`.
+    announcement: RefCell<String>,
+    announce_timeout: Cell<Option<i32>>,
+    /// Moves `announcement` into `announcer`; set up right after the state is
+    /// created since it needs a handle to it.
+    announce_callback: RefCell<Option<Closure<dyn FnMut()>>>,
+}
+
+#[derive(Clone, Copy)]
+struct Trim {
+    curve_index: usize,
+    start: f64,
+    end: f64,
+}
+
+impl Trim {
+    fn new(curve_index: usize) -> Self {
+        Self {
+            curve_index,
+            start: 0.0,
+            end: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum TrimEnd {
+    Start,
+    End,
+}
+
+impl State {
+    /// A fresh editor with an empty scene, showing its panels and
+    /// announcements in the given elements.
+    fn new(
+        id: usize,
+        layers_panel: HtmlElement,
+        help_panel: HtmlElement,
+        handles_panel: HtmlElement,
+        announcer: HtmlElement,
+        scene_layer: SceneLayer,
+    ) -> Self {
+        Self {
+            id,
+            curves: RefCell::new(Vec::new()),
+            drag_state: Cell::new(None),
+            new_curve: Cell::new(None),
+            chain_curves: Cell::new(false),
+            pen_chain: Cell::new(None),
+            continued_tangent: Cell::new(None),
+            mouse: Cell::new(Point::new(0.0, 0.0)),
+            mode: Cell::new(Mode::Edit),
+            snap: Cell::new(SnapSettings::default()),
+            snap_held_off: Cell::new(false),
+            new_curve_kind: Cell::new(CurveKind::Cubic),
+            create_binding: Cell::new(CreateBinding::Click),
+            relative_nudge: Cell::new(false),
+            normalize_winding: Cell::new(false),
+            picked_style: Cell::new(None),
+            selection: RefCell::new(Vec::new()),
+            show_tangent_extensions: Cell::new(false),
+            show_crosshair: Cell::new(false),
+            show_osculating_circle: Cell::new(false),
+            show_influence: Cell::new(false),
+            show_handle_labels: Cell::new(false),
+            show_gizmo: Cell::new(false),
+            show_page: Cell::new(false),
+            show_kinks: Cell::new(false),
+            show_grid_labels: Cell::new(false),
+            show_point_table: Cell::new(true),
+            show_rulers: Cell::new(false),
+            cursor: Cell::new(None),
+            camera: Cell::new(Camera::default()),
+            next_group: Cell::new(0),
+            snap_target: Cell::new(None),
+            export_precision: Cell::new(DEFAULT_EXPORT_PRECISION),
+            animate_view: Cell::new(true),
+            view_animation: Cell::new(None),
+            pan_origin: Cell::new(None),
+            rotation_pivot: Cell::new(None),
+            rotation: Cell::new(None),
+            scaling: Cell::new(None),
+            mirror_axis: Cell::new(None),
+            drawing_mirror_axis: Cell::new(false),
+            animation_frame: RefCell::new(None),
+            frame_requested: Cell::new(false),
+            easing: Cell::new(Easing::Linear),
+            dot_preview: Cell::new(None),
+            scene_layer,
+            dash_speed: Cell::new(0.0),
+            dash_offset: Cell::new(0.0),
+            dash_time: Cell::new(None),
+            arc_lengths: RefCell::new(ArcLengthCache::default()),
+            subpaths: RefCell::new((Vec::new(), Vec::new())),
+            layers_panel,
+            help_panel,
+            handles_panel,
+            numeric_origin: RefCell::new(None),
+            anchor_preview: Cell::new(None),
+            dragged_layer: Cell::new(None),
+            history: RefCell::new(History::default()),
+            drag_origin: RefCell::new(Vec::new()),
+            active_anchor: Cell::new(None),
+            previous_anchor: Cell::new(None),
+            dimensions: RefCell::new(Vec::new()),
+            active_handle: Cell::new(None),
+            trim: Cell::new(None),
+            trim_drag: Cell::new(None),
+            scrub: Cell::new(None),
+            scrubbing: Cell::new(false),
+            handle_style: Cell::new(HandleStyle::default()),
+            shadow: Cell::new(None),
+            high_contrast: Cell::new(false),
+            announcer,
+            announcement: RefCell::new(String::new()),
+            announce_timeout: Cell::new(None),
+            announce_callback: RefCell::new(None),
+        }
+    }
+
+    /// The primary selection.
+    fn selected(&self) -> Option<usize> {
+        self.selection.borrow().last().copied()
+    }
+
+    /// The snap settings in effect, with snapping off while Alt holds it
+    /// off.
+    fn snap_settings(&self) -> SnapSettings {
+        let mut snap = self.snap.get();
+        snap.enabled &= !self.snap_held_off.get();
+        snap
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    /// Clicks create curves and grab handles.
+    Edit,
+    /// Clicks copy a picked style onto curves.
+    Eyedropper,
+    /// Only the curves are drawn, as they would export, and clicks do
+    /// nothing.
+    Preview,
+    /// Clicks split curves, adding an anchor where they are clicked.
+    InsertAnchor,
+    /// Dragging rotates the selection about a pivot, which Alt-clicking
+    /// places.
+    Rotate,
+    /// Dragging draws an axis to mirror a copy of the selection across.
+    Mirror,
+}
+
+/// Which clicks on empty canvas in edit mode create curves. Clicks that
+/// don't only clear the selection, so selecting and dragging can't leave
+/// stray curves behind. By default a plain click creates.
+#[derive(Clone, Copy, PartialEq)]
+enum CreateBinding {
+    Click,
+    /// Only clicks with Ctrl, or Cmd on macOS, create.
+    ModifierClick,
+    /// Clicks only select.
+    Never,
+}
+
+impl CreateBinding {
+    fn next(self) -> Self {
+        match self {
+            CreateBinding::Click => CreateBinding::ModifierClick,
+            CreateBinding::ModifierClick => CreateBinding::Never,
+            CreateBinding::Never => CreateBinding::Click,
+        }
+    }
+
+    fn allows(self, event: &MouseEvent) -> bool {
+        match self {
+            CreateBinding::Click => true,
+            CreateBinding::ModifierClick => event.ctrl_key() || event.meta_key(),
+            CreateBinding::Never => false,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            CreateBinding::Click => "Clicks create curves",
+            CreateBinding::ModifierClick => "Ctrl+clicks create curves",
+            CreateBinding::Never => "Clicks only select",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Similarity {
+    Color,
+    Width,
+    Closed,
+}
+
+impl Similarity {
+    fn matches(self, curve: &Curve, reference: &Curve) -> bool {
+        match self {
+            Similarity::Color => curve.style.color == reference.style.color,
+            Similarity::Width => curve.style.width == reference.style.width,
+            Similarity::Closed => curve.is_closed() == reference.is_closed(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct SnapTarget {
+    point: Point,
+    /// Unit tangent of the target curve leading away from `point`, if it has
+    /// one. The dragged curve is lined up with it so the join is smooth.
+    direction: Option<(f64, f64)>,
+    kind: SnapKind,
+}
+
+impl SnapTarget {
+    /// Where the control point next to the dragged `anchor` of `curve` goes so
+    /// the curve arrives at the target along its tangent, keeping the
+    /// handle's length. `None` if there is no tangent to match or the handle
+    /// has no length.
+    fn aligned_handle(&self, curve: Curve, anchor: PointHandle) -> Option<(PointHandle, Point)> {
+        let (x, y) = self.direction?;
+        let handle = curve.handle_of(anchor);
+
+        let length = distance(curve.point(handle), self.point);
+
+        if length == 0.0 {
+            return None;
+        }
+
+        Some((
+            handle,
+            Point::new(self.point.x - x * length, self.point.y - y * length),
+        ))
+    }
+}
+
+type FrameCallback = Closure<dyn FnMut(f64)>;
+
+/// Each curve's ends, then the subpaths they make up.
+type SubpathCache = (Vec<(Point, Point)>, Vec<Subpath>);
+
+/// What `render_scene` draws and where.
+#[derive(Clone, Copy)]
+struct RenderOptions {
+    camera: Camera,
+    /// Size of the target in pixels, for culling and the background.
+    width: f64,
+    height: f64,
+    /// Fill color painted under the curves; transparent if `None`.
+    background: Option<&'static str>,
+    /// Shifts the dashes of dashed curves along them.
+    dash_offset: f64,
+    /// Handles, pins, and selection marks are only drawn with a style.
+    handles: Option<HandleStyle>,
+    /// Multiplies every curve's stroke width.
+    stroke_scale: f64,
+    /// Cast by every curve's stroke.
+    shadow: Option<Shadow>,
+    /// Spreads stroking the curves over several frames when it would take
+    /// longer than `FRAME_BUDGET`, through `State::scene_layer`.
+    budgeted: bool,
+}
+
+/// Curves stroked as one line.
+#[derive(Clone, PartialEq)]
+struct Stroke {
+    /// In screen pixels, each starting where the previous one ends.
+    curves: Vec<Curve>,
+    closed: bool,
+    /// Whether the inside of the line, for aligned strokes, is on its left.
+    left_inside: bool,
+}
+
+/// An offscreen canvas holding the curves' strokes as far as they have been
+/// drawn, so that drawing a huge scene can carry on across frames. Only the
+/// picture is spread out: everything else, hit testing included, works on
+/// the whole scene at once.
+struct SceneLayer {
+    canvas: HtmlCanvasElement,
+    context: CanvasRenderingContext2d,
+    /// What the layer is being drawn for; it starts over when this changes.
+    key: RefCell<Option<LayerKey>>,
+    /// How many of the key's strokes are in the layer.
+    progress: Cell<usize>,
+    /// A copy of the layer as it was last drawn to the end.
+    finished: HtmlCanvasElement,
+    finished_context: CanvasRenderingContext2d,
+    /// Whether `finished` holds anything yet.
+    has_finished: Cell<bool>,
+}
+
+#[derive(PartialEq)]
+struct LayerKey {
+    strokes: Vec<Stroke>,
+    dash_offset: f64,
+    shadow: Option<Shadow>,
+    width: u32,
+    height: u32,
+}
+
+impl SceneLayer {
+    fn new() -> Self {
+        let (canvas, context) = offscreen_canvas(0, 0);
+        let (finished, finished_context) = offscreen_canvas(0, 0);
+
+        Self {
+            canvas,
+            context,
+            key: RefCell::new(None),
+            progress: Cell::new(0),
+            finished,
+            finished_context,
+            has_finished: Cell::new(false),
+        }
+    }
+
+    /// Copies the layer, just drawn to the end, into `finished`.
+    fn keep_finished(&self) {
+        let (width, height) = (self.canvas.width(), self.canvas.height());
+
+        if (self.finished.width(), self.finished.height()) != (width, height) {
+            self.finished.set_width(width);
+            self.finished.set_height(height);
+        }
+
+        self.finished_context
+            .clear_rect(0.0, 0.0, width as f64, height as f64);
+        self.finished_context
+            .draw_image_with_html_canvas_element(&self.canvas, 0.0, 0.0)
+            .unwrap();
+        self.has_finished.set(true);
+    }
+
+    /// Strokes still to be drawn, and all of them, while the layer is
+    /// incomplete.
+    fn pending(&self) -> Option<(usize, usize)> {
+        let total = self.key.borrow().as_ref()?.strokes.len();
+        let progress = self.progress.get();
+
+        (progress < total).then_some((total - progress, total))
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Rotation {
+    pivot: Point,
+    /// Angle of the pointer about the pivot when the drag started.
+    start_angle: f64,
+}
+
+#[derive(Clone, Copy)]
+struct Scaling {
+    /// Sides of the box the dragged handle is on, as for
+    /// `GizmoHandle::Scale`.
+    sides: (f64, f64),
+    /// The selection's box when the drag started, in world units.
+    bounds: (Point, Point),
+}
+
+/// A handle of the transform gizmo.
+#[derive(Clone, Copy)]
+enum GizmoHandle {
+    /// Scales the selection away from the opposite side of its box. Each
+    /// side is -1 for the left or top, 1 for the right or bottom, or 0 for a
+    /// handle in the middle, which leaves that axis alone.
+    Scale(f64, f64),
+    /// Rotates the selection about the middle of its box.
+    Rotate,
+}
+
+#[derive(Clone, Copy)]
+struct DotPreview {
+    /// Timestamp of the first frame, filled in once that frame runs.
+    start: Option<f64>,
+    /// From 0 to 1 over `DOT_PREVIEW_DURATION`.
+    progress: f64,
+}
+
+#[derive(Clone, Copy)]
+struct ViewAnimation {
+    from: Camera,
+    to: Camera,
+    /// Timestamp of the first frame, filled in once that frame runs.
+    start: Option<f64>,
+}
+
+/// Maps world coordinates, where curves live, to canvas pixels:
+/// `screen = world * zoom + offset`.
+#[derive(Clone, Copy, PartialEq)]
+struct Camera {
+    offset: Point,
+    zoom: f64,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            offset: Point::new(0.0, 0.0),
+            zoom: 1.0,
+        }
+    }
+}
+
+impl Camera {
+    /// The view that centers the `(min, max)` box in a `width` by `height`
+    /// area, as large as fits with a `FIT_MARGIN` margin.
+    fn fitting((min, max): (Point, Point), width: f64, height: f64) -> Camera {
+        let zoom = match (max.x - min.x, max.y - min.y) {
+            (0.0, 0.0) => 1.0,
+            (w, h) => {
+                (width * (1.0 - 2.0 * FIT_MARGIN) / w).min(height * (1.0 - 2.0 * FIT_MARGIN) / h)
+            }
+        };
+
+        let center = Point::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+
+        Camera {
+            offset: Point::new(
+                width / 2.0 - center.x * zoom,
+                height / 2.0 - center.y * zoom,
+            ),
+            zoom,
+        }
+    }
+
+    fn to_screen(self, point: Point) -> Point {
+        Point::new(
+            point.x * self.zoom + self.offset.x,
+            point.y * self.zoom + self.offset.y,
+        )
+    }
+
+    fn to_world(self, point: Point) -> Point {
+        Point::new(
+            (point.x - self.offset.x) / self.zoom,
+            (point.y - self.offset.y) / self.zoom,
+        )
+    }
+
+    fn curve_to_screen(self, curve: Curve) -> Curve {
+        curve.map(|point| self.to_screen(point))
+    }
+
+    fn lerp(self, other: Camera, t: f64) -> Camera {
+        Camera {
+            offset: self.offset.lerp(other.offset, t),
+            zoom: self.zoom + (other.zoom - self.zoom) * t,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct DragState {
+    curve_index: usize,
+    point: PointHandle,
+}
+
+impl DragState {
+    fn new(curve_index: usize, point: PointHandle) -> Self {
+        Self { curve_index, point }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum PointHandle {
+    A,
+    B,
+    C,
+    D,
+}
+
+/// The handle under `screen` that is seen on top, as a curve index and
+/// handle. Handles are drawn in curve order over every stroke, so the last
+/// curve's win. Within a curve, anchors win over control points on them.
+fn handle_at(
+    curves: &[Curve],
+    camera: Camera,
+    screen: Point,
+    radius: f64,
+) -> Option<(usize, PointHandle)> {
+    curves.iter().enumerate().rev().find_map(|(index, curve)| {
+        hit_test_handle(camera.curve_to_screen(curve.clone()), screen, radius)
+            .map(|handle| (index, handle))
+    })
+}
+
+/// Which point of `curve` is within `radius` of `point`. The control points
+/// of a curve locked straight can't be grabbed.
+fn hit_test_handle(curve: Curve, point: Point, radius: f64) -> Option<PointHandle> {
+    if is_point_inside_circle(curve.a, radius, point) {
+        return Some(PointHandle::A);
+    }
+
+    let controls = !curve.locked_straight;
+
+    if controls && is_point_inside_circle(curve.b, radius, point) {
+        return Some(PointHandle::B);
+    }
+
+    if controls && curve.kind == CurveKind::Cubic && is_point_inside_circle(curve.c, radius, point)
+    {
+        return Some(PointHandle::C);
+    }
+
+    if is_point_inside_circle(curve.d, radius, point) {
+        return Some(PointHandle::D);
+    }
+
+    None
+}
+
+/// The topmost curve with a handle or part of its body under `screen`.
+fn hit_test_curve(state: &State, screen: Point) -> Option<usize> {
+    let camera = state.camera.get();
+    let radius = state.handle_style.get().radius;
+
+    state.curves.borrow().iter().rposition(|curve| {
+        let curve = camera.curve_to_screen(curve.clone());
+
+        if !is_near_bounds(
+            curve.control_bounds(),
+            screen,
+            CURVE_HIT_TOLERANCE.max(radius),
+        ) {
+            return false;
+        }
+
+        hit_test_handle(curve.clone(), screen, radius).is_some()
+            || curve.distance_to(screen) <= CURVE_HIT_TOLERANCE
+    })
+}
+
+/// The topmost curve whose body passes within `tolerance` of `point`, both in
+/// world units.
+fn curve_at(state: &State, point: Point, tolerance: f64) -> Option<usize> {
+    state.curves.borrow().iter().rposition(|curve| {
+        is_near_bounds(curve.control_bounds(), point, tolerance)
+            && curve.distance_to(point) <= tolerance
+    })
+}
+
+/// Whether `point` is within `margin` of the `(min, max)` box. A cheap
+/// first check before precise hit testing.
+fn is_near_bounds((min, max): (Point, Point), point: Point, margin: f64) -> bool {
+    min.x - margin <= point.x
+        && point.x <= max.x + margin
+        && min.y - margin <= point.y
+        && point.y <= max.y + margin
+}
+
+/// Whether anything drawn for `curve`, in screen pixels, can show on a
+/// `width` by `height` canvas. The margin covers strokes, halos and handle
+/// dots of `handle_radius` poking out of the control points' bounds.
+fn is_on_screen(curve: &Curve, width: f64, height: f64, handle_radius: f64) -> bool {
+    let margin = curve.style.width + SELECTION_HALO_WIDTH + handle_radius;
+    let (min, max) = curve.control_bounds();
+
+    max.x >= -margin && max.y >= -margin && min.x <= width + margin && min.y <= height + margin
+}
+
+fn distance(p: Point, q: Point) -> f64 {
+    (p.x - q.x).hypot(p.y - q.y)
+}
+
+fn is_point_inside_circle(center: Point, radius: f64, point: Point) -> bool {
+    (center.x - point.x).powi(2) + (center.y - point.y).powi(2) <= radius.powi(2)
+}
+
+/// Marks `curve` as selected with a wide stroke to be drawn over.
+fn draw_selection_halo(context: &CanvasRenderingContext2d, curve: Curve) {
+    context.set_stroke_style(&JsString::from(SELECTION_COLOR));
+    context.set_line_width(curve.style.width + SELECTION_HALO_WIDTH);
+    context.begin_path();
+    trace_curve(context, curve);
+    context.stroke();
+}
+
+/// Writes the curve's index and the handle's letter, like `3B`, beside each
+/// of `curve`'s handles, clear of dots `radius` in size.
+fn draw_handle_labels(context: &CanvasRenderingContext2d, index: usize, curve: Curve, radius: f64) {
+    let handles: &[(PointHandle, &str)] = match curve.kind {
+        CurveKind::Cubic => &[
+            (PointHandle::A, "A"),
+            (PointHandle::B, "B"),
+            (PointHandle::C, "C"),
+            (PointHandle::D, "D"),
+        ],
+        CurveKind::Quadratic => &[
+            (PointHandle::A, "A"),
+            (PointHandle::B, "B"),
+            (PointHandle::D, "D"),
+        ],
+    };
+
+    context.set_font(HANDLE_LABEL_FONT);
+    context.set_fill_style(&JsString::from("rgba(0, 0, 0, 0.7)"));
+
+    for &(handle, letter) in handles {
+        let point = curve.point(handle);
+
+        context
+            .fill_text(
+                &format!("{index}{letter}"),
+                point.x + radius + 2.0,
+                point.y - radius - 2.0,
+            )
+            .unwrap();
+    }
+}
+
+/// Draws `curve`'s handles, the lines to them, and its axis locks.
+fn draw_handles(context: &CanvasRenderingContext2d, curve: Curve, handle_style: HandleStyle) {
+    let Curve { a, b, c, d, .. } = curve;
+
+    context.set_stroke_style(&JsString::from(handle_style.line_color));
+    context.set_line_width(handle_style.line_width);
+
+    if handle_style.dashed_lines {
+        context.set_line_dash_offset(0.0);
+
+        let dash = Array::of2(&JsValue::from(4.0), &JsValue::from(4.0));
+        context.set_line_dash(&dash).unwrap();
+    }
+
+    context.begin_path();
+    context.move_to(a.x, a.y);
+    context.line_to(b.x, b.y);
+
+    // A quadratic's single control point is shared by both ends.
+    let d_handle = match curve.kind {
+        CurveKind::Cubic => c,
+        CurveKind::Quadratic => b,
+    };
+
+    if handle_style.connected_polygon {
+        context.line_to(d_handle.x, d_handle.y);
+        context.line_to(d.x, d.y);
+    }
+    else {
+        context.move_to(d.x, d.y);
+        context.line_to(d_handle.x, d_handle.y);
+    }
+
+    context.stroke();
+
+    context.set_line_dash(&Array::new()).unwrap();
+    context.set_line_width(1.0);
+
+    context.begin_path();
+
+    for (point, anchor_type) in [(a, curve.a_type), (d, curve.d_type)] {
+        let shape = match handle_style.anchor_shape {
+            AnchorShape::ByType if anchor_type == AnchorType::Corner => DotShape::Square,
+            AnchorShape::ByType | AnchorShape::Circle => DotShape::Circle,
+            AnchorShape::Square => DotShape::Square,
+        };
+
+        trace_dot(context, point, handle_style.radius, shape);
+    }
+
+    paint_dots(context, handle_style.anchor_color, handle_style.filled);
+
+    // Control points locked onto the chord can't be moved, so they aren't
+    // shown.
+    if !curve.locked_straight {
+        context.begin_path();
+        trace_dot(context, b, handle_style.radius, handle_style.control_shape);
+
+        if curve.kind == CurveKind::Cubic {
+            trace_dot(context, c, handle_style.radius, handle_style.control_shape);
+        }
+
+        paint_dots(context, handle_style.control_color, handle_style.filled);
+    }
+
+    // A short bar through each locked point, along the axis it can move on.
+    context.set_stroke_style(&JsString::from(handle_style.line_color));
+    context.begin_path();
+
+    for handle in [
+        PointHandle::A,
+        PointHandle::B,
+        PointHandle::C,
+        PointHandle::D,
+    ] {
+        let point = curve.point(handle);
+        let length = handle_style.radius * 2.5;
+
+        match curve.locks[handle as usize] {
+            AxisLock::None => {}
+            AxisLock::X => {
+                context.move_to(point.x, point.y - length);
+                context.line_to(point.x, point.y + length);
+            }
+            AxisLock::Y => {
+                context.move_to(point.x - length, point.y);
+                context.line_to(point.x + length, point.y);
+            }
+        }
+    }
+
+    context.stroke();
+}
+
+/// Makes what is drawn next cast `shadow`, or nothing.
+fn set_shadow(context: &CanvasRenderingContext2d, shadow: Option<Shadow>) {
+    let shadow = shadow.unwrap_or(Shadow {
+        blur: 0.0,
+        color: "transparent",
+        offset_x: 0.0,
+        offset_y: 0.0,
+    });
+
+    context.set_shadow_blur(shadow.blur);
+    context.set_shadow_color(shadow.color);
+    context.set_shadow_offset_x(shadow.offset_x);
+    context.set_shadow_offset_y(shadow.offset_y);
+}
+
+/// Strokes `curve` in its style, with its opacity scaled by `opacity`.
+fn stroke_curve(context: &CanvasRenderingContext2d, curve: Curve, opacity: f64) {
+    stroke_path(context, &[curve], false, true, opacity);
+}
+
+/// Strokes `curves`, each starting where the previous one ends, as one line
+/// in the first one's style, joined at the corners as that style says and
+/// closed if `closed`. Its opacity is scaled by `opacity`.
+fn stroke_path(
+    context: &CanvasRenderingContext2d,
+    curves: &[Curve],
+    closed: bool,
+    left_inside: bool,
+    opacity: f64,
+) {
+    let style = curves[0].style;
+
+    context.set_stroke_style(&JsString::from(style.color.css()));
+    context.set_line_width(style.width);
+    context.set_global_alpha(style.opacity * opacity);
+
+    if style.dashed {
+        let dash = Array::of2(
+            &JsValue::from(style.width * 4.0),
+            &JsValue::from(style.width * 3.0),
+        );
+
+        context.set_line_dash(&dash).unwrap();
+    }
+
+    context.set_line_join(style.join.css());
+    context.set_miter_limit(style.miter_limit);
+
+    let aligned = style
+        .align
+        .offset(style.width, left_inside)
+        .filter(|_| !style.dashed);
+
+    match (style.color_scale, aligned) {
+        (Some(scale), _) => {
+            for curve in curves {
+                stroke_color_scale(context, curve.clone(), scale);
+            }
+        }
+        // Canvas only strokes centered, so a stroke to one side is filled
+        // between the curve and its offset.
+        (None, Some(offset)) => {
+            context.set_fill_style(&JsString::from(style.color.css()));
+            context.begin_path();
+
+            for ring in aligned_outline(curves, closed, offset, style.miter_limit) {
+                for (index, &(x, y)) in ring.iter().enumerate() {
+                    if index == 0 {
+                        context.move_to(x, y);
+                    }
+                    else {
+                        context.line_to(x, y);
+                    }
+                }
+
+                context.close_path();
+            }
+
+            context.fill();
+        }
+        (None, None) => {
+            context.begin_path();
+            trace_path(context, curves);
+
+            if closed {
+                context.close_path();
+            }
+
+            context.stroke();
+        }
+    }
+
+    context.set_line_join("miter");
+    context.set_miter_limit(DEFAULT_MITER_LIMIT);
+    context.set_line_dash(&Array::new()).unwrap();
+    context.set_global_alpha(1.0);
+    context.set_line_width(1.0);
+}
+
+/// The outline of a stroke lying to one side of the curves, reaching
+/// `offset` to their left, as rings to fill. An open line's stroke is one
+/// ring, along the curves and back along their offset; a closed line's is
+/// the two running opposite ways.
+fn aligned_outline(
+    curves: &[Curve],
+    closed: bool,
+    offset: f64,
+    miter_limit: f64,
+) -> Vec<Vec<(f64, f64)>> {
+    let line = flatten_path(curves);
+    let mut edge = outline::offset(&line, offset, closed, miter_limit);
+    edge.reverse();
+
+    if closed {
+        vec![line, edge]
+    }
+    else {
+        vec![line.into_iter().chain(edge).collect()]
+    }
+}
+
+/// The curves, each starting where the previous one ends, flattened into
+/// one polyline.
+fn flatten_path(curves: &[Curve]) -> Vec<(f64, f64)> {
+    let mut points = Vec::new();
+
+    for curve in curves {
+        let flattened = curve.flatten(FLATTEN_TOLERANCE);
+        let skip = usize::from(!points.is_empty());
+
+        points.extend(flattened.into_iter().skip(skip));
+    }
+
+    points
+}
+
+/// For each curve, whether the inside of the path it is part of is to the
+/// left of its own way: the side a closed chain encloses, or for an open
+/// chain the left of the way the chain runs.
+fn left_inside(curves: &[Curve], subpaths: &[Subpath]) -> Vec<bool> {
+    let mut left_inside = vec![true; curves.len()];
+
+    for subpath in subpaths {
+        for chain in topology::chains(curves, &subpath.curves) {
+            let run: Vec<Curve> = chain
+                .curves
+                .iter()
+                .map(|&(index, reversed)| curves[index].clone().reversed_if(reversed))
+                .collect();
+            let chain_left_inside =
+                !chain.closed || outline::signed_area(&flatten_path(&run)) > 0.0;
+
+            for &(index, reversed) in &chain.curves {
+                left_inside[index] = chain_left_inside != reversed;
+            }
+        }
+    }
+
+    left_inside
+}
+
+/// Draws the cap `curve`'s style asks for at its `end`, in its stroke color.
+fn draw_cap(context: &CanvasRenderingContext2d, curve: Curve, end: PointHandle) {
+    let style = curve.style;
+    let (cap, point, angle) = match end {
+        PointHandle::A => (style.start_cap, curve.a, curve.start_angle() + PI),
+        _ => (style.end_cap, curve.d, curve.end_angle()),
+    };
+
+    let size = style.width * CAP_SCALE;
+    let (x, y) = (angle.cos() * size, angle.sin() * size);
+
+    context.set_fill_style(&JsString::from(style.color.css()));
+    context.set_global_alpha(style.opacity);
+    context.begin_path();
+
+    match cap {
+        CapStyle::None => {}
+        CapStyle::Dot => trace_dot(context, point, size / 2.0, DotShape::Circle),
+        CapStyle::Arrow => {
+            context.move_to(point.x, point.y);
+            context.line_to(point.x - x - y / 2.0, point.y - y + x / 2.0);
+            context.line_to(point.x - x + y / 2.0, point.y - y - x / 2.0);
+            context.close_path();
+        }
+        CapStyle::Bar => {
+            let (dx, dy) = (x / size * style.width / 2.0, y / size * style.width / 2.0);
+
+            context.move_to(point.x - dx - y / 2.0, point.y - dy + x / 2.0);
+            context.line_to(point.x + dx - y / 2.0, point.y + dy + x / 2.0);
+            context.line_to(point.x + dx + y / 2.0, point.y + dy - x / 2.0);
+            context.line_to(point.x - dx + y / 2.0, point.y - dy - x / 2.0);
+            context.close_path();
+        }
+    }
+
+    context.fill();
+    context.set_global_alpha(1.0);
+}
+
+/// Strokes `curve` in short straight pieces, each colored by `scale` at its
+/// start. Dashes continue from piece to piece.
+fn stroke_color_scale(context: &CanvasRenderingContext2d, curve: Curve, scale: ColorScale) {
+    let points: Vec<(f64, f64)> = curve.samples(COLOR_SCALE_SEGMENTS).collect();
+    let lengths: Vec<f64> = points
+        .windows(2)
+        .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+        .collect();
+    let total: f64 = lengths.iter().sum();
+
+    let values: Vec<f64> = match scale {
+        ColorScale::Position => lengths
+            .iter()
+            .scan(0.0, |along, length| {
+                let start = *along;
+                *along += length;
+                Some(if total > 0.0 { start / total } else { 0.0 })
+            })
+            .collect(),
+        ColorScale::Curvature => {
+            let curvatures: Vec<f64> = (0..COLOR_SCALE_SEGMENTS)
+                .map(|i| {
+                    let t = (i as f64 + 0.5) / COLOR_SCALE_SEGMENTS as f64;
+                    curve.curvature_at(t).unwrap_or(0.0).abs()
+                })
+                .collect();
+            let max = curvatures.iter().copied().fold(0.0, f64::max);
+
+            curvatures
+                .iter()
+                .map(|curvature| {
+                    if max > 0.0 {
+                        curvature / max
+                    }
+                    else {
+                        0.0
+                    }
+                })
+                .collect()
+        }
+    };
+
+    let dash_offset = context.line_dash_offset();
+    let mut along = 0.0;
+
+    // Round caps hide the seams between pieces.
+    context.set_line_cap("round");
+
+    for ((pair, value), length) in points.windows(2).zip(values).zip(lengths) {
+        context.set_stroke_style(&JsString::from(scale.css(value)));
+        context.set_line_dash_offset(dash_offset + along);
+        context.begin_path();
+        context.move_to(pair[0].0, pair[0].1);
+        context.line_to(pair[1].0, pair[1].1);
+        context.stroke();
+
+        along += length;
+    }
+
+    context.set_line_cap("butt");
+    context.set_line_dash_offset(dash_offset);
+}
+
+/// Draws lines from evenly spaced points on `curve` to each of its points,
+/// as opaque as that point's Bernstein weight there, showing which part of
+/// the curve each control point shapes.
+fn draw_influence(context: &CanvasRenderingContext2d, curve: Curve, handle_style: HandleStyle) {
+    let anchor = handle_style.anchor_color;
+    let control = handle_style.control_color;
+
+    context.set_line_width(1.0);
+
+    for i in 1..INFLUENCE_SAMPLES {
+        let t = i as f64 / INFLUENCE_SAMPLES as f64;
+        let mt = 1.0 - t;
+        let from = curve.point_at(t);
+
+        let weighted: &[(Point, f64, &str)] = match curve.kind {
+            CurveKind::Cubic => &[
+                (curve.a, mt * mt * mt, anchor),
+                (curve.b, 3.0 * mt * mt * t, control),
+                (curve.c, 3.0 * mt * t * t, control),
+                (curve.d, t * t * t, anchor),
+            ],
+            CurveKind::Quadratic => &[
+                (curve.a, mt * mt, anchor),
+                (curve.b, 2.0 * mt * t, control),
+                (curve.d, t * t, anchor),
+            ],
+        };
+
+        for &(to, weight, color) in weighted {
+            context.set_stroke_style(&JsString::from(color));
+            context.set_global_alpha(weight * INFLUENCE_OPACITY);
+            context.begin_path();
+            context.move_to(from.x, from.y);
+            context.line_to(to.x, to.y);
+            context.stroke();
+        }
+    }
+
+    context.set_global_alpha(1.0);
+}
+
+/// Marks the rotation pivot with a circled cross.
+fn draw_pivot(context: &CanvasRenderingContext2d, point: Point) {
+    let radius = PIVOT_MARKER_RADIUS;
+
+    context.set_stroke_style(&JsString::from(PIVOT_COLOR));
+    context.set_line_width(1.5);
+    context.begin_path();
+    trace_dot(context, point, radius, DotShape::Circle);
+    context.move_to(point.x - radius * 1.5, point.y);
+    context.line_to(point.x + radius * 1.5, point.y);
+    context.move_to(point.x, point.y - radius * 1.5);
+    context.line_to(point.x, point.y + radius * 1.5);
+    context.stroke();
+    context.set_line_width(1.0);
+}
+
+/// Adds a handle dot to the current path.
+fn trace_dot(context: &CanvasRenderingContext2d, point: Point, radius: f64, shape: DotShape) {
+    match shape {
+        DotShape::Circle => {
+            context.move_to(point.x + radius, point.y);
+            context
+                .arc(point.x, point.y, radius, 0.0, 2.0 * PI)
+                .unwrap();
+        }
+        DotShape::Square => {
+            context.rect(
+                point.x - radius,
+                point.y - radius,
+                2.0 * radius,
+                2.0 * radius,
+            );
+        }
+        DotShape::Diamond => {
+            context.move_to(point.x + radius, point.y);
+            context.line_to(point.x, point.y + radius);
+            context.line_to(point.x - radius, point.y);
+            context.line_to(point.x, point.y - radius);
+            context.close_path();
+        }
+    }
+}
+
+/// Fills the dots traced into the current path, or outlines them when they
+/// are not `filled`.
+fn paint_dots(context: &CanvasRenderingContext2d, color: &str, filled: bool) {
+    if filled {
+        context.set_fill_style(&JsString::from(color));
+        context.fill();
+    }
+    else {
+        context.set_stroke_style(&JsString::from(color));
+        context.stroke();
+    }
+}
+
+/// Adds the curve to the current path as a new subpath.
+fn trace_curve(context: &CanvasRenderingContext2d, curve: Curve) {
+    trace_path(context, &[curve]);
+}
+
+/// Adds `curves` to the current path as one new subpath, each continuing
+/// from where the previous one ends.
+fn trace_path(context: &CanvasRenderingContext2d, curves: &[Curve]) {
+    context.move_to(curves[0].a.x, curves[0].a.y);
+
+    for curve @ &Curve { b, c, d, kind, .. } in curves {
+        // Canvas has no rational curves, so those are traced as polylines.
+        if curve.weights.is_some() {
+            for (x, y) in curve.flatten(FLATTEN_TOLERANCE).into_iter().skip(1) {
+                context.line_to(x, y);
+            }
+
+            continue;
+        }
+
+        match kind {
+            CurveKind::Cubic => context.bezier_curve_to(b.x, b.y, c.x, c.y, d.x, d.y),
+            CurveKind::Quadratic => context.quadratic_curve_to(b.x, b.y, d.x, d.y),
+        }
+    }
+}
+
+fn draw_pin(context: &CanvasRenderingContext2d, point: Point) {
+    context.set_stroke_style(&JsString::from(PIN_COLOR));
+    context.set_line_width(2.0);
+    context.begin_path();
+    context.move_to(point.x - PIN_SIZE, point.y - PIN_SIZE);
+    context.line_to(point.x + PIN_SIZE, point.y + PIN_SIZE);
+    context.move_to(point.x + PIN_SIZE, point.y - PIN_SIZE);
+    context.line_to(point.x - PIN_SIZE, point.y + PIN_SIZE);
+    context.stroke();
+    context.set_line_width(1.0);
+}
+
+/// Draws dashed rays continuing the curve past `a` and `d` along its end
+/// tangents, which helps when lining up the next curve by eye.
+fn draw_tangent_extensions(context: &CanvasRenderingContext2d, curve: Curve) {
+    let dash = Array::of2(&JsValue::from(4.0), &JsValue::from(4.0));
+
+    context.set_stroke_style(&JsString::from("rgba(0, 0, 0, 0.35)"));
+    context.set_line_dash(&dash).unwrap();
+    context.begin_path();
+
+    if let Some((tx, ty)) = curve.tangent_at(0.0) {
+        let a = curve.a;
+
+        context.move_to(a.x, a.y);
+        context.line_to(
+            a.x - tx * TANGENT_EXTENSION_LENGTH,
+            a.y - ty * TANGENT_EXTENSION_LENGTH,
+        );
+    }
+
+    if let Some((tx, ty)) = curve.tangent_at(1.0) {
+        let d = curve.d;
+
+        context.move_to(d.x, d.y);
+        context.line_to(
+            d.x + tx * TANGENT_EXTENSION_LENGTH,
+            d.y + ty * TANGENT_EXTENSION_LENGTH,
+        );
+    }
+
+    context.stroke();
+    context.set_line_dash(&Array::new()).unwrap();
+}
+
+/// Draws the tangent and the normal through the point at `t` on `curve`.
+fn draw_tangent_and_normal(context: &CanvasRenderingContext2d, curve: Curve, t: f64) {
+    let Some((tx, ty)) = curve.tangent_at(t)
+    else {
+        return;
+    };
+
+    let point = curve.point_at(t);
+    let length = TANGENT_EXTENSION_LENGTH;
+    let dash = Array::of2(&JsValue::from(4.0), &JsValue::from(4.0));
+
+    context.set_stroke_style(&JsString::from("rgba(0, 0, 0, 0.35)"));
+    context.set_line_dash(&dash).unwrap();
+    context.begin_path();
+    context.move_to(point.x - tx * length, point.y - ty * length);
+    context.line_to(point.x + tx * length, point.y + ty * length);
+    context.move_to(point.x + ty * length / 2.0, point.y - tx * length / 2.0);
+    context.line_to(point.x - ty * length / 2.0, point.y + tx * length / 2.0);
+    context.stroke();
+    context.set_line_dash(&Array::new()).unwrap();
+}
+
+/// Marks the scrubbed `point`, at parameter `t`, with a diamond to drag and
+/// a label giving both.
+fn draw_scrub_marker(
+    context: &CanvasRenderingContext2d,
+    camera: Camera,
+    point: Point,
+    t: f64,
+    radius: f64,
+) {
+    let screen = camera.to_screen(point);
+
+    context.begin_path();
+    trace_dot(context, screen, radius * 1.5, DotShape::Diamond);
+    paint_dots(context, SCRUB_COLOR, true);
+
+    context.set_font(STATUS_FONT);
+    context.set_fill_style(&JsString::from(SCRUB_COLOR));
+    context
+        .fill_text(
+            &format!("t = {t:.3} ({:.1}, {:.1})", point.x, point.y),
+            screen.x + CROSSHAIR_LABEL_OFFSET,
+            screen.y - CROSSHAIR_LABEL_OFFSET,
+        )
+        .unwrap();
+}
+
+/// Highlights the part of `curve` that trimming keeps, with a handle at
+/// each end of it.
+fn draw_trim(context: &CanvasRenderingContext2d, camera: Camera, curve: Curve, trim: Trim) {
+    let kept = camera.curve_to_screen(curve.clone().trim(trim.start, trim.end));
+
+    context.set_stroke_style(&JsString::from(TRIM_COLOR));
+    context.set_line_width(curve.style.width + SELECTION_HALO_WIDTH);
+    context.begin_path();
+    trace_curve(context, kept.clone());
+    context.stroke();
+    context.set_line_width(1.0);
+
+    context.set_fill_style(&JsString::from(TRIM_COLOR));
+    context.begin_path();
+
+    for point in [kept.a, kept.d] {
+        trace_dot(context, point, POINT_RADIUS, DotShape::Circle);
+    }
+
+    context.fill();
+}
+
+/// Draws the circle that best fits `curve` at `t`, with a line from the curve
+/// to its center. Nothing is drawn where the curve is (nearly) straight and
+/// the circle would be unboundedly large.
+fn draw_osculating_circle(
+    context: &CanvasRenderingContext2d,
+    camera: Camera,
+    curve: Curve,
+    t: f64,
+) {
+    let (Some(curvature), Some((tx, ty))) = (curve.curvature_at(t), curve.tangent_at(t))
+    else {
+        return;
+    };
+
+    if curvature.abs() < MIN_CURVATURE {
+        return;
+    }
+
+    // The normal `(-ty, tx)` points to the left of the direction of travel,
+    // which is where the center lies when the curvature is positive.
+    let radius = 1.0 / curvature;
+    let point = curve.point_at(t);
+    let center = Point::new(point.x - ty * radius, point.y + tx * radius);
+
+    let point = camera.to_screen(point);
+    let center = camera.to_screen(center);
+
+    context.set_stroke_style(&JsString::from(OSCULATING_CIRCLE_COLOR));
+    context.begin_path();
+    context
+        .arc(
+            center.x,
+            center.y,
+            radius.abs() * camera.zoom,
+            0.0,
+            2.0 * PI,
+        )
+        .unwrap();
+    context.move_to(point.x, point.y);
+    context.line_to(center.x, center.y);
+    context.stroke();
+}
+
+/// Draws an arrowhead at `d` pointing along the curve's end tangent.
+fn draw_arrowhead(context: &CanvasRenderingContext2d, curve: Curve) {
+    let Some((x, y)) = curve.tangent_at(1.0)
+    else {
+        return;
+    };
+
+    let d = curve.d;
+    let back = Point::new(d.x - x * ARROWHEAD_SIZE, d.y - y * ARROWHEAD_SIZE);
+    let (nx, ny) = (-y * ARROWHEAD_SIZE / 2.0, x * ARROWHEAD_SIZE / 2.0);
+
+    context.set_fill_style(&JsString::from(SELECTION_COLOR));
+    context.begin_path();
+    context.move_to(d.x, d.y);
+    context.line_to(back.x + nx, back.y + ny);
+    context.line_to(back.x - nx, back.y - ny);
+    context.close_path();
+    context.fill();
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Point {
+    x: f64,
+    y: f64,
+}
+
+impl Point {
+    fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    fn lerp(self, other: Point, t: f64) -> Point {
+        Point::new(
+            self.x + (other.x - self.x) * t,
+            self.y + (other.y - self.y) * t,
+        )
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Color {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+impl Color {
+    const fn new(r: u8, g: u8, b: u8) -> Self {
+        Self { r, g, b }
+    }
+
+    fn css(self) -> String {
+        format!("#{:02x}{:02x}{:02x}", self.r, self.g, self.b)
+    }
+}
+
+/// How a curve's stroke is painted. Handles are unaffected.
+#[derive(Clone, Copy, PartialEq)]
+struct Style {
+    color: Color,
+    /// In screen pixels.
+    width: f64,
+    dashed: bool,
+    opacity: f64,
+    /// Colors the stroke by a property that varies along the curve, in place
+    /// of `color`.
+    color_scale: Option<ColorScale>,
+    /// How corners are drawn where the curve meets the next one in its path.
+    join: LineJoin,
+    /// Longest a miter may get, as a multiple of the stroke width, before it
+    /// is beveled instead.
+    miter_limit: f64,
+    /// What is drawn at `a` and `d` where they meet no other curve.
+    start_cap: CapStyle,
+    end_cap: CapStyle,
+    /// Which side of the curve the stroke lies on.
+    align: StrokeAlign,
+}
+
+/// Where a stroke lies relative to its curve. Inside is the side a closed
+/// path encloses, and the left of an open path's way.
+#[derive(Clone, Copy, PartialEq)]
+enum StrokeAlign {
+    /// Centered on the curve, as canvas and SVG stroke.
+    Center,
+    Inside,
+    Outside,
+}
+
+impl StrokeAlign {
+    fn next(self) -> Self {
+        match self {
+            StrokeAlign::Center => StrokeAlign::Inside,
+            StrokeAlign::Inside => StrokeAlign::Outside,
+            StrokeAlign::Outside => StrokeAlign::Center,
+        }
+    }
+
+    /// How far to the left of the curve the stroke's far edge is, for a
+    /// stroke `width` wide on a curve whose inside is on its left if
+    /// `left_inside`. `None` for centered strokes.
+    fn offset(self, width: f64, left_inside: bool) -> Option<f64> {
+        let inside = if left_inside { width } else { -width };
+
+        match self {
+            StrokeAlign::Center => None,
+            StrokeAlign::Inside => Some(inside),
+            StrokeAlign::Outside => Some(-inside),
+        }
+    }
+}
+
+/// A mark at a loose curve end, scaled with the stroke width.
+#[derive(Clone, Copy, PartialEq)]
+enum CapStyle {
+    None,
+    Dot,
+    /// Points away from the curve.
+    Arrow,
+    /// Crosses the curve.
+    Bar,
+}
+
+impl CapStyle {
+    fn next(self) -> Self {
+        match self {
+            CapStyle::None => CapStyle::Dot,
+            CapStyle::Dot => CapStyle::Arrow,
+            CapStyle::Arrow => CapStyle::Bar,
+            CapStyle::Bar => CapStyle::None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LineJoin {
+    Miter,
+    Round,
+    Bevel,
+}
+
+impl LineJoin {
+    fn next(self) -> Self {
+        match self {
+            LineJoin::Miter => LineJoin::Round,
+            LineJoin::Round => LineJoin::Bevel,
+            LineJoin::Bevel => LineJoin::Miter,
+        }
+    }
+
+    /// The name canvas and SVG use for it.
+    fn css(self) -> &'static str {
+        match self {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        }
+    }
+}
+
+/// What a curve colored along its length shows.
+#[derive(Clone, Copy, PartialEq)]
+enum ColorScale {
+    /// Distance along the curve, through the hues of the rainbow.
+    Position,
+    /// Magnitude of the curvature relative to its largest on the curve, from
+    /// blue where the curve is straightest to red where it bends most.
+    Curvature,
+}
+
+impl ColorScale {
+    /// Cycles off, position, curvature.
+    fn next(scale: Option<Self>) -> Option<Self> {
+        match scale {
+            None => Some(ColorScale::Position),
+            Some(ColorScale::Position) => Some(ColorScale::Curvature),
+            Some(ColorScale::Curvature) => None,
+        }
+    }
+
+    /// The color for `value`, between 0 and 1.
+    fn css(self, value: f64) -> String {
+        let hue = match self {
+            ColorScale::Position => 300.0 * value,
+            ColorScale::Curvature => 240.0 * (1.0 - value),
+        };
+
+        format!("hsl({hue}, 100%, 45%)")
+    }
+}
+
+impl Style {
+    fn has_caps(&self) -> bool {
+        self.start_cap != CapStyle::None || self.end_cap != CapStyle::None
+    }
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            color: PALETTE[0],
+            width: STROKE_WIDTHS[0],
+            dashed: false,
+            opacity: 1.0,
+            color_scale: None,
+            join: LineJoin::Miter,
+            miter_limit: DEFAULT_MITER_LIMIT,
+            start_cap: CapStyle::None,
+            end_cap: CapStyle::None,
+            align: StrokeAlign::Center,
+        }
+    }
+}
+
+/// How handle dots and the lines to them are drawn, in screen pixels.
+#[derive(Clone, Copy)]
+struct HandleStyle {
+    /// Also how close a click must be to grab a handle.
+    radius: f64,
+    /// Solid dots, or just their outlines.
+    filled: bool,
+    anchor_shape: AnchorShape,
+    control_shape: DotShape,
+    anchor_color: &'static str,
+    control_color: &'static str,
+    line_color: &'static str,
+    line_width: f64,
+    dashed_lines: bool,
+    /// Draws the control polygon `a`, `b`, `c`, `d` as one line, rather than
+    /// a line from each anchor to its control point.
+    connected_polygon: bool,
+}
+
+impl Default for HandleStyle {
+    fn default() -> Self {
+        Self {
+            radius: POINT_RADIUS,
+            filled: true,
+            anchor_shape: AnchorShape::ByType,
+            control_shape: DotShape::Circle,
+            anchor_color: "blue",
+            control_color: "red",
+            line_color: "black",
+            line_width: 1.0,
+            dashed_lines: false,
+            connected_polygon: false,
+        }
+    }
+}
+
+impl HandleStyle {
+    /// Larger handles, told apart by shape as well as color, in colors that
+    /// stand out against a light background for all kinds of color vision.
+    fn high_contrast() -> Self {
+        Self {
+            radius: 7.0,
+            filled: true,
+            anchor_shape: AnchorShape::Square,
+            control_shape: DotShape::Diamond,
+            anchor_color: "black",
+            control_color: "#d55e00",
+            line_color: "black",
+            line_width: 2.0,
+            dashed_lines: false,
+            connected_polygon: false,
+        }
+    }
+}
+
+/// A drop shadow under strokes, in screen pixels.
+#[derive(Clone, Copy, PartialEq)]
+struct Shadow {
+    blur: f64,
+    color: &'static str,
+    offset_x: f64,
+    offset_y: f64,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            blur: 6.0,
+            color: "rgba(0, 0, 0, 0.35)",
+            offset_x: 2.0,
+            offset_y: 3.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum DotShape {
+    Circle,
+    Square,
+    Diamond,
+}
+
+#[derive(Clone, Copy)]
+enum AnchorShape {
+    /// Squares for corners, circles for smooth anchors.
+    ByType,
+    Circle,
+    Square,
+}
+
+impl AnchorShape {
+    fn next(self) -> Self {
+        match self {
+            AnchorShape::ByType => AnchorShape::Circle,
+            AnchorShape::Circle => AnchorShape::Square,
+            AnchorShape::Square => AnchorShape::ByType,
+        }
+    }
+}
+
+/// How `Curve::arc_length_by` measures a curve.
+#[derive(Clone, Copy)]
+enum ArcLengthMethod {
+    /// Gauss-Legendre quadrature of the speed over this many equal pieces of
+    /// the parameter range. Very accurate for few evaluations on smooth
+    /// curves.
+    Quadrature { intervals: usize },
+    /// Summing a polyline flattened to within `ARC_LENGTH_TOLERANCE`. Slower,
+    /// but dependable where the speed changes abruptly, as near cusps.
+    Flatten,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CurveKind {
+    Cubic,
+    /// Uses `b` as its only control point; `c` is ignored.
+    Quadratic,
+}
+
+#[derive(Clone, PartialEq)]
+struct Curve {
+    a: Point,
+    b: Point,
+    c: Point,
+    d: Point,
+    kind: CurveKind,
+    style: Style,
+    /// Curves sharing a group were created together (e.g. one imported
+    /// feature) and are kept together on export.
+    group: Option<u32>,
+    /// A point the curve is kept passing through while it is edited.
+    pin: Option<Pin>,
+    /// How the handle at `a` moves along with a neighbouring curve's handle
+    /// at a junction.
+    a_type: AnchorType,
+    d_type: AnchorType,
+    /// Axis locks of `a`, `b`, `c` and `d`, which hold across drags.
+    locks: [AxisLock; 4],
+    /// Keeps the control points evenly spaced on the chord, so the curve
+    /// stays a straight segment however its anchors move.
+    locked_straight: bool,
+    /// Weights of the points of `cubic_points`, making the curve a rational
+    /// bezier, which can trace conics such as circular arcs exactly. `None`
+    /// is the same as all ones.
+    weights: Option<[f64; 4]>,
+    /// Data a host application keeps with the curve. The editor carries it
+    /// along, to the pieces when splitting, but never looks at it.
+    meta: Option<Rc<str>>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum AxisLock {
+    None,
+    /// The point only moves vertically.
+    X,
+    /// The point only moves horizontally.
+    Y,
+}
+
+impl AxisLock {
+    fn next(self) -> Self {
+        match self {
+            AxisLock::None => AxisLock::X,
+            AxisLock::X => AxisLock::Y,
+            AxisLock::Y => AxisLock::None,
+        }
+    }
+
+    /// Where a point at `from` asked to move to `to` may go.
+    fn constrain(self, from: Point, to: Point) -> Point {
+        match self {
+            AxisLock::None => to,
+            AxisLock::X => Point::new(from.x, to.y),
+            AxisLock::Y => Point::new(to.x, from.y),
+        }
+    }
+}
+
+/// How the two handles meeting at a junction relate while one is dragged.
+#[derive(Clone, Copy, PartialEq)]
+enum AnchorType {
+    /// The handles move independently.
+    Corner,
+    /// The other handle turns to stay opposite, keeping its length.
+    Smooth,
+    /// The other handle mirrors the dragged one exactly.
+    Symmetric,
+}
+
+impl AnchorType {
+    fn next(self) -> Self {
+        match self {
+            AnchorType::Corner => AnchorType::Smooth,
+            AnchorType::Smooth => AnchorType::Symmetric,
+            AnchorType::Symmetric => AnchorType::Corner,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Pin {
+    t: f64,
+    point: Point,
+}
+
+impl Curve {
+    fn new(a: Point, b: Point, c: Point, d: Point) -> Self {
+        Self {
+            a,
+            b,
+            c,
+            d,
+            kind: CurveKind::Cubic,
+            style: Style::default(),
+            group: None,
+            pin: None,
+            a_type: AnchorType::Corner,
+            d_type: AnchorType::Corner,
+            locks: [AxisLock::None; 4],
+            locked_straight: false,
+            weights: None,
+            meta: None,
+        }
+    }
+
+    fn quadratic(a: Point, control: Point, d: Point) -> Self {
+        Self {
+            kind: CurveKind::Quadratic,
+            ..Self::new(a, control, control, d)
+        }
+    }
+
+    /// The control points of the curve as a cubic. Quadratics are elevated,
+    /// which is exact, so geometry only ever has to deal with cubics.
+    fn cubic_points(&self) -> [Point; 4] {
+        match self.kind {
+            CurveKind::Cubic => [self.a, self.b, self.c, self.d],
+            CurveKind::Quadratic => [
+                self.a,
+                self.a.lerp(self.b, 2.0 / 3.0),
+                self.d.lerp(self.b, 2.0 / 3.0),
+                self.d,
+            ],
+        }
+    }
+
+    /// A quadratic approximating this cubic, with the same endpoints. Its
+    /// control point averages the controls each end tangent implies,
+    /// `(3b - a) / 2` and `(3c - d) / 2`. The result deviates from
+    /// the cubic by at most `√3 / 36 · |d - 3c + 3b - a|`, so it is exact for
+    /// cubics that are elevated quadratics and degrades as the cubic's
+    /// inflection grows. Weights are dropped, since they belong to the cubic's
+    /// points, so this is no approximation of a rational cubic. Quadratics are
+    /// returned unchanged.
+    fn to_quadratic(&self) -> Self {
+        if self.kind == CurveKind::Quadratic {
+            return self.clone();
+        }
+
+        let Curve { a, b, c, d, .. } = *self;
+        let control = Point::new(
+            (3.0 * (b.x + c.x) - (a.x + d.x)) / 4.0,
+            (3.0 * (b.y + c.y) - (a.y + d.y)) / 4.0,
+        );
+
+        Self {
+            b: control,
+            c: control,
+            kind: CurveKind::Quadratic,
+            weights: None,
+            ..self.clone()
+        }
+    }
+
+    /// The same curve as a cubic. Degree elevation is exact, so the shape is
+    /// unchanged.
+    fn to_cubic(&self) -> Self {
+        let [a, b, c, d] = self.cubic_points();
+
+        Self {
+            a,
+            b,
+            c,
+            d,
+            kind: CurveKind::Cubic,
+            ..self.clone()
+        }
+    }
+
+    /// The parts of the curve before and after `t`, which together trace the
+    /// same shape. Both keep the curve's kind, style, group and metadata; a
+    /// pin goes to the part it lies on.
+    fn split(self, t: f64) -> (Self, Self) {
+        let (mut first, mut second) = match (self.weights, self.kind) {
+            // De Casteljau on the points in homogeneous coordinates, which
+            // rational curves are ordinary curves in.
+            (Some(weights), _) => {
+                let points = self.cubic_points();
+                let [a, b, c, d] =
+                    array::from_fn(|i| [points[i].x, points[i].y, 1.0].map(|v| v * weights[i]));
+                let lerp = |p: [f64; 3], q: [f64; 3]| array::from_fn(|i| p[i] + (q[i] - p[i]) * t);
+                let project = |points: [[f64; 3]; 4]| {
+                    let [a, b, c, d] = points.map(|[x, y, w]| Point::new(x / w, y / w));
+
+                    Curve {
+                        weights: Some(points.map(|[_, _, w]| w)),
+                        ..Curve::new(a, b, c, d)
+                    }
+                };
+
+                let ab = lerp(a, b);
+                let bc = lerp(b, c);
+                let cd = lerp(c, d);
+                let abc = lerp(ab, bc);
+                let bcd = lerp(bc, cd);
+                let middle = lerp(abc, bcd);
+
+                (project([a, ab, abc, middle]), project([middle, bcd, cd, d]))
+            }
+            (None, CurveKind::Cubic) => {
+                let ab = self.a.lerp(self.b, t);
+                let bc = self.b.lerp(self.c, t);
+                let cd = self.c.lerp(self.d, t);
+                let abc = ab.lerp(bc, t);
+                let bcd = bc.lerp(cd, t);
+                let middle = abc.lerp(bcd, t);
+
+                (
+                    Curve::new(self.a, ab, abc, middle),
+                    Curve::new(middle, bcd, cd, self.d),
+                )
+            }
+            (None, CurveKind::Quadratic) => {
+                let ab = self.a.lerp(self.b, t);
+                let bd = self.b.lerp(self.d, t);
+                let middle = ab.lerp(bd, t);
+
+                (
+                    Curve::quadratic(self.a, ab, middle),
+                    Curve::quadratic(middle, bd, self.d),
+                )
+            }
+        };
+
+        for part in [&mut first, &mut second] {
+            part.style = self.style;
+            part.group = self.group;
+            part.meta = self.meta.clone();
+        }
+
+        // The halves meet with matching tangents.
+        first.a_type = self.a_type;
+        first.d_type = AnchorType::Smooth;
+        second.a_type = AnchorType::Smooth;
+        second.d_type = self.d_type;
+        first.locks[0] = self.locks[0];
+        second.locks[3] = self.locks[3];
+
+        match self.pin {
+            Some(pin) if pin.t < t => {
+                first.pin = Some(Pin {
+                    t: pin.t / t,
+                    ..pin
+                });
+            }
+            Some(pin) => {
+                second.pin = Some(Pin {
+                    t: (pin.t - t) / (1.0 - t),
+                    ..pin
+                });
+            }
+            None => {}
+        }
+
+        (first, second)
+    }
+
+    /// The curve with each handle's distance from its anchor scaled by
+    /// `factor`, keeping its direction. A quadratic's control is scaled from
+    /// the middle of the chord instead.
+    fn with_tension(self, factor: f64) -> Self {
+        match self.kind {
+            CurveKind::Cubic => Self {
+                b: self.a.lerp(self.b, factor),
+                c: self.d.lerp(self.c, factor),
+                ..self
+            },
+            CurveKind::Quadratic => Self {
+                b: self.a.lerp(self.d, 0.5).lerp(self.b, factor),
+                ..self
+            },
+        }
+    }
+
+    /// The curve made symmetric about the perpendicular bisector of its
+    /// chord, keeping its anchors. Both handles get the average of their
+    /// reach along and across the chord, mirrored. A quadratic's control
+    /// moves onto the bisector.
+    fn symmetric(self) -> Self {
+        let length = distance(self.a, self.d);
+
+        if length == 0.0 {
+            return self;
+        }
+
+        let (x, y) = (
+            (self.d.x - self.a.x) / length,
+            (self.d.y - self.a.y) / length,
+        );
+
+        // How far `handle` reaches from `anchor` along the chord, towards the
+        // other anchor, and across it.
+        let reach = |anchor: Point, handle: Point, inwards: f64| {
+            let (dx, dy) = (handle.x - anchor.x, handle.y - anchor.y);
+            (inwards * (dx * x + dy * y), dy * x - dx * y)
+        };
+        let place = |anchor: Point, (along, across): (f64, f64), inwards: f64| {
+            Point::new(
+                anchor.x + inwards * along * x - across * y,
+                anchor.y + inwards * along * y + across * x,
+            )
+        };
+
+        match self.kind {
+            CurveKind::Cubic => {
+                let (b_along, b_across) = reach(self.a, self.b, 1.0);
+                let (c_along, c_across) = reach(self.d, self.c, -1.0);
+                let average = ((b_along + c_along) / 2.0, (b_across + c_across) / 2.0);
+
+                Self {
+                    b: place(self.a, average, 1.0),
+                    c: place(self.d, average, -1.0),
+                    ..self
+                }
+            }
+            CurveKind::Quadratic => {
+                let (_, across) = reach(self.a, self.b, 1.0);
+                let b = place(self.a, (length / 2.0, across), 1.0);
+
+                Self { b, c: b, ..self }
+            }
+        }
+    }
+
+    /// Splits the curve into `n` pieces of equal arc length, found from an
+    /// arc-length table. The pieces join up into the curve again.
+    fn split_by_arclen(&self, n: usize) -> Vec<Curve> {
+        let table = frames::arc_length_table(slice::from_ref(self), Camera::default());
+        let length = table.last().unwrap().0;
+
+        let mut pieces = Vec::new();
+        let mut rest = self.clone();
+        let mut start = 0.0;
+
+        for piece in 1..n {
+            let t = frames::position_at_length(&table, length * piece as f64 / n as f64);
+            let (first, second) = rest.split((t - start) / (1.0 - start));
+
+            pieces.push(first);
+            rest = second;
+            start = t;
+        }
+
+        pieces.push(rest);
+        pieces
+    }
+
+    /// The part of the curve between `t0` and `t1`, where `t0 < t1`.
+    fn trim(self, t0: f64, t1: f64) -> Self {
+        let (_, rest) = self.split(t0);
+        let (middle, _) = rest.split((t1 - t0) / (1.0 - t0));
+
+        middle
+    }
+
+    /// The parameter of the point on the curve closest to `point`. Coarse
+    /// sampling finds the neighbourhood, which is then narrowed down by
+    /// ternary search.
+    fn nearest_t(&self, point: Point) -> f64 {
+        let squared_distance = |t: f64| {
+            let p = self.point_at(t);
+            (p.x - point.x).powi(2) + (p.y - point.y).powi(2)
+        };
+
+        let closest = self
+            .samples(NEAREST_T_SAMPLES)
+            .map(|(x, y)| (x - point.x).powi(2) + (y - point.y).powi(2))
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map_or(0, |(index, _)| index);
+
+        let step = 1.0 / NEAREST_T_SAMPLES as f64;
+        let mut low = (closest as f64 - 1.0).max(0.0) * step;
+        let mut high = (closest as f64 + 1.0).min(NEAREST_T_SAMPLES as f64) * step;
+
+        for _ in 0..NEAREST_T_ITERATIONS {
+            let first = low + (high - low) / 3.0;
+            let second = high - (high - low) / 3.0;
+
+            if squared_distance(first) < squared_distance(second) {
+                high = second;
+            }
+            else {
+                low = first;
+            }
+        }
+
+        (low + high) / 2.0
+    }
+
+    /// The curve traversed backwards if `reversed`, or as it is.
+    fn reversed_if(self, reversed: bool) -> Self {
+        match reversed {
+            true => self.reversed(),
+            false => self,
+        }
+    }
+
+    /// The same curve traversed from `d` to `a`.
+    fn reversed(self) -> Self {
+        Self {
+            a: self.d,
+            b: match self.kind {
+                CurveKind::Cubic => self.c,
+                CurveKind::Quadratic => self.b,
+            },
+            c: self.b,
+            d: self.a,
+            a_type: self.d_type,
+            d_type: self.a_type,
+            locks: match self.kind {
+                CurveKind::Cubic => [self.locks[3], self.locks[2], self.locks[1], self.locks[0]],
+                CurveKind::Quadratic => {
+                    [self.locks[3], self.locks[1], self.locks[2], self.locks[0]]
+                }
+            },
+            pin: self.pin.map(|pin| Pin {
+                t: 1.0 - pin.t,
+                ..pin
+            }),
+            weights: self.weights.map(|[a, b, c, d]| [d, c, b, a]),
+            ..self
+        }
+    }
+
+    /// After `moved` was edited, shifts the control points so the curve
+    /// passes through its pin again at the pinned `t`.
+    ///
+    /// Only control points are adjusted, and never the one just moved: a
+    /// moved anchor spreads the correction over both controls (the smallest
+    /// change that works), while a moved control of a cubic leaves all of it
+    /// to the other one. This has no solution when the pin sits at `t = 0` or
+    /// `t = 1`, where the curve is fixed by its anchor, or when the moved
+    /// control is a quadratic's only one; the pin is then left unsatisfied.
+    fn satisfy_pin(&mut self, moved: PointHandle) {
+        let Some(pin) = self.pin
+        else {
+            return;
+        };
+
+        let current = self.point_at(pin.t);
+        let residual = Point::new(pin.point.x - current.x, pin.point.y - current.y);
+        let t = pin.t;
+        let mt = 1.0 - t;
+
+        // How much of a shift of each control point carries over to the curve
+        // at `t`.
+        let (weight_b, weight_c) = match self.kind {
+            CurveKind::Cubic => (3.0 * mt * mt * t, 3.0 * mt * t * t),
+            CurveKind::Quadratic => (2.0 * mt * t, 0.0),
+        };
+
+        let (share_b, share_c) = match (self.kind, moved) {
+            (CurveKind::Quadratic, PointHandle::B) => return,
+            (CurveKind::Quadratic, _) => (1.0 / weight_b, 0.0),
+            (_, PointHandle::B) => (0.0, 1.0 / weight_c),
+            (_, PointHandle::C) => (1.0 / weight_b, 0.0),
+            _ => {
+                let norm = weight_b * weight_b + weight_c * weight_c;
+                (weight_b / norm, weight_c / norm)
+            }
+        };
+
+        if !share_b.is_finite() || !share_c.is_finite() {
+            return;
+        }
+
+        self.b.x += residual.x * share_b;
+        self.b.y += residual.y * share_b;
+
+        self.c.x += residual.x * share_c;
+        self.c.y += residual.y * share_c;
+    }
+
+    /// How the handle at the given anchor moves with a neighbouring curve's.
+    fn anchor_type(&self, anchor: PointHandle) -> AnchorType {
+        match anchor {
+            PointHandle::D => self.d_type,
+            _ => self.a_type,
+        }
+    }
+
+    fn anchor_type_mut(&mut self, anchor: PointHandle) -> &mut AnchorType {
+        match anchor {
+            PointHandle::D => &mut self.d_type,
+            _ => &mut self.a_type,
+        }
+    }
+
+    /// The anchor a control handle belongs to. A quadratic's single control
+    /// belongs to both, so it has none.
+    fn anchor_of(&self, handle: PointHandle) -> Option<PointHandle> {
+        match (handle, self.kind) {
+            (PointHandle::B, CurveKind::Cubic) => Some(PointHandle::A),
+            (PointHandle::C, CurveKind::Cubic) => Some(PointHandle::D),
+            _ => None,
+        }
+    }
+
+    /// The control point next to the given anchor.
+    fn handle_of(&self, anchor: PointHandle) -> PointHandle {
+        match (anchor, self.kind) {
+            (PointHandle::A, _) | (_, CurveKind::Quadratic) => PointHandle::B,
+            _ => PointHandle::C,
+        }
+    }
+
+    /// Moves the control points evenly onto the chord between the anchors.
+    fn straighten(&mut self) {
+        match self.kind {
+            CurveKind::Cubic => {
+                self.b = self.a.lerp(self.d, 1.0 / 3.0);
+                self.c = self.a.lerp(self.d, 2.0 / 3.0);
+            }
+            CurveKind::Quadratic => {
+                self.b = self.a.lerp(self.d, 0.5);
+                self.c = self.b;
+            }
+        }
+    }
+
+    /// A straight segment from `a` to `d`, with the control points on the
+    /// thirds so it can later be bent either way.
+    fn line(a: Point, d: Point) -> Self {
+        Self::new(a, a.lerp(d, 1.0 / 3.0), a.lerp(d, 2.0 / 3.0), d)
+    }
+
+    fn point(&self, handle: PointHandle) -> Point {
+        match handle {
+            PointHandle::A => self.a,
+            PointHandle::B => self.b,
+            PointHandle::C => self.c,
+            PointHandle::D => self.d,
+        }
+    }
+
+    fn point_mut(&mut self, handle: PointHandle) -> &mut Point {
+        match handle {
+            PointHandle::A => &mut self.a,
+            PointHandle::B => &mut self.b,
+            PointHandle::C => &mut self.c,
+            PointHandle::D => &mut self.d,
+        }
+    }
+
+    fn map(self, f: impl Fn(Point) -> Point) -> Self {
+        Self {
+            a: f(self.a),
+            b: f(self.b),
+            c: f(self.c),
+            d: f(self.d),
+            ..self
+        }
+    }
+
+    fn point_at(&self, t: f64) -> Point {
+        if let Some([point, ..]) = self.rational_derivatives(t) {
+            return Point::new(point.0, point.1);
+        }
+
+        let mt = 1.0 - t;
+        let [a, b, c, d] = self.cubic_points();
+
+        Point::new(
+            mt * mt * mt * a.x + 3.0 * mt * mt * t * b.x + 3.0 * mt * t * t * c.x + t * t * t * d.x,
+            mt * mt * mt * a.y + 3.0 * mt * mt * t * b.y + 3.0 * mt * t * t * c.y + t * t * t * d.y,
+        )
+    }
+
+    /// `n + 1` points at evenly spaced parameters from `t = 0` to `t = 1`
+    /// inclusive. Unlike `flatten`, the spacing ignores curvature.
+    fn samples(&self, n: usize) -> impl Iterator<Item = (f64, f64)> {
+        let curve = self.clone();
+
+        (0..=n).map(move |i| {
+            let t = if n == 0 { 0.0 } else { i as f64 / n as f64 };
+            let point = curve.point_at(t);
+
+            (point.x, point.y)
+        })
+    }
+
+    /// First derivative with respect to `t`, in world units per unit of `t`.
+    /// Tangents, curvature and arc lengths are all built on this and
+    /// `second_derivative_at`, so the formulas live only here.
+    fn derivative_at(&self, t: f64) -> (f64, f64) {
+        if let Some([_, first, _]) = self.rational_derivatives(t) {
+            return first;
+        }
+
+        let [a, b, c, d] = self.cubic_points();
+        let mt = 1.0 - t;
+
+        (
+            3.0 * mt * mt * (b.x - a.x) + 6.0 * mt * t * (c.x - b.x) + 3.0 * t * t * (d.x - c.x),
+            3.0 * mt * mt * (b.y - a.y) + 6.0 * mt * t * (c.y - b.y) + 3.0 * t * t * (d.y - c.y),
+        )
+    }
+
+    /// Second derivative with respect to `t`.
+    fn second_derivative_at(&self, t: f64) -> (f64, f64) {
+        if let Some([_, _, second]) = self.rational_derivatives(t) {
+            return second;
+        }
+
+        let [a, b, c, d] = self.cubic_points();
+        let mt = 1.0 - t;
+
+        (
+            6.0 * mt * (c.x - 2.0 * b.x + a.x) + 6.0 * t * (d.x - 2.0 * c.x + b.x),
+            6.0 * mt * (c.y - 2.0 * b.y + a.y) + 6.0 * t * (d.y - 2.0 * c.y + b.y),
+        )
+    }
+
+    /// The point at `t` and its first and second derivatives, if the curve is
+    /// rational. It is the quotient of the weighted points' curve by the
+    /// weights' curve, differentiated by the quotient rule.
+    fn rational_derivatives(&self, t: f64) -> Option<[(f64, f64); 3]> {
+        let weights = self.weights?;
+        let points = self.cubic_points();
+
+        let [x, dx, ddx] = bernstein(array::from_fn(|i| points[i].x * weights[i]), t);
+        let [y, dy, ddy] = bernstein(array::from_fn(|i| points[i].y * weights[i]), t);
+        let [w, dw, ddw] = bernstein(weights, t);
+
+        let point = (x / w, y / w);
+        let first = ((dx - point.0 * dw) / w, (dy - point.1 * dw) / w);
+        let second = (
+            (ddx - 2.0 * first.0 * dw - point.0 * ddw) / w,
+            (ddy - 2.0 * first.1 * dw - point.1 * ddw) / w,
+        );
+
+        Some([point, first, second])
+    }
+
+    /// Signed curvature at `t`, positive where the curve turns towards
+    /// `(-ty, tx)` for tangent `(tx, ty)`. `None` where the derivative
+    /// vanishes and curvature is undefined.
+    fn curvature_at(&self, t: f64) -> Option<f64> {
+        let (dx, dy) = self.derivative_at(t);
+        let (ddx, ddy) = self.second_derivative_at(t);
+        let speed = dx.hypot(dy);
+
+        (speed > 0.0).then(|| (dx * ddy - dy * ddx) / (speed * speed * speed))
+    }
+
+    /// Axis-aligned bounds of the control points as `(min, max)`. Looser than
+    /// `bounding_box` but cheaper, and they also contain the handles.
+    fn control_bounds(&self) -> (Point, Point) {
+        let points = self.cubic_points();
+
+        points
+            .iter()
+            .skip(1)
+            .fold((points[0], points[0]), |(min, max), p| {
+                (
+                    Point::new(min.x.min(p.x), min.y.min(p.y)),
+                    Point::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            })
+    }
+
+    /// Tight axis-aligned bounds as `(min, max)`, found from the endpoints and
+    /// wherever the derivative of either coordinate crosses zero.
+    fn bounding_box(&self) -> (Point, Point) {
+        if self.weights.is_some() {
+            return self.flatten(FLATTEN_TOLERANCE).into_iter().fold(
+                (self.a, self.a),
+                |(min, max), (x, y)| {
+                    (
+                        Point::new(min.x.min(x), min.y.min(y)),
+                        Point::new(max.x.max(x), max.y.max(y)),
+                    )
+                },
+            );
+        }
+
+        let mut min = Point::new(self.a.x.min(self.d.x), self.a.y.min(self.d.y));
+        let mut max = Point::new(self.a.x.max(self.d.x), self.a.y.max(self.d.y));
+
+        let [a, b, c, d] = self.cubic_points();
+        let xs = derivative_roots(a.x, b.x, c.x, d.x);
+        let ys = derivative_roots(a.y, b.y, c.y, d.y);
+
+        for t in xs.into_iter().chain(ys).flatten() {
+            let point = self.point_at(t);
+
+            min = Point::new(min.x.min(point.x), min.y.min(point.y));
+            max = Point::new(max.x.max(point.x), max.y.max(point.y));
+        }
+
+        (min, max)
+    }
+
+    /// Direction of travel at `a`, in radians clockwise from the x axis
+    /// since y points down. Where the derivative vanishes, as when `b` sits
+    /// on `a`, it is the chord's direction instead.
+    fn start_angle(&self) -> f64 {
+        self.end_direction(self.derivative_at(0.0))
+    }
+
+    /// Direction of travel at `d`, like `start_angle`.
+    fn end_angle(&self) -> f64 {
+        self.end_direction(self.derivative_at(1.0))
+    }
+
+    fn end_direction(&self, (x, y): (f64, f64)) -> f64 {
+        match (x, y) {
+            (0.0, 0.0) => (self.d.y - self.a.y).atan2(self.d.x - self.a.x),
+            _ => y.atan2(x),
+        }
+    }
+
+    /// Unit tangent at `t`, or `None` if the curve has no direction there
+    /// (all four points coincide).
+    ///
+    /// The derivative vanishes at an endpoint whose control point sits on
+    /// top of it, so the direction falls back to the next distinct point.
+    fn tangent_at(&self, t: f64) -> Option<(f64, f64)> {
+        let [a, b, c, d] = self.cubic_points().map(|p| (p.x, p.y));
+        let (mut x, mut y) = self.derivative_at(t);
+
+        if x == 0.0 && y == 0.0 {
+            let (from, to) = if t < 0.5 {
+                (a, [b, c, d].into_iter().find(|&p| p != a))
+            }
+            else {
+                (d, [c, b, a].into_iter().find(|&p| p != d))
+            };
+
+            let to = to?;
+
+            if t < 0.5 {
+                x = to.0 - from.0;
+                y = to.1 - from.1;
+            }
+            else {
+                x = from.0 - to.0;
+                y = from.1 - to.1;
+            }
+        }
+
+        let length = x.hypot(y);
+
+        if length == 0.0 {
+            return None;
+        }
+
+        Some((x / length, y / length))
+    }
+
+    /// Approximates the curve with a polyline whose segments stay within
+    /// `tolerance` pixels of it. The first and last points are `a` and `d`.
+    fn flatten(&self, tolerance: f64) -> Vec<(f64, f64)> {
+        let points = self.cubic_points().map(|p| (p.x, p.y));
+        let mut polyline = vec![points[0]];
+        let depth = MAX_SUBDIVISION_DEPTH.get();
+
+        let reached = match self.weights {
+            Some(_) => flatten_rational(self.clone(), tolerance, depth, &mut polyline),
+            None => flatten_recursive(points, tolerance, depth, &mut polyline),
+        };
+
+        if !reached {
+            warn_depth_reached("Flattening");
+        }
+
+        polyline
+    }
+
+    /// Farthest the curve strays from the segment between its ends, measured
+    /// on a flattening to `tolerance`.
+    fn chord_deviation(&self, tolerance: f64) -> f64 {
+        let (a, d) = ((self.a.x, self.a.y), (self.d.x, self.d.y));
+
+        self.flatten(tolerance)
+            .into_iter()
+            .map(|point| distance_to_segment(point, a, d))
+            .fold(0.0, f64::max)
+    }
+
+    /// Measured by quadrature, unless halving its intervals changes the
+    /// result by more than `ARC_LENGTH_TOLERANCE`, which means the speed
+    /// varies too sharply for it and the curve is flattened instead.
+    fn arc_length(&self) -> f64 {
+        let fine = self.arc_length_by(ArcLengthMethod::Quadrature {
+            intervals: ARC_LENGTH_INTERVALS,
+        });
+        let coarse = self.arc_length_by(ArcLengthMethod::Quadrature {
+            intervals: ARC_LENGTH_INTERVALS / 2,
+        });
+
+        if (fine - coarse).abs() <= ARC_LENGTH_TOLERANCE {
+            fine
+        }
+        else {
+            self.arc_length_by(ArcLengthMethod::Flatten)
+        }
+    }
+
+    fn arc_length_by(&self, method: ArcLengthMethod) -> f64 {
+        match method {
+            ArcLengthMethod::Quadrature { intervals } => {
+                let width = 1.0 / intervals as f64;
+
+                (0..intervals)
+                    .flat_map(|interval| {
+                        let middle = (interval as f64 + 0.5) * width;
+
+                        GAUSS_LEGENDRE.map(|(node, weight)| {
+                            let (dx, dy) = self.derivative_at(middle + node * width / 2.0);
+                            weight * width / 2.0 * dx.hypot(dy)
+                        })
+                    })
+                    .sum()
+            }
+            ArcLengthMethod::Flatten => self
+                .flatten(ARC_LENGTH_TOLERANCE)
+                .windows(2)
+                .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+                .sum(),
+        }
+    }
+
+    /// Distance from `point` to the nearest point on the curve, accurate to
+    /// within `FLATTEN_TOLERANCE`.
+    fn distance_to(&self, point: Point) -> f64 {
+        self.flatten(FLATTEN_TOLERANCE)
+            .windows(2)
+            .map(|pair| distance_to_segment((point.x, point.y), pair[0], pair[1]))
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.a == self.d
+    }
 
-    if let Some(a) = state.new_curve.get() {
-        context.set_stroke_style(&JsString::from("black"));
-        context.begin_path();
-        context.move_to(a.x as _, a.y as _);
-        context.line_to(state.mouse.get().x as _, state.mouse.get().y as _);
-        context.stroke();
+    /// Whether the curve has collapsed to (nearly) a single point, which
+    /// leaves it invisible and impossible to grab by anything but a handle.
+    fn is_degenerate(&self) -> bool {
+        let (min, max) = self.bounding_box();
+
+        max.x - min.x < DEGENERATE_EXTENT && max.y - min.y < DEGENERATE_EXTENT
     }
 }
 
-struct State {
-    curves: RefCell<Vec<Curve>>,
-    drag_state: Cell<Option<DragState>>,
-    new_curve: Cell<Option<Point>>,
-    mouse: Cell<Point>,
-}
+/// Parameters in `(0, 1)` where the derivative of the one-dimensional cubic
+/// with coefficients `p0..p3` vanishes.
+fn derivative_roots(p0: f64, p1: f64, p2: f64, p3: f64) -> [Option<f64>; 2] {
+    // B'(t) / 3 = a t^2 + b t + c
+    let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+    let b = 2.0 * (p0 - 2.0 * p1 + p2);
+    let c = p1 - p0;
 
-#[derive(Clone, Copy)]
-struct DragState {
-    curve_index: usize,
-    point: PointHandle,
-}
+    let inside = |t: f64| (t > 0.0 && t < 1.0).then_some(t);
 
-impl DragState {
-    fn new(curve_index: usize, point: PointHandle) -> Self {
-        Self { curve_index, point }
+    if a.abs() < 1e-12 {
+        if b.abs() < 1e-12 {
+            return [None, None];
+        }
+
+        return [inside(-c / b), None];
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+
+    if discriminant < 0.0 {
+        return [None, None];
     }
+
+    let root = discriminant.sqrt();
+
+    [
+        inside((-b + root) / (2.0 * a)),
+        inside((-b - root) / (2.0 * a)),
+    ]
 }
 
-#[derive(Clone, Copy)]
-enum PointHandle {
-    A,
-    B,
-    C,
-    D,
+/// Like `flatten_recursive`, for a rational curve. With positive weights it
+/// also lies within its control points' hull, so the same test applies, but
+/// halving it takes `Curve::split`.
+fn flatten_rational(
+    curve: Curve,
+    tolerance: f64,
+    depth: u32,
+    polyline: &mut Vec<(f64, f64)>,
+) -> bool {
+    let [a, b, c, d] = curve.cubic_points().map(|p| (p.x, p.y));
+
+    if distance_to_segment(b, a, d) <= tolerance && distance_to_segment(c, a, d) <= tolerance {
+        polyline.push(d);
+        return true;
+    }
+
+    if depth == 0 {
+        polyline.push(d);
+        return false;
+    }
+
+    let (first, second) = curve.split(0.5);
+
+    let first = flatten_rational(first, tolerance, depth - 1, polyline);
+    let second = flatten_rational(second, tolerance, depth - 1, polyline);
+
+    first && second
 }
 
-fn is_point_inside_circle(center: Point, radius: u32, point: Point) -> bool {
-    let cx = center.x as f64;
-    let cy = center.y as f64;
-    let x = point.x as f64;
-    let y = point.y as f64;
-    let r = radius as f64;
+/// The value and first and second derivatives at `t` of the one-dimensional
+/// cubic bezier with coefficients `q`.
+fn bernstein(q: [f64; 4], t: f64) -> [f64; 3] {
+    let mt = 1.0 - t;
 
-    (cx - x).powi(2) + (cy - y).powi(2) <= r.powi(2)
+    [
+        mt * mt * mt * q[0] + 3.0 * mt * mt * t * q[1] + 3.0 * mt * t * t * q[2] + t * t * t * q[3],
+        3.0 * (mt * mt * (q[1] - q[0]) + 2.0 * mt * t * (q[2] - q[1]) + t * t * (q[3] - q[2])),
+        6.0 * (mt * (q[2] - 2.0 * q[1] + q[0]) + t * (q[3] - 2.0 * q[2] + q[1])),
+    ]
 }
 
-fn draw_curve(context: &CanvasRenderingContext2d, curve: Curve) {
-    let ax = curve.a.x as f64;
-    let ay = curve.a.y as f64;
-    let bx = curve.b.x as f64;
-    let by = curve.b.y as f64;
-    let cx = curve.c.x as f64;
-    let cy = curve.c.y as f64;
-    let dx = curve.d.x as f64;
-    let dy = curve.d.y as f64;
+/// Returns whether every piece came within `tolerance`, rather than some
+/// being cut short by running out of `depth`.
+fn flatten_recursive(
+    points: [(f64, f64); 4],
+    tolerance: f64,
+    depth: u32,
+    polyline: &mut Vec<(f64, f64)>,
+) -> bool {
+    let [a, b, c, d] = points;
 
-    context.set_stroke_style(&JsString::from("black"));
-    context.begin_path();
-    context.move_to(ax, ay);
-    context.bezier_curve_to(bx, by, cx, cy, dx, dy);
-    context.stroke();
+    // The control points bound how far the curve strays from its chord, so
+    // once both sit close enough to the chord the chord itself will do.
+    if distance_to_segment(b, a, d) <= tolerance && distance_to_segment(c, a, d) <= tolerance {
+        polyline.push(d);
+        return true;
+    }
 
-    context.begin_path();
-    context.move_to(ax, ay);
-    context.line_to(bx, by);
-    context.stroke();
+    if depth == 0 {
+        polyline.push(d);
+        return false;
+    }
 
-    context.begin_path();
-    context.move_to(dx, dy);
-    context.line_to(cx, cy);
-    context.stroke();
+    let mid = |p: (f64, f64), q: (f64, f64)| ((p.0 + q.0) / 2.0, (p.1 + q.1) / 2.0);
 
-    context.set_fill_style(&JsString::from("blue"));
-    context.begin_path();
-    context
-        .arc(ax, ay, POINT_RADIUS as _, 0.0, 2.0 * PI)
-        .unwrap();
-    context
-        .arc(dx, dy, POINT_RADIUS as _, 0.0, 2.0 * PI)
-        .unwrap();
-    context.fill();
+    let ab = mid(a, b);
+    let bc = mid(b, c);
+    let cd = mid(c, d);
+    let abc = mid(ab, bc);
+    let bcd = mid(bc, cd);
+    let abcd = mid(abc, bcd);
 
-    context.set_fill_style(&JsString::from("red"));
-    context.begin_path();
-    context
-        .arc(bx, by, POINT_RADIUS as _, 0.0, 2.0 * PI)
-        .unwrap();
-    context
-        .arc(cx, cy, POINT_RADIUS as _, 0.0, 2.0 * PI)
-        .unwrap();
-    context.fill();
+    let first = flatten_recursive([a, ab, abc, abcd], tolerance, depth - 1, polyline);
+    let second = flatten_recursive([abcd, bcd, cd, d], tolerance, depth - 1, polyline);
+
+    first && second
 }
 
-#[derive(Clone, Copy)]
-struct Point {
-    x: i32,
-    y: i32,
+/// Notes in the console that `operation` stopped subdividing at
+/// `MAX_SUBDIVISION_DEPTH`, so its result is rougher than asked for.
+fn warn_depth_reached(operation: &str) {
+    console::warn_1(&JsValue::from(format!(
+        "{operation} reached the maximum subdivision depth of {}; the result is approximate",
+        MAX_SUBDIVISION_DEPTH.get()
+    )));
 }
 
-impl Point {
-    fn new(x: i32, y: i32) -> Self {
-        Self { x, y }
+fn distance_to_segment(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_squared = dx * dx + dy * dy;
+
+    let t = if length_squared == 0.0 {
+        0.0
     }
-}
+    else {
+        (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length_squared).clamp(0.0, 1.0)
+    };
 
-#[derive(Clone, Copy)]
-struct Curve {
-    a: Point,
-    b: Point,
-    c: Point,
-    d: Point,
+    (point.0 - (a.0 + t * dx)).hypot(point.1 - (a.1 + t * dy))
 }
 
-impl Curve {
-    fn new(a: Point, b: Point, c: Point, d: Point) -> Self {
-        Self { a, b, c, d }
+/// Formats a coordinate for text exports with at most `precision` decimal
+/// places, dropping trailing zeros so round values stay short. Every exporter
+/// goes through this so the same scene always serializes the same way.
+fn format_coordinate(value: f64, precision: usize) -> String {
+    let mut text = format!("{value:.precision$}");
+
+    if text.contains('.') {
+        text.truncate(text.trim_end_matches('0').trim_end_matches('.').len());
+    }
+
+    if text == "-0" {
+        text.remove(0);
     }
+
+    text
+}
+
+fn download_text(filename: &str, mime: &str, text: &str) {
+    let options = BlobPropertyBag::new();
+    options.set_type(mime);
+
+    let blob = Blob::new_with_str_sequence_and_options(&Array::of1(&JsValue::from(text)), &options)
+        .unwrap();
+
+    let url = Url::create_object_url_with_blob(&blob).unwrap();
+    download_url(filename, &url);
+    Url::revoke_object_url(&url).unwrap();
+}
+
+fn download_url(filename: &str, url: &str) {
+    let document = window().unwrap().document().unwrap();
+
+    let anchor: HtmlAnchorElement = document.create_element("a").unwrap().dyn_into().unwrap();
+    anchor.set_href(url);
+    anchor.set_download(filename);
+    anchor.click();
+}
+
+/// A canvas of the given size that is not part of the page, for rendering
+/// exports.
+fn offscreen_canvas(width: u32, height: u32) -> (HtmlCanvasElement, CanvasRenderingContext2d) {
+    let canvas: HtmlCanvasElement = window()
+        .unwrap()
+        .document()
+        .unwrap()
+        .create_element("canvas")
+        .unwrap()
+        .dyn_into()
+        .unwrap();
+
+    canvas.set_width(width);
+    canvas.set_height(height);
+
+    let context = canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into()
+        .unwrap();
+
+    (canvas, context)
 }
 
-fn add_event_listener(
+fn add_event_listener<E: FromWasmAbi + 'static>(
     target: &EventTarget,
     kind: &str,
-    listener: impl FnMut(MouseEvent) + 'static,
+    listener: impl FnMut(E) + 'static,
 ) {
     let closure = Closure::<dyn FnMut(_)>::new(listener);
 
@@ -322,3 +8655,696 @@ fn add_event_listener(
 
     closure.forget();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+    use std::f64::consts::FRAC_PI_4;
+    use std::f64::consts::SQRT_2;
+
+    /// An editor with no page behind it. Its elements are `null`, so only
+    /// code that leaves the page alone, and doesn't announce, can run on it.
+    pub(crate) fn detached_state() -> State {
+        let element = || JsValue::NULL.unchecked_into::<HtmlElement>();
+        let state = State::new(
+            0,
+            element(),
+            element(),
+            element(),
+            element(),
+            SceneLayer {
+                canvas: JsValue::NULL.unchecked_into(),
+                context: JsValue::NULL.unchecked_into(),
+                key: RefCell::new(None),
+                progress: Cell::new(0),
+                finished: JsValue::NULL.unchecked_into(),
+                finished_context: JsValue::NULL.unchecked_into(),
+                has_finished: Cell::new(false),
+            },
+        );
+
+        // Without a page there are no animation frames to ask for.
+        state.frame_requested.set(true);
+        state
+    }
+
+    fn assert_near(p: Point, q: Point) {
+        assert!(
+            distance(p, q) < 1e-9,
+            "({}, {}) != ({}, {})",
+            p.x,
+            p.y,
+            q.x,
+            q.y
+        );
+    }
+
+    #[test]
+    fn arc_length_cache_measures_edited_curves_again() {
+        let mut cache = ArcLengthCache::default();
+        let mut curve = Curve::line(Point::new(0.0, 0.0), Point::new(10.0, 0.0));
+
+        assert!((cache.length(2, curve.clone()) - 10.0).abs() < 1e-9);
+
+        curve.d = Point::new(20.0, 0.0);
+        curve.c = Point::new(15.0, 0.0);
+        assert!((cache.length(2, curve.clone()) - 20.0).abs() < 1e-9);
+
+        // Another curve moved into the index is measured too.
+        let other = Curve::line(Point::new(0.0, 0.0), Point::new(0.0, 5.0));
+        assert!((cache.length(2, other) - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quadratics_elevate_to_cubics_exactly() {
+        let (a, b, d) = (
+            Point::new(0.0, 0.0),
+            Point::new(40.0, 90.0),
+            Point::new(100.0, 10.0),
+        );
+        let cubic = Curve::quadratic(a, b, d).to_cubic();
+
+        assert!(cubic.kind == CurveKind::Cubic);
+
+        for step in 0..=10 {
+            let t = step as f64 / 10.0;
+            let mt = 1.0 - t;
+            let expected = Point::new(
+                mt * mt * a.x + 2.0 * mt * t * b.x + t * t * d.x,
+                mt * mt * a.y + 2.0 * mt * t * b.y + t * t * d.y,
+            );
+
+            assert_near(cubic.point_at(t), expected);
+        }
+
+        // An elevated quadratic converts back to the same control point.
+        assert_near(cubic.to_quadratic().b, b);
+
+        // Weights of the cubic's points mean nothing to the quadratic.
+        let rational = Curve {
+            weights: Some([1.0, 2.0, 2.0, 1.0]),
+            ..cubic
+        };
+        assert!(rational.to_quadratic().weights.is_none());
+    }
+
+    #[test]
+    fn samples_run_from_start_to_end() {
+        let curve = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 30.0),
+            Point::new(50.0, -20.0),
+            Point::new(60.0, 5.0),
+        );
+        let samples: Vec<(f64, f64)> = curve.samples(8).collect();
+
+        assert_eq!(samples.len(), 9);
+        assert_eq!(samples[0], (0.0, 0.0));
+        assert_eq!(samples[8], (60.0, 5.0));
+
+        let middle = curve.point_at(0.5);
+        assert_eq!(samples[4], (middle.x, middle.y));
+
+        assert_eq!(curve.samples(0).collect::<Vec<_>>(), [(0.0, 0.0)]);
+    }
+
+    #[test]
+    fn transforming_the_selection_is_one_undo_step() {
+        let state = detached_state();
+        let original: Vec<Curve> = (0..10)
+            .map(|i| Curve::line(Point::new(i as f64, 0.0), Point::new(i as f64, 10.0)))
+            .collect();
+        *state.curves.borrow_mut() = original.clone();
+        *state.selection.borrow_mut() = (0..10).collect();
+
+        // Dragging a gizmo corner, as the mouse handlers do, through several
+        // moves before letting go.
+        let bounds = selection_bounds(&state).unwrap();
+        let scaling = Scaling {
+            sides: (1.0, 1.0),
+            bounds,
+        };
+        *state.drag_origin.borrow_mut() = state.curves.borrow().clone();
+
+        for step in 1..=5 {
+            let reach = 10.0 * step as f64;
+            let mouse = Point::new(bounds.1.x + reach, bounds.1.y + reach);
+            scale_selection(&state, scaling, mouse, false);
+        }
+
+        finish_transform(&state);
+        let scaled = state.curves.borrow().clone();
+
+        assert!(scaled
+            .iter()
+            .zip(&original)
+            .all(|(scaled, original)| scaled != original));
+
+        *state.drag_origin.borrow_mut() = scaled.clone();
+
+        for step in 1..=5 {
+            rotate_selection(&state, bounds.0, 0.1 * step as f64);
+        }
+
+        finish_transform(&state);
+
+        let mut history = state.history.borrow_mut();
+        let mut curves = state.curves.borrow_mut();
+
+        assert!(history.undo(&mut curves));
+        assert!(*curves == scaled);
+        assert!(history.undo(&mut curves));
+        assert!(*curves == original);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn transaction_without_changes_records_nothing() {
+        let state = detached_state();
+        *state.curves.borrow_mut() = vec![Curve::line(Point::new(0.0, 0.0), Point::new(1.0, 1.0))];
+
+        transaction(&state, || {});
+
+        assert!(!can_undo(&state));
+    }
+
+    #[test]
+    fn scene_bounds_cover_every_curve() {
+        let state = detached_state();
+        assert!(scene_bounds(&state).is_none());
+
+        // An arch peaking at y = 30 halfway along, well inside its handles.
+        *state.curves.borrow_mut() = vec![
+            Curve::new(
+                Point::new(0.0, 0.0),
+                Point::new(0.0, 40.0),
+                Point::new(100.0, 40.0),
+                Point::new(100.0, 0.0),
+            ),
+            Curve::line(Point::new(-20.0, -10.0), Point::new(50.0, -5.0)),
+        ];
+
+        let (min, max) = scene_bounds(&state).unwrap();
+        assert_near(min, Point::new(-20.0, -10.0));
+        assert_near(max, Point::new(100.0, 30.0));
+    }
+
+    #[test]
+    fn derivatives_match_finite_differences() {
+        let cubic = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(30.0, 80.0),
+            Point::new(90.0, -40.0),
+            Point::new(120.0, 20.0),
+        );
+        let quadratic = Curve::quadratic(
+            Point::new(0.0, 0.0),
+            Point::new(50.0, 100.0),
+            Point::new(100.0, 0.0),
+        );
+        let rational = Curve {
+            weights: Some([1.0, 2.5, 0.5, 1.0]),
+            ..cubic.clone()
+        };
+        let h = 1e-4;
+
+        for curve in [cubic, quadratic, rational] {
+            for t in [0.1, 0.35, 0.5, 0.8] {
+                let before = curve.point_at(t - h);
+                let at = curve.point_at(t);
+                let after = curve.point_at(t + h);
+
+                let (dx, dy) = curve.derivative_at(t);
+                assert!((dx - (after.x - before.x) / (2.0 * h)).abs() < 1e-4);
+                assert!((dy - (after.y - before.y) / (2.0 * h)).abs() < 1e-4);
+
+                let (ddx, ddy) = curve.second_derivative_at(t);
+                assert!((ddx - (after.x - 2.0 * at.x + before.x) / (h * h)).abs() < 1e-2);
+                assert!((ddy - (after.y - 2.0 * at.y + before.y) / (h * h)).abs() < 1e-2);
+            }
+        }
+    }
+
+    /// Length of `curve` summed over a very fine polyline.
+    fn sampled_length(curve: &Curve) -> f64 {
+        let points: Vec<_> = curve.samples(100_000).collect();
+
+        points
+            .windows(2)
+            .map(|pair| (pair[1].0 - pair[0].0).hypot(pair[1].1 - pair[0].1))
+            .sum()
+    }
+
+    #[test]
+    fn quadrature_measures_arc_length() {
+        let curves = [
+            Curve::new(
+                Point::new(0.0, 0.0),
+                Point::new(30.0, 80.0),
+                Point::new(90.0, -40.0),
+                Point::new(120.0, 20.0),
+            ),
+            Curve::quadratic(
+                Point::new(0.0, 0.0),
+                Point::new(50.0, 100.0),
+                Point::new(100.0, 0.0),
+            ),
+            Curve::line(Point::new(0.0, 0.0), Point::new(30.0, 40.0)),
+        ];
+
+        for curve in curves {
+            let reference = sampled_length(&curve);
+            let quadrature = curve.arc_length_by(ArcLengthMethod::Quadrature {
+                intervals: ARC_LENGTH_INTERVALS,
+            });
+
+            assert!((quadrature - reference).abs() < 1e-4);
+            assert!((curve.arc_length() - reference).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn arc_length_around_a_cusp_falls_back_to_flattening() {
+        // Doubles back on itself, stopping dead where it turns.
+        let curve = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(120.0, 0.0),
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+        );
+
+        let fine = curve.arc_length_by(ArcLengthMethod::Quadrature {
+            intervals: ARC_LENGTH_INTERVALS,
+        });
+        let coarse = curve.arc_length_by(ArcLengthMethod::Quadrature {
+            intervals: ARC_LENGTH_INTERVALS / 2,
+        });
+        assert!((fine - coarse).abs() > ARC_LENGTH_TOLERANCE);
+
+        let reference = sampled_length(&curve);
+        assert!((curve.arc_length() - reference).abs() < ARC_LENGTH_TOLERANCE * 10.0);
+    }
+
+    #[test]
+    fn curves_split_into_pieces_of_equal_length() {
+        let curve = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 120.0),
+            Point::new(140.0, 90.0),
+            Point::new(150.0, 0.0),
+        );
+        let pieces = curve.split_by_arclen(5);
+        let length = curve.arc_length();
+
+        assert_eq!(pieces.len(), 5);
+
+        for piece in &pieces {
+            assert!((piece.arc_length() - length / 5.0).abs() < length * 1e-3);
+        }
+
+        // End to end, the pieces retrace the curve.
+        assert_near(pieces[0].a, curve.a);
+        assert_near(pieces[4].d, curve.d);
+
+        for pair in pieces.windows(2) {
+            assert_near(pair[0].d, pair[1].a);
+        }
+
+        for piece in &pieces {
+            for t in [0.25, 0.5, 0.75] {
+                assert!(curve.distance_to(piece.point_at(t)) <= FLATTEN_TOLERANCE);
+            }
+        }
+    }
+
+    #[test]
+    fn flattening_stops_at_the_depth_limit() {
+        // A non-finite control point is never close enough to the chord.
+        let points = [(0.0, 0.0), (f64::NAN, 5.0), (8.0, 5.0), (10.0, 0.0)];
+        let mut polyline = vec![points[0]];
+
+        assert!(!flatten_recursive(
+            points,
+            FLATTEN_TOLERANCE,
+            8,
+            &mut polyline
+        ));
+        assert_eq!(polyline.len(), (1 << 8) + 1);
+        assert_eq!(polyline.last(), Some(&(10.0, 0.0)));
+
+        let mut polyline = vec![(0.0, 0.0)];
+        let curve = [(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)];
+        assert!(flatten_recursive(
+            curve,
+            FLATTEN_TOLERANCE,
+            8,
+            &mut polyline
+        ));
+    }
+
+    #[test]
+    fn curve_at_finds_the_topmost_curve() {
+        let state = detached_state();
+        *state.curves.borrow_mut() = vec![
+            Curve::line(Point::new(0.0, 0.0), Point::new(100.0, 0.0)),
+            Curve::line(Point::new(50.0, -50.0), Point::new(50.0, 50.0)),
+            Curve::line(Point::new(0.0, 1.0), Point::new(100.0, 1.0)),
+        ];
+
+        // Where all three overlap, the last drawn is on top.
+        assert_eq!(curve_at(&state, Point::new(50.0, 0.5), 2.0), Some(2));
+        assert_eq!(curve_at(&state, Point::new(50.0, 30.0), 2.0), Some(1));
+        assert_eq!(curve_at(&state, Point::new(10.0, -1.5), 2.0), Some(0));
+        assert_eq!(curve_at(&state, Point::new(10.0, 20.0), 2.0), None);
+    }
+
+    #[test]
+    fn end_angles_follow_the_handles() {
+        let curve = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 10.0),
+            Point::new(20.0, -10.0),
+            Point::new(30.0, 0.0),
+        );
+        assert!((curve.start_angle() - FRAC_PI_4).abs() < 1e-12);
+        assert!((curve.end_angle() - FRAC_PI_4).abs() < 1e-12);
+
+        let curve = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 10.0),
+            Point::new(10.0, 0.0),
+            Point::new(-10.0, 0.0),
+        );
+        assert!((curve.start_angle() - FRAC_PI_2).abs() < 1e-12);
+        assert!((curve.end_angle().abs() - PI).abs() < 1e-12);
+    }
+
+    #[test]
+    fn end_angles_fall_back_to_the_chord() {
+        // Handles on their anchors leave no derivative at either end.
+        let curve = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 10.0),
+            Point::new(0.0, 10.0),
+        );
+
+        assert!((curve.start_angle() - FRAC_PI_2).abs() < 1e-12);
+        assert!((curve.end_angle() - FRAC_PI_2).abs() < 1e-12);
+    }
+
+    #[test]
+    fn coinciding_handles_grab_the_top_curve() {
+        let curves = [
+            Curve::line(Point::new(0.0, 0.0), Point::new(90.0, 0.0)),
+            Curve::line(Point::new(90.0, 0.0), Point::new(90.0, 90.0)),
+        ];
+        let camera = Camera::default();
+
+        // The first curve's end and the second's start coincide.
+        assert!(
+            handle_at(&curves, camera, Point::new(90.0, 1.0), 5.0) == Some((1, PointHandle::A))
+        );
+        assert!(handle_at(&curves, camera, Point::new(1.0, 0.0), 5.0) == Some((0, PointHandle::A)));
+    }
+
+    /// A quarter of the circle of radius 100 around the origin, as a rational
+    /// cubic: the exact rational quadratic, raised a degree.
+    fn quarter_circle() -> Curve {
+        let handle = 100.0 * (2.0 - SQRT_2);
+        let inner = (1.0 + SQRT_2) / 3.0;
+
+        Curve {
+            weights: Some([1.0, inner, inner, 1.0]),
+            ..Curve::new(
+                Point::new(100.0, 0.0),
+                Point::new(100.0, handle),
+                Point::new(handle, 100.0),
+                Point::new(0.0, 100.0),
+            )
+        }
+    }
+
+    #[test]
+    fn unit_weights_change_nothing() {
+        let curve = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(30.0, 80.0),
+            Point::new(90.0, -40.0),
+            Point::new(120.0, 20.0),
+        );
+        let rational = Curve {
+            weights: Some([1.0; 4]),
+            ..curve.clone()
+        };
+
+        for step in 0..=10 {
+            let t = step as f64 / 10.0;
+            assert_near(rational.point_at(t), curve.point_at(t));
+        }
+
+        let (rational, plain) = (
+            rational.flatten(FLATTEN_TOLERANCE),
+            curve.flatten(FLATTEN_TOLERANCE),
+        );
+        assert_eq!(rational.len(), plain.len());
+
+        for (p, q) in rational.into_iter().zip(plain) {
+            assert_near(Point::new(p.0, p.1), Point::new(q.0, q.1));
+        }
+    }
+
+    #[test]
+    fn weights_make_circular_arcs() {
+        let arc = quarter_circle();
+
+        for step in 0..=20 {
+            let point = arc.point_at(step as f64 / 20.0);
+            assert!((point.x.hypot(point.y) - 100.0).abs() < 1e-9);
+        }
+
+        for (x, y) in arc.flatten(FLATTEN_TOLERANCE) {
+            assert!((x.hypot(y) - 100.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn rational_flattening_follows_the_curve() {
+        let curve = Curve {
+            weights: Some([1.0, 3.0, 0.4, 1.5]),
+            ..Curve::new(
+                Point::new(0.0, 0.0),
+                Point::new(20.0, 120.0),
+                Point::new(140.0, 90.0),
+                Point::new(150.0, 0.0),
+            )
+        };
+        let polyline = curve.flatten(FLATTEN_TOLERANCE);
+
+        assert_eq!(polyline.first(), Some(&(0.0, 0.0)));
+        assert_eq!(polyline.last(), Some(&(150.0, 0.0)));
+
+        for step in 0..=100 {
+            let point = curve.point_at(step as f64 / 100.0);
+            let off = polyline
+                .windows(2)
+                .map(|pair| distance_to_segment((point.x, point.y), pair[0], pair[1]))
+                .fold(f64::INFINITY, f64::min);
+
+            assert!(off <= FLATTEN_TOLERANCE);
+        }
+    }
+
+    #[test]
+    fn set_curve_keeps_the_curve_consistent() {
+        let state = detached_state();
+        let line = Curve::line(Point::new(0.0, 0.0), Point::new(90.0, 0.0));
+        *state.curves.borrow_mut() = vec![
+            Curve {
+                pin: Some(Pin {
+                    t: 0.5,
+                    point: Point::new(45.0, 0.0),
+                }),
+                ..line.clone()
+            },
+            Curve {
+                locked_straight: true,
+                ..line.clone()
+            },
+            Curve {
+                weights: Some([1.0, 2.0, 2.0, 1.0]),
+                ..line
+            },
+        ];
+        let arch = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(0.0, 60.0),
+            Point::new(90.0, 60.0),
+            Point::new(90.0, 0.0),
+        );
+
+        for index in 0..3 {
+            assert!(set_curve(&state, index, arch.clone()).is_ok());
+        }
+
+        let curves = state.curves.borrow().clone();
+        assert_near(curves[0].point_at(0.5), Point::new(45.0, 0.0));
+        let chord = Curve::line(arch.a, arch.d);
+        assert_near(curves[1].b, chord.b);
+        assert_near(curves[1].c, chord.c);
+        assert!(curves[2].weights.is_some());
+
+        let quadratic = Curve::quadratic(
+            Point::new(0.0, 0.0),
+            Point::new(45.0, 60.0),
+            Point::new(90.0, 0.0),
+        );
+        assert!(set_curve(&state, 2, quadratic).is_ok());
+        assert!(state.curves.borrow()[2].weights.is_none());
+    }
+
+    #[test]
+    fn set_curve_rejects_non_finite_points() {
+        let state = detached_state();
+        let line = Curve::line(Point::new(0.0, 0.0), Point::new(90.0, 0.0));
+        *state.curves.borrow_mut() = vec![line.clone()];
+
+        for bad in [f64::NAN, f64::INFINITY] {
+            let shape = Curve::line(Point::new(0.0, 0.0), Point::new(bad, 0.0));
+            assert!(set_curve(&state, 0, shape).is_err());
+        }
+
+        assert!(set_curve(&state, 1, line.clone()).is_err());
+        assert!(state.curves.borrow()[0] == line);
+    }
+
+    #[test]
+    fn both_halves_keep_the_metadata() {
+        let curve = Curve {
+            meta: Some(Rc::from("{\"id\": 7}")),
+            ..Curve::line(Point::new(0.0, 0.0), Point::new(90.0, 0.0))
+        };
+
+        let (first, second) = curve.clone().split(0.4);
+
+        for piece in [first, second].into_iter().chain(curve.split_by_arclen(3)) {
+            assert!(piece.meta == curve.meta);
+            assert_eq!(piece.meta.as_deref(), Some("{\"id\": 7}"));
+        }
+    }
+
+    #[test]
+    fn fitting_brings_far_curves_into_view() {
+        let (width, height) = (800.0, 600.0);
+        let scenes = [
+            vec![Curve::line(
+                Point::new(1e7, 1e7),
+                Point::new(1e7 + 0.5, 1e7 + 0.3),
+            )],
+            vec![Curve::new(
+                Point::new(-1e7, -1e7),
+                Point::new(-1e7 + 40.0, -1e7 - 90.0),
+                Point::new(-1e7 + 160.0, -1e7 + 90.0),
+                Point::new(-1e7 + 200.0, -1e7),
+            )],
+            vec![
+                Curve::line(Point::new(-1e7, 1e7), Point::new(-1e7 + 10.0, 1e7)),
+                Curve::line(Point::new(1e7, -1e7), Point::new(1e7, -1e7 + 10.0)),
+            ],
+        ];
+
+        for curves in scenes {
+            let camera = Camera::fitting(curves_bounds(&curves).unwrap(), width, height);
+
+            for curve in curves {
+                let (min, max) = camera.curve_to_screen(curve).bounding_box();
+
+                assert!(min.x >= 0.0 && min.y >= 0.0, "({}, {})", min.x, min.y);
+                assert!(max.x <= width && max.y <= height, "({}, {})", max.x, max.y);
+            }
+        }
+    }
+
+    #[test]
+    fn culling_keeps_every_curve_that_shows() {
+        /// A fixed linear congruential sequence, scaled to `low..high`.
+        fn random(seed: &mut u64, low: f64, high: f64) -> f64 {
+            *seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            low + (high - low) * (*seed >> 11) as f64 / (1_u64 << 53) as f64
+        }
+
+        fn around(seed: &mut u64, center: Point, spread: f64) -> Point {
+            Point::new(
+                random(seed, center.x - spread, center.x + spread),
+                random(seed, center.y - spread, center.y + spread),
+            )
+        }
+
+        let (width, height, radius) = (800.0, 600.0, 5.0);
+        let mut seed = 12345;
+        let mut curves = Vec::new();
+
+        // Spread over a box three times the canvas's size, so most curves
+        // straddle its edges or miss it.
+        for _ in 0..4000 {
+            let a = around(
+                &mut seed,
+                Point::new(width / 2.0, height / 2.0),
+                1.5 * width,
+            );
+            let mut curve = Curve::new(
+                a,
+                around(&mut seed, a, 200.0),
+                around(&mut seed, a, 200.0),
+                around(&mut seed, a, 200.0),
+            );
+            curve.style.width = random(&mut seed, 1.0, 20.0);
+            curves.push(curve);
+        }
+
+        // Partly visible: only a sliver of each crosses an edge.
+        curves.extend([
+            Curve::line(Point::new(-300.0, 10.0), Point::new(1.0, 10.0)),
+            Curve::line(
+                Point::new(width - 1.0, 590.0),
+                Point::new(width + 300.0, 590.0),
+            ),
+            Curve::line(Point::new(400.0, -300.0), Point::new(400.0, 1.0)),
+            Curve::line(
+                Point::new(400.0, height - 1.0),
+                Point::new(400.0, height + 300.0),
+            ),
+            // A loop dipping into the top left corner.
+            Curve::new(
+                Point::new(-100.0, -100.0),
+                Point::new(200.0, -100.0),
+                Point::new(-100.0, 200.0),
+                Point::new(-100.0, -100.0),
+            ),
+        ]);
+
+        let mut culled = 0;
+
+        for curve in &curves {
+            if is_on_screen(curve, width, height, radius) {
+                continue;
+            }
+
+            // Neither the curve nor anything drawn around it reaches the
+            // canvas.
+            let margin = curve.style.width + SELECTION_HALO_WIDTH + radius;
+            assert!(!curve.samples(200).any(|(x, y)| {
+                (-margin..=width + margin).contains(&x) && (-margin..=height + margin).contains(&y)
+            }));
+            culled += 1;
+        }
+
+        assert!(curves[4000..]
+            .iter()
+            .all(|curve| is_on_screen(curve, width, height, radius)));
+        assert!(culled > curves.len() / 4, "only {culled} culled");
+    }
+}
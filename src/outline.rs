@@ -0,0 +1,102 @@
+/// `points` moved `distance` to the left of the way they run, or to the
+/// right for a negative `distance`. Corners are mitered, with miters cut
+/// short at `miter_limit` times `distance`. A `closed` polyline's last point
+/// should repeat its first.
+pub fn offset(
+    points: &[(f64, f64)],
+    distance: f64,
+    closed: bool,
+    miter_limit: f64,
+) -> Vec<(f64, f64)> {
+    let mut points = points.to_vec();
+    points.dedup();
+
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+
+    if points.len() < 2 {
+        return points;
+    }
+
+    // Left normals of the segments, the closing one included if closed.
+    let segments = if closed {
+        points.len()
+    }
+    else {
+        points.len() - 1
+    };
+    let normals: Vec<(f64, f64)> = (0..segments)
+        .map(|index| {
+            let (x, y) = points[index];
+            let (next_x, next_y) = points[(index + 1) % points.len()];
+            let length = (next_x - x).hypot(next_y - y);
+
+            (-(next_y - y) / length, (next_x - x) / length)
+        })
+        .collect();
+
+    let mut offset: Vec<(f64, f64)> = points
+        .iter()
+        .enumerate()
+        .map(|(index, &(x, y))| {
+            let before = match (index, closed) {
+                (0, false) => None,
+                (0, true) => normals.last().copied(),
+                _ => Some(normals[index - 1]),
+            };
+            let after = normals.get(index).copied();
+
+            let (nx, ny) = match (before, after) {
+                (Some(before), Some(after)) => {
+                    let (sum_x, sum_y) = (before.0 + after.0, before.1 + after.1);
+                    let length = sum_x.hypot(sum_y);
+
+                    // A turn right back leaves no miter to speak of.
+                    if length < 1e-9 {
+                        after
+                    }
+                    else {
+                        let (x, y) = (sum_x / length, sum_y / length);
+                        let scale = (1.0 / (x * after.0 + y * after.1)).min(miter_limit);
+
+                        (x * scale, y * scale)
+                    }
+                }
+                (Some(normal), None) | (None, Some(normal)) => normal,
+                (None, None) => (0.0, 0.0),
+            };
+
+            (x + nx * distance, y + ny * distance)
+        })
+        .collect();
+
+    if closed {
+        offset.push(offset[0]);
+    }
+
+    offset
+}
+
+/// Twice the area `points` enclose, positive when they run with their
+/// inside on the left.
+pub fn signed_area(points: &[(f64, f64)]) -> f64 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(&(x, y), &(next_x, next_y))| x * next_y - next_x * y)
+        .sum()
+}
+
+/// Whether `point` is inside the polygon `points`, by the even-odd rule.
+pub fn contains(points: &[(f64, f64)], (x, y): (f64, f64)) -> bool {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .filter(|&(&(x1, y1), &(x2, y2))| {
+            (y1 > y) != (y2 > y) && x < x1 + (y - y1) / (y2 - y1) * (x2 - x1)
+        })
+        .count()
+        % 2
+        == 1
+}
@@ -0,0 +1,251 @@
+use crate::distance;
+use crate::frames::arc_length_table;
+use crate::frames::position_at_length;
+use crate::Camera;
+use crate::Curve;
+use crate::Point;
+
+/// Points of the old path each refitted curve is fitted to.
+const FIT_SAMPLES: usize = 24;
+/// Rounds of moving the fitted points' parameters to where the fitted curve
+/// passes closest, each followed by fitting again.
+const REPARAMETERIZE_ITERATIONS: usize = 4;
+/// Points per old curve that the new path is measured against.
+const DEVIATION_SAMPLES: usize = 32;
+
+/// The path of `path`'s curves, each starting where the previous one ends,
+/// with as many curves but their anchors evenly spaced by arc length. The
+/// ends stay put, and each curve is fitted to the part of the old path it
+/// replaces, so the shape only changes where that part has a corner a
+/// cubic can't follow.
+pub fn redistribute(path: &[Curve]) -> Vec<Curve> {
+    let table = arc_length_table(path, Camera::default());
+    let length = table.last().unwrap().0;
+    let count = path.len();
+
+    // The point and direction where a position along the path is; a
+    // position on an anchor has a direction on either side of it.
+    let point_at = |position: f64| {
+        let index = (position as usize).min(count - 1);
+        path[index].point_at(position - index as f64)
+    };
+    let tangent_at = |position: f64, leaving: bool| {
+        let index = if leaving {
+            position.floor() as usize
+        }
+        else {
+            (position.ceil() as usize).max(1) - 1
+        }
+        .min(count - 1);
+
+        path[index]
+            .tangent_at(position - index as f64)
+            .unwrap_or((0.0, 0.0))
+    };
+
+    let positions: Vec<f64> = (0..=count)
+        .map(|piece| match piece {
+            0 => 0.0,
+            _ if piece == count => count as f64,
+            _ => position_at_length(&table, length * piece as f64 / count as f64),
+        })
+        .collect();
+
+    positions
+        .windows(2)
+        .map(|pair| {
+            let (start, end) = (pair[0], pair[1]);
+            let samples: Vec<Point> = (0..FIT_SAMPLES)
+                .map(|sample| {
+                    let fraction = sample as f64 / (FIT_SAMPLES - 1) as f64;
+                    point_at(start + (end - start) * fraction)
+                })
+                .collect();
+
+            fit_cubic(&samples, tangent_at(start, true), tangent_at(end, false))
+        })
+        .collect()
+}
+
+/// The cubic from the first to the last of `points` that leaves along
+/// `start_tangent` and arrives along `end_tangent`, both unit vectors, with
+/// handle lengths that bring it closest to the points in the least-squares
+/// sense.
+fn fit_cubic(points: &[Point], start_tangent: (f64, f64), end_tangent: (f64, f64)) -> Curve {
+    let a = points[0];
+    let d = points[points.len() - 1];
+    let chord = distance(a, d);
+
+    // Parameters for the points, first by how far along the polyline they
+    // are, then refined.
+    let mut parameters: Vec<f64> = points
+        .iter()
+        .scan((0.0, a), |(length, previous), &point| {
+            *length += distance(*previous, point);
+            *previous = point;
+            Some(*length)
+        })
+        .collect();
+    let total = *parameters.last().unwrap();
+
+    if total == 0.0 {
+        return Curve::line(a, d);
+    }
+
+    for parameter in &mut parameters {
+        *parameter /= total;
+    }
+
+    let mut curve = fit_handles(points, &parameters, start_tangent, end_tangent, chord);
+
+    for _ in 0..REPARAMETERIZE_ITERATIONS {
+        for (parameter, &point) in parameters.iter_mut().zip(points) {
+            *parameter = newton_step(&curve, *parameter, point);
+        }
+
+        curve = fit_handles(points, &parameters, start_tangent, end_tangent, chord);
+    }
+
+    curve
+}
+
+/// Solves for the handle lengths of the cubic fitted by `fit_cubic`, given a
+/// parameter for each point. Falls back to a third of the chord when the
+/// solution is degenerate or would turn a handle around.
+fn fit_handles(
+    points: &[Point],
+    parameters: &[f64],
+    (tx1, ty1): (f64, f64),
+    (tx2, ty2): (f64, f64),
+    chord: f64,
+) -> Curve {
+    let a = points[0];
+    let d = points[points.len() - 1];
+    let (mut c11, mut c12, mut c22, mut x1, mut x2) = (0.0, 0.0, 0.0, 0.0, 0.0);
+
+    for (&point, &t) in points.iter().zip(parameters) {
+        let mt = 1.0 - t;
+        let (b0, b1, b2, b3) = (mt * mt * mt, 3.0 * mt * mt * t, 3.0 * mt * t * t, t * t * t);
+
+        // What each handle length contributes, per unit, at this parameter:
+        // the start handle pushes along the start tangent, the end handle
+        // back along the end tangent.
+        let (ax1, ay1) = (tx1 * b1, ty1 * b1);
+        let (ax2, ay2) = (-tx2 * b2, -ty2 * b2);
+        let rest_x = point.x - (a.x * (b0 + b1) + d.x * (b2 + b3));
+        let rest_y = point.y - (a.y * (b0 + b1) + d.y * (b2 + b3));
+
+        c11 += ax1 * ax1 + ay1 * ay1;
+        c12 += ax1 * ax2 + ay1 * ay2;
+        c22 += ax2 * ax2 + ay2 * ay2;
+        x1 += rest_x * ax1 + rest_y * ay1;
+        x2 += rest_x * ax2 + rest_y * ay2;
+    }
+
+    let determinant = c11 * c22 - c12 * c12;
+    let (start, end) = if determinant.abs() > 1e-12 {
+        (
+            (x1 * c22 - c12 * x2) / determinant,
+            (c11 * x2 - c12 * x1) / determinant,
+        )
+    }
+    else {
+        (0.0, 0.0)
+    };
+
+    let minimum = chord * 1e-6;
+    let (start, end) = if start > minimum && end > minimum {
+        (start, end)
+    }
+    else {
+        (chord / 3.0, chord / 3.0)
+    };
+
+    Curve::new(
+        a,
+        Point::new(a.x + tx1 * start, a.y + ty1 * start),
+        Point::new(d.x - tx2 * end, d.y - ty2 * end),
+        d,
+    )
+}
+
+/// The parameter `t` moved by one Newton step towards where `curve` passes
+/// closest to `point`.
+fn newton_step(curve: &Curve, t: f64, point: Point) -> f64 {
+    let p = curve.point_at(t);
+    let (dx, dy) = curve.derivative_at(t);
+    let (ddx, ddy) = curve.second_derivative_at(t);
+    let (ex, ey) = (p.x - point.x, p.y - point.y);
+
+    let numerator = ex * dx + ey * dy;
+    let denominator = dx * dx + dy * dy + ex * ddx + ey * ddy;
+
+    match denominator {
+        0.0 => t,
+        _ => (t - numerator / denominator).clamp(0.0, 1.0),
+    }
+}
+
+/// The farthest a point of `old` lies from `new`, for checking that a
+/// rebuilt path keeps its shape.
+pub fn deviation(old: &[Curve], new: &[Curve]) -> f64 {
+    old.iter()
+        .flat_map(|curve| curve.samples(DEVIATION_SAMPLES))
+        .map(|(x, y)| {
+            new.iter()
+                .map(|curve| curve.distance_to(Point::new(x, y)))
+                .fold(f64::INFINITY, f64::min)
+        })
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One smooth arch, cut into three pieces of very different lengths.
+    fn uneven_arch() -> Vec<Curve> {
+        let arch = Curve::new(
+            Point::new(0.0, 0.0),
+            Point::new(20.0, 150.0),
+            Point::new(180.0, 150.0),
+            Point::new(200.0, 0.0),
+        );
+
+        vec![
+            arch.clone().trim(0.0, 0.1),
+            arch.clone().trim(0.1, 0.3),
+            arch.trim(0.3, 1.0),
+        ]
+    }
+
+    #[test]
+    fn anchors_end_up_evenly_spaced() {
+        let path = uneven_arch();
+        let new = redistribute(&path);
+        let lengths: Vec<f64> = new.iter().map(Curve::arc_length).collect();
+        let total: f64 = path.iter().map(Curve::arc_length).sum();
+
+        assert_eq!(new.len(), path.len());
+
+        for length in lengths {
+            assert!((length - total / 3.0).abs() < total * 0.01);
+        }
+
+        assert!(new[0].a == path[0].a);
+        assert!(new[2].d == path[2].d);
+
+        for pair in new.windows(2) {
+            assert!(distance(pair[0].d, pair[1].a) < 1e-9);
+        }
+    }
+
+    #[test]
+    fn redistributing_keeps_the_shape() {
+        let path = uneven_arch();
+        let new = redistribute(&path);
+
+        assert!(deviation(&path, &new) < 0.5);
+        assert!(deviation(&new, &path) < 0.5);
+    }
+}
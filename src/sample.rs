@@ -0,0 +1,54 @@
+use crate::Curve;
+use crate::Point;
+use crate::Style;
+use crate::PALETTE;
+use crate::STROKE_WIDTHS;
+
+/// A small scene to try things out on: a closed heart above a dashed wave.
+pub fn sample_scene() -> Vec<Curve> {
+    let heart = Style {
+        color: PALETTE[1],
+        width: STROKE_WIDTHS[2],
+        ..Style::default()
+    };
+    let wave = Style {
+        color: PALETTE[2],
+        width: STROKE_WIDTHS[1],
+        dashed: true,
+        ..Style::default()
+    };
+
+    let mut curves = vec![
+        Curve::new(
+            Point::new(200.0, 300.0),
+            Point::new(60.0, 200.0),
+            Point::new(120.0, 80.0),
+            Point::new(200.0, 160.0),
+        ),
+        Curve::new(
+            Point::new(200.0, 160.0),
+            Point::new(280.0, 80.0),
+            Point::new(340.0, 200.0),
+            Point::new(200.0, 300.0),
+        ),
+    ];
+
+    for curve in &mut curves {
+        curve.style = heart;
+    }
+
+    for i in 0..4 {
+        let x = 60.0 + 70.0 * i as f64;
+        let crest = if i % 2 == 0 { 320.0 } else { 400.0 };
+
+        let mut curve = Curve::quadratic(
+            Point::new(x, 360.0),
+            Point::new(x + 35.0, crest),
+            Point::new(x + 70.0, 360.0),
+        );
+        curve.style = wave;
+        curves.push(curve);
+    }
+
+    curves
+}
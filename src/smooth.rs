@@ -0,0 +1,129 @@
+use crate::distance_to_segment;
+use crate::Curve;
+use crate::Point;
+use crate::DEGENERATE_EXTENT;
+
+/// Bends the straight curves among `indices` into a smooth path through the
+/// same anchors. Where a straight curve meets a neighbour, the handles there
+/// follow the Catmull-Rom tangent through the neighbouring anchors, unless
+/// the path turns by more than `max_angle` radians: such corners, and path
+/// ends, stay sharp. Neighbours are curves that end where the curve starts or
+/// start where it ends. Curves that are already curved, or pinned, are left
+/// alone.
+pub fn smooth_corners(curves: &mut [Curve], indices: &[usize], max_angle: f64) {
+    let original = curves.to_vec();
+
+    for &index in indices {
+        let curve = &original[index];
+
+        if !is_straight(curve) || curve.pin.is_some() {
+            continue;
+        }
+
+        let previous = original
+            .iter()
+            .enumerate()
+            .find(|&(other, candidate)| other != index && candidate.d == curve.a)
+            .map(|(_, candidate)| candidate.a);
+
+        let next = original
+            .iter()
+            .enumerate()
+            .find(|&(other, candidate)| other != index && candidate.a == curve.d)
+            .map(|(_, candidate)| candidate.d);
+
+        let smooth = |before: Option<Point>, at: Point, after: Point| {
+            before.filter(|&before| turn_angle(before, at, after) <= max_angle)
+        };
+
+        // Catmull-Rom with uniform parameters puts the handle a sixth of the
+        // way along the chord between the neighbouring anchors.
+        let b = match smooth(previous, curve.a, curve.d) {
+            Some(before) => Point::new(
+                curve.a.x + (curve.d.x - before.x) / 6.0,
+                curve.a.y + (curve.d.y - before.y) / 6.0,
+            ),
+            None => curve.a.lerp(curve.d, 1.0 / 3.0),
+        };
+
+        let c = match smooth(next, curve.d, curve.a) {
+            Some(after) => Point::new(
+                curve.d.x - (after.x - curve.a.x) / 6.0,
+                curve.d.y - (after.y - curve.a.y) / 6.0,
+            ),
+            None => curve.d.lerp(curve.a, 1.0 / 3.0),
+        };
+
+        curves[index] = Curve {
+            b,
+            c,
+            ..curve.to_cubic()
+        };
+    }
+}
+
+/// Whether the control points lie on the chord, so the curve draws as a
+/// straight segment.
+fn is_straight(curve: &Curve) -> bool {
+    let [a, b, c, d] = curve.cubic_points().map(|p| (p.x, p.y));
+
+    distance_to_segment(b, a, d) < DEGENERATE_EXTENT
+        && distance_to_segment(c, a, d) < DEGENERATE_EXTENT
+}
+
+/// How far the direction changes, in radians from 0 to π, when going from
+/// `a` through `b` to `c`. Either order of `a` and `c` gives the same angle.
+fn turn_angle(a: Point, b: Point, c: Point) -> f64 {
+    let (x1, y1) = (b.x - a.x, b.y - a.y);
+    let (x2, y2) = (c.x - b.x, c.y - b.y);
+
+    (x1 * y2 - y1 * x2).atan2(x1 * x2 + y1 * y2).abs()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    fn polyline(points: &[(f64, f64)]) -> Vec<Curve> {
+        points
+            .windows(2)
+            .map(|pair| {
+                Curve::line(
+                    Point::new(pair[0].0, pair[0].1),
+                    Point::new(pair[1].0, pair[1].1),
+                )
+            })
+            .collect()
+    }
+
+    /// Whether the handles on either side of the junction where `before`
+    /// ends and `after` starts point in opposite directions.
+    fn is_smooth(before: &Curve, after: &Curve) -> bool {
+        let (x1, y1) = (before.d.x - before.c.x, before.d.y - before.c.y);
+        let (x2, y2) = (after.b.x - after.a.x, after.b.y - after.a.y);
+
+        (x1 * y2 - y1 * x2).abs() < 1e-9 && x1 * x2 + y1 * y2 > 0.0
+    }
+
+    #[test]
+    fn gentle_zigzag_smooths() {
+        let mut curves = polyline(&[(0.0, 0.0), (10.0, 2.0), (20.0, 0.0), (30.0, 2.0)]);
+
+        smooth_corners(&mut curves, &[0, 1, 2], PI / 4.0);
+
+        assert!(is_smooth(&curves[0], &curves[1]));
+        assert!(is_smooth(&curves[1], &curves[2]));
+        assert!(!is_straight(&curves[1]));
+    }
+
+    #[test]
+    fn right_angle_stays_sharp() {
+        let mut curves = polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+
+        smooth_corners(&mut curves, &[0, 1], PI / 4.0);
+
+        assert!(is_straight(&curves[0]) && is_straight(&curves[1]));
+        assert!(!is_smooth(&curves[0], &curves[1]));
+    }
+}
@@ -0,0 +1,251 @@
+use crate::distance;
+use crate::Point;
+use crate::PointHandle;
+use crate::State;
+use crate::SNAP_RADIUS;
+
+#[derive(Clone, Copy)]
+pub struct SnapSettings {
+    /// Master switch for all snapping.
+    pub enabled: bool,
+    pub grid: GridKind,
+    /// Distance between neighbouring grid points, in world units.
+    pub grid_size: f64,
+    /// Rounding of new curves' lengths.
+    pub length: LengthSnap,
+    /// Whether a curve that a dragged end snaps onto the body of is split
+    /// there on release, making a junction.
+    pub split_curves: bool,
+}
+
+impl Default for SnapSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            grid: GridKind::Off,
+            grid_size: 20.0,
+            length: LengthSnap::Off,
+            split_curves: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum GridKind {
+    Off,
+    Square,
+    /// A triangular lattice whose lines run vertically and at 30° and 150°,
+    /// as used for isometric drawings.
+    Isometric,
+}
+
+impl GridKind {
+    pub fn next(self) -> Self {
+        match self {
+            GridKind::Off => GridKind::Square,
+            GridKind::Square => GridKind::Isometric,
+            GridKind::Isometric => GridKind::Off,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum LengthSnap {
+    Off,
+    /// To the nearest multiple of ten world units.
+    Tens,
+    /// To the nearest multiple of the grid size.
+    GridSize,
+}
+
+impl LengthSnap {
+    pub fn next(self) -> Self {
+        match self {
+            LengthSnap::Off => LengthSnap::Tens,
+            LengthSnap::Tens => LengthSnap::GridSize,
+            LengthSnap::GridSize => LengthSnap::Off,
+        }
+    }
+}
+
+impl SnapSettings {
+    /// `end` moved along the line from `start` so their distance is rounded
+    /// as `length` asks, or `None` if length snapping is off. The distance
+    /// never rounds down to zero.
+    pub fn snap_length(&self, start: Point, end: Point) -> Option<Point> {
+        let step = match self.length {
+            _ if !self.enabled => return None,
+            LengthSnap::Off => return None,
+            LengthSnap::Tens => 10.0,
+            LengthSnap::GridSize => self.grid_size,
+        };
+
+        let length = distance(start, end);
+
+        if length == 0.0 {
+            return None;
+        }
+
+        let rounded = ((length / step).round() * step).max(step);
+
+        Some(start.lerp(end, rounded / length))
+    }
+
+    /// Distances along either axis between neighbouring grid points that
+    /// line up with the axes. Neighbouring points of an isometric lattice are
+    /// two steps apart along either axis.
+    pub fn node_steps(&self) -> (f64, f64) {
+        match self.grid {
+            GridKind::Isometric => {
+                let (w, h) = isometric_steps(self.grid_size);
+                (2.0 * w, 2.0 * h)
+            }
+            _ => (self.grid_size, self.grid_size),
+        }
+    }
+
+    /// The grid point nearest to `point`, or `point` itself when grid
+    /// snapping is off.
+    pub fn snap_to_grid(&self, point: Point) -> Point {
+        if !self.enabled {
+            return point;
+        }
+
+        let size = self.grid_size;
+
+        match self.grid {
+            GridKind::Off => point,
+            GridKind::Square => Point::new(
+                (point.x / size).round() * size,
+                (point.y / size).round() * size,
+            ),
+            GridKind::Isometric => {
+                let (w, h) = isometric_steps(size);
+
+                // Lattice points are `i * (w, h) + j * (-w, h)`. Rounding `i`
+                // and `j` separately can miss the nearest point of a skewed
+                // lattice, so all four surrounding points are compared.
+                let i = (point.x / w + point.y / h) / 2.0;
+                let j = (point.y / h - point.x / w) / 2.0;
+
+                [i.floor(), i.ceil()]
+                    .into_iter()
+                    .flat_map(|i| [(i, j.floor()), (i, j.ceil())])
+                    .map(|(i, j)| Point::new((i - j) * w, (i + j) * h))
+                    .min_by(|p, q| distance(*p, point).total_cmp(&distance(*q, point)))
+                    .unwrap()
+            }
+        }
+    }
+}
+
+/// Horizontal and vertical offsets between neighbouring isometric lattice
+/// points, `size` apart along the 30° axis.
+pub fn isometric_steps(size: f64) -> (f64, f64) {
+    (
+        size * 30f64.to_radians().cos(),
+        size * 30f64.to_radians().sin(),
+    )
+}
+
+/// What a point was snapped to.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SnapKind {
+    None,
+    /// An endpoint of the curve at `curve_index`.
+    Anchor {
+        curve_index: usize,
+        anchor: PointHandle,
+    },
+    /// The point at parameter `t` along the curve at `curve_index`.
+    Curve {
+        curve_index: usize,
+        t: f64,
+    },
+    Grid,
+}
+
+/// Where `tentative` should land given every enabled snap source, and what
+/// it landed on. Sources are tried in order of priority, so they never
+/// compete:
+///
+/// 1. The nearest curve endpoint within `SNAP_RADIUS` screen pixels, other
+///    than the point being dragged.
+/// 2. The nearest point on another curve's body within `SNAP_RADIUS` screen
+///    pixels, while dragging a curve's end.
+/// 3. The nearest grid point, when a grid is shown.
+pub fn resolve_snap(state: &State, tentative: Point) -> (Point, SnapKind) {
+    let settings = state.snap_settings();
+
+    if !settings.enabled {
+        return (tentative, SnapKind::None);
+    }
+
+    let camera = state.camera.get();
+    let screen = camera.to_screen(tentative);
+    let drag_state = state.drag_state.get();
+    let dragged = drag_state.map(|drag_state| (drag_state.curve_index, drag_state.point));
+
+    let anchor = state
+        .curves
+        .borrow()
+        .iter()
+        .enumerate()
+        .flat_map(|(index, curve)| {
+            [
+                (index, PointHandle::A, curve.a),
+                (index, PointHandle::D, curve.d),
+            ]
+        })
+        .filter(|&(index, handle, _)| dragged != Some((index, handle)))
+        .map(|(index, handle, point)| {
+            (
+                index,
+                handle,
+                point,
+                distance(camera.to_screen(point), screen),
+            )
+        })
+        .filter(|&(.., distance)| distance <= SNAP_RADIUS)
+        .min_by(|(.., a), (.., b)| a.total_cmp(b));
+
+    if let Some((curve_index, anchor, point, _)) = anchor {
+        return (
+            point,
+            SnapKind::Anchor {
+                curve_index,
+                anchor,
+            },
+        );
+    }
+
+    let dragged_end =
+        drag_state.filter(|drag_state| matches!(drag_state.point, PointHandle::A | PointHandle::D));
+
+    if let Some(drag_state) = dragged_end {
+        let body = state
+            .curves
+            .borrow()
+            .iter()
+            .enumerate()
+            .filter(|&(index, _)| index != drag_state.curve_index)
+            .map(|(index, curve)| {
+                let t = curve.nearest_t(tentative);
+                let point = curve.point_at(t);
+
+                (index, t, point, distance(camera.to_screen(point), screen))
+            })
+            .filter(|&(.., distance)| distance <= SNAP_RADIUS)
+            .min_by(|(.., a), (.., b)| a.total_cmp(b));
+
+        if let Some((curve_index, t, point, _)) = body {
+            return (point, SnapKind::Curve { curve_index, t });
+        }
+    }
+
+    if settings.grid != GridKind::Off {
+        return (settings.snap_to_grid(tentative), SnapKind::Grid);
+    }
+
+    (tentative, SnapKind::None)
+}
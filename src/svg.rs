@@ -0,0 +1,493 @@
+use crate::aligned_outline;
+use crate::cached_subpaths;
+use crate::dimension::svg_dimensions;
+use crate::format_coordinate;
+use crate::left_inside;
+use crate::topology;
+use crate::CapStyle;
+use crate::Curve;
+use crate::CurveKind;
+use crate::LineJoin;
+use crate::Point;
+use crate::PointHandle;
+use crate::State;
+use crate::CAP_SCALE;
+use crate::DEFAULT_MITER_LIMIT;
+use crate::FLATTEN_TOLERANCE;
+use std::slice;
+use wasm_bindgen::JsValue;
+use web_sys::console;
+use web_sys::DomParser;
+use web_sys::Element;
+use web_sys::SupportedType;
+
+/// Serializes the scene as an SVG document of the given size, one `<path>`
+/// per curve followed by the dimensions. Cubics are written with `C` and quadratics with `Q`, so both
+/// come back unchanged through `import_svg`. End caps become markers, one
+/// per cap, color and opacity in use. Strokes aligned to one side become
+/// polygons filling their outline, each curve's on its own, over the curve
+/// itself left unstroked.
+///
+/// `view_box` is the world-space `(min, max)` rectangle shown, which should
+/// have the same aspect ratio as the document to avoid letterboxing.
+pub fn export_svg(state: &State, width: f64, height: f64, view_box: (Point, Point)) -> String {
+    let precision = state.export_precision.get();
+    let format = |point: Point| {
+        format!(
+            "{} {}",
+            format_coordinate(point.x, precision),
+            format_coordinate(point.y, precision)
+        )
+    };
+
+    let (min, max) = view_box;
+    let mut svg = format!(
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        format_coordinate(width, precision),
+        format_coordinate(height, precision),
+        format_coordinate(min.x, precision),
+        format_coordinate(min.y, precision),
+        format_coordinate(max.x - min.x, precision),
+        format_coordinate(max.y - min.y, precision)
+    );
+
+    let curves = state.curves.borrow();
+    let loose_ends: Vec<(usize, PointHandle)> = cached_subpaths(state)
+        .into_iter()
+        .filter(|subpath| {
+            subpath
+                .curves
+                .iter()
+                .any(|&index| curves[index].style.has_caps())
+        })
+        .flat_map(|subpath| topology::loose_ends(&curves, &subpath.curves))
+        .collect();
+    let left_inside = left_inside(&curves, &cached_subpaths(state));
+
+    // Markers, as the cap, color and opacity they draw.
+    let mut markers: Vec<(CapStyle, String, f64)> = Vec::new();
+    let mut marker = |cap: CapStyle, curve: &Curve| {
+        let key = (cap, curve.style.color.css(), curve.style.opacity);
+        let position = match markers.iter().position(|marker| *marker == key) {
+            Some(position) => position,
+            None => {
+                markers.push(key);
+                markers.len() - 1
+            }
+        };
+
+        format!("url(#cap-{position})")
+    };
+
+    let mut paths = String::new();
+
+    for (index, curve) in curves.iter().enumerate() {
+        let data = match curve.kind {
+            // SVG has no rational curves, so those are written as polylines.
+            _ if curve.weights.is_some() => {
+                let points: Vec<String> = curve
+                    .flatten(FLATTEN_TOLERANCE)
+                    .into_iter()
+                    .map(|(x, y)| format(Point::new(x, y)))
+                    .collect();
+
+                format!("M {}", points.join(" L "))
+            }
+            CurveKind::Cubic => format!(
+                "M {} C {} {} {}",
+                format(curve.a),
+                format(curve.b),
+                format(curve.c),
+                format(curve.d)
+            ),
+            CurveKind::Quadratic => format!(
+                "M {} Q {} {}",
+                format(curve.a),
+                format(curve.b),
+                format(curve.d)
+            ),
+        };
+
+        let style = curve.style;
+        let aligned = style
+            .align
+            .offset(style.width, left_inside[index])
+            .filter(|_| !style.dashed && style.color_scale.is_none());
+
+        if let Some(offset) = aligned {
+            let points: Vec<String> =
+                aligned_outline(slice::from_ref(curve), false, offset, style.miter_limit)
+                    .concat()
+                    .into_iter()
+                    .map(|(x, y)| format(Point::new(x, y)).replace(' ', ","))
+                    .collect();
+
+            paths.push_str(&format!(
+                r#"<path d="{data}" fill="none" stroke="none"/><polygon points="{}" fill="{}""#,
+                points.join(" "),
+                style.color.css()
+            ));
+
+            if style.opacity != 1.0 {
+                paths.push_str(&format!(r#" fill-opacity="{}""#, style.opacity));
+            }
+
+            paths.push_str("/>");
+            continue;
+        }
+
+        paths.push_str(&format!(
+            r#"<path d="{data}" fill="none" stroke="{}" stroke-width="{}""#,
+            style.color.css(),
+            style.width
+        ));
+
+        if style.opacity != 1.0 {
+            paths.push_str(&format!(r#" stroke-opacity="{}""#, style.opacity));
+        }
+
+        if style.dashed {
+            paths.push_str(&format!(
+                r#" stroke-dasharray="{} {}""#,
+                style.width * 4.0,
+                style.width * 3.0
+            ));
+        }
+
+        if style.join != LineJoin::Miter {
+            paths.push_str(&format!(r#" stroke-linejoin="{}""#, style.join.css()));
+        }
+
+        if style.miter_limit != DEFAULT_MITER_LIMIT {
+            paths.push_str(&format!(r#" stroke-miterlimit="{}""#, style.miter_limit));
+        }
+
+        for (attribute, cap, end) in [
+            ("marker-start", style.start_cap, PointHandle::A),
+            ("marker-end", style.end_cap, PointHandle::D),
+        ] {
+            if cap != CapStyle::None && loose_ends.contains(&(index, end)) {
+                paths.push_str(&format!(r#" {attribute}="{}""#, marker(cap, curve)));
+            }
+        }
+
+        paths.push_str("/>");
+    }
+
+    if !markers.is_empty() {
+        svg.push_str("<defs>");
+
+        for (position, (cap, color, opacity)) in markers.iter().enumerate() {
+            svg.push_str(&format!(
+                r#"<marker id="cap-{position}" markerUnits="strokeWidth" orient="auto-start-reverse" overflow="visible" fill="{color}""#
+            ));
+
+            if *opacity != 1.0 {
+                svg.push_str(&format!(r#" fill-opacity="{opacity}""#));
+            }
+
+            svg.push('>');
+            svg.push_str(&svg_cap(*cap));
+            svg.push_str("</marker>");
+        }
+
+        svg.push_str("</defs>");
+    }
+
+    svg.push_str(&paths);
+    svg.push_str(&svg_dimensions(state));
+    svg.push_str("</svg>");
+    svg
+}
+
+/// The shape of a cap marker pointing along x, in stroke widths. It is drawn
+/// without `<path>` so importing the document again doesn't turn it into
+/// curves.
+fn svg_cap(cap: CapStyle) -> String {
+    let half = CAP_SCALE / 2.0;
+
+    match cap {
+        CapStyle::None => String::new(),
+        CapStyle::Dot => format!(r#"<circle r="{half}"/>"#),
+        CapStyle::Arrow => format!(
+            r#"<polygon points="0,0 {},{} {},{half}"/>"#,
+            -CAP_SCALE, -half, -CAP_SCALE
+        ),
+        CapStyle::Bar => format!(
+            r#"<rect x="-0.5" y="{}" width="1" height="{CAP_SCALE}"/>"#,
+            -half
+        ),
+    }
+}
+
+/// Appends the curves of every `<path>` in an SVG document to the scene, each
+/// path as its own group. Transforms and styles are not applied.
+///
+/// Returns the number of paths imported. Nothing is added if any path data is
+/// malformed.
+pub fn import_svg(state: &State, text: &str) -> Result<usize, String> {
+    let document = DomParser::new()
+        .unwrap()
+        .parse_from_string(text, SupportedType::ImageSvgXml)
+        .map_err(|_| "could not parse the document")?;
+
+    if document.get_elements_by_tag_name("parsererror").length() > 0 {
+        return Err("the document is not well-formed XML".to_string());
+    }
+
+    let elements = document.get_elements_by_tag_name("path");
+    let mut paths = Vec::new();
+
+    for index in 0..elements.length() {
+        let element: Element = elements.item(index).unwrap();
+
+        if let Some(data) = element.get_attribute("d") {
+            paths.push(parse_path(&data)?);
+        }
+    }
+
+    let imported = paths.len();
+    let mut curves = state.curves.borrow_mut();
+
+    for path in paths {
+        let group = state.next_group.get();
+        state.next_group.set(group + 1);
+
+        curves.extend(path.into_iter().map(|curve| Curve {
+            group: Some(group),
+            ..curve
+        }));
+    }
+
+    Ok(imported)
+}
+
+/// Parses SVG path data into curves. Lines become straight cubics and
+/// elliptical arcs, which have no exact bezier form, are replaced by straight
+/// lines to their endpoints with a console warning.
+pub fn parse_path(data: &str) -> Result<Vec<Curve>, String> {
+    let mut tokens = Tokens::new(data);
+    let mut curves = Vec::new();
+
+    let mut current = Point::new(0.0, 0.0);
+    let mut start = current;
+    // The control point reflected by a following `S` or `T`, kept only while
+    // the previous command was of the matching kind.
+    let mut last_cubic_control = None;
+    let mut last_quadratic_control = None;
+    let mut command = None;
+
+    while let Some(next) = tokens.command_or_number()? {
+        let letter = match next {
+            Token::Command(letter) => letter,
+            // Numbers without a command letter repeat the previous command,
+            // except that `M` continues as `L`.
+            Token::Number => match command {
+                Some('M') => 'L',
+                Some('m') => 'l',
+                // Closing takes no numbers, so there is nothing to repeat.
+                Some('Z' | 'z') => {
+                    return Err("numbers can't follow a closepath command".to_string())
+                }
+                Some(letter) => letter,
+                None => return Err("path data must start with a command".to_string()),
+            },
+        };
+
+        command = Some(letter);
+        let relative = letter.is_ascii_lowercase();
+        let origin = if relative {
+            current
+        }
+        else {
+            Point::new(0.0, 0.0)
+        };
+
+        let point = |tokens: &mut Tokens| -> Result<Point, String> {
+            let x = tokens.number()?;
+            let y = tokens.number()?;
+            Ok(Point::new(origin.x + x, origin.y + y))
+        };
+
+        let mut cubic_control = None;
+        let mut quadratic_control = None;
+
+        match letter.to_ascii_uppercase() {
+            'M' => {
+                current = point(&mut tokens)?;
+                start = current;
+            }
+            'L' => {
+                let end = point(&mut tokens)?;
+                curves.push(Curve::line(current, end));
+                current = end;
+            }
+            'H' => {
+                let x = tokens.number()? + if relative { current.x } else { 0.0 };
+                let end = Point::new(x, current.y);
+                curves.push(Curve::line(current, end));
+                current = end;
+            }
+            'V' => {
+                let y = tokens.number()? + if relative { current.y } else { 0.0 };
+                let end = Point::new(current.x, y);
+                curves.push(Curve::line(current, end));
+                current = end;
+            }
+            'C' | 'S' => {
+                let b = if letter.eq_ignore_ascii_case(&'C') {
+                    point(&mut tokens)?
+                }
+                else {
+                    reflect(last_cubic_control, current)
+                };
+
+                let c = point(&mut tokens)?;
+                let d = point(&mut tokens)?;
+
+                curves.push(Curve::new(current, b, c, d));
+                cubic_control = Some(c);
+                current = d;
+            }
+            'Q' | 'T' => {
+                let control = if letter.eq_ignore_ascii_case(&'Q') {
+                    point(&mut tokens)?
+                }
+                else {
+                    reflect(last_quadratic_control, current)
+                };
+
+                let d = point(&mut tokens)?;
+
+                curves.push(Curve::quadratic(current, control, d));
+                quadratic_control = Some(control);
+                current = d;
+            }
+            'A' => {
+                for _ in 0..5 {
+                    tokens.number()?;
+                }
+
+                let end = point(&mut tokens)?;
+
+                console::warn_1(&JsValue::from(
+                    "SVG arcs are not supported and were imported as straight lines",
+                ));
+
+                curves.push(Curve::line(current, end));
+                current = end;
+            }
+            'Z' => {
+                if current != start {
+                    curves.push(Curve::line(current, start));
+                }
+
+                current = start;
+            }
+            _ => return Err(format!("unsupported path command '{letter}'")),
+        }
+
+        last_cubic_control = cubic_control;
+        last_quadratic_control = quadratic_control;
+    }
+
+    Ok(curves)
+}
+
+/// Mirrors the previous control point through `current`, or just `current`
+/// if there is none.
+fn reflect(control: Option<Point>, current: Point) -> Point {
+    match control {
+        Some(control) => Point::new(2.0 * current.x - control.x, 2.0 * current.y - control.y),
+        None => current,
+    }
+}
+
+enum Token {
+    Command(char),
+    /// A number is next; it is left in place for `Tokens::number`.
+    Number,
+}
+
+struct Tokens<'a> {
+    data: &'a str,
+    position: usize,
+}
+
+impl<'a> Tokens<'a> {
+    fn new(data: &'a str) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn command_or_number(&mut self) -> Result<Option<Token>, String> {
+        self.skip_separators();
+
+        match self.rest().chars().next() {
+            None => Ok(None),
+            Some(letter) if letter.is_ascii_alphabetic() && !matches!(letter, 'e' | 'E') => {
+                self.position += 1;
+                Ok(Some(Token::Command(letter)))
+            }
+            Some(_) => Ok(Some(Token::Number)),
+        }
+    }
+
+    fn number(&mut self) -> Result<f64, String> {
+        self.skip_separators();
+
+        let rest = self.rest().as_bytes();
+        let mut end = 0;
+        let mut seen_dot = false;
+        let mut seen_exponent = false;
+
+        while end < rest.len() {
+            match rest[end] {
+                b'+' | b'-' if end == 0 || matches!(rest[end - 1], b'e' | b'E') => {}
+                b'0'..=b'9' => {}
+                // A second dot starts the next number, as in `0.5.5`.
+                b'.' if !seen_dot && !seen_exponent => seen_dot = true,
+                b'e' | b'E' if !seen_exponent && end > 0 => seen_exponent = true,
+                _ => break,
+            }
+
+            end += 1;
+        }
+
+        // Overflowing to infinity would put points nowhere.
+        let number = self.rest()[..end]
+            .parse()
+            .ok()
+            .filter(|number: &f64| number.is_finite())
+            .ok_or_else(|| format!("expected a number at {}", self.position))?;
+
+        self.position += end;
+
+        Ok(number)
+    }
+
+    fn skip_separators(&mut self) {
+        let skipped = self.rest().len()
+            - self
+                .rest()
+                .trim_start_matches(|c: char| c.is_whitespace() || c == ',')
+                .len();
+
+        self.position += skipped;
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.data[self.position..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_after_closepath_are_an_error() {
+        assert!(parse_path("M0 0 L10 10 Z 5 5").is_err());
+        assert!(parse_path("m0 0 l10 10 z 5").is_err());
+        assert_eq!(parse_path("M0 0 L10 10 Z M5 5 L6 6").unwrap().len(), 3);
+    }
+}
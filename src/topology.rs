@@ -0,0 +1,458 @@
+use crate::distance;
+use crate::flatten_path;
+use crate::outline;
+use crate::Curve;
+use crate::Point;
+use crate::PointHandle;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+/// Curves joined end to end, where ends meet at exactly the same point.
+#[derive(Clone)]
+pub struct Subpath {
+    /// Indices of the curves, in increasing order.
+    pub curves: Vec<usize>,
+    /// Whether every end meets another curve's end, so the subpath encloses
+    /// an area and can be filled.
+    pub closed: bool,
+}
+
+/// Splits the curves into subpaths. Each curve belongs to exactly one, and
+/// subpaths are ordered by their lowest curve index.
+pub fn subpaths(curves: &[Curve]) -> Vec<Subpath> {
+    // Curve ends by position, for finding which ends meet.
+    let mut ends: HashMap<(u64, u64), Vec<usize>> = HashMap::new();
+
+    for (index, curve) in curves.iter().enumerate() {
+        ends.entry(key(curve.a)).or_default().push(index);
+        ends.entry(key(curve.d)).or_default().push(index);
+    }
+
+    let mut subpath_of = vec![None; curves.len()];
+    let mut subpaths = Vec::new();
+
+    for start in 0..curves.len() {
+        if subpath_of[start].is_some() {
+            continue;
+        }
+
+        let mut subpath = Subpath {
+            curves: vec![start],
+            closed: true,
+        };
+        let mut pending = vec![start];
+        subpath_of[start] = Some(subpaths.len());
+
+        while let Some(current) = pending.pop() {
+            for point in [curves[current].a, curves[current].d] {
+                let meeting = &ends[&key(point)];
+
+                // A curve's only end at a point is dangling; a curve whose
+                // ends coincide meets itself.
+                if meeting.len() < 2 {
+                    subpath.closed = false;
+                }
+
+                for &other in meeting {
+                    if subpath_of[other].is_none() {
+                        subpath_of[other] = Some(subpaths.len());
+                        subpath.curves.push(other);
+                        pending.push(other);
+                    }
+                }
+            }
+        }
+
+        subpath.curves.sort_unstable();
+        subpaths.push(subpath);
+    }
+
+    subpaths
+}
+
+/// Curves of a subpath that follow each other end to start, so they can be
+/// stroked as one line with joins at the corners.
+pub struct Chain {
+    /// Indices of the curves in stroking order, each with whether it runs
+    /// backwards, from `d` to `a`.
+    pub curves: Vec<(usize, bool)>,
+    /// Whether the last curve leads back into the first.
+    pub closed: bool,
+}
+
+/// Splits `subpath`'s curves into chains. Chains run through points where
+/// exactly two ends meet, and break where three or more do.
+pub fn chains(curves: &[Curve], subpath: &[usize]) -> Vec<Chain> {
+    let mut ends: HashMap<(u64, u64), Vec<(usize, PointHandle)>> = HashMap::new();
+
+    for &index in subpath {
+        for handle in [PointHandle::A, PointHandle::D] {
+            ends.entry(key(curves[index].point(handle)))
+                .or_default()
+                .push((index, handle));
+        }
+    }
+
+    // The one other end meeting this one, if the two make a plain junction.
+    let partner = |end: (usize, PointHandle)| match ends[&key(curves[end.0].point(end.1))][..] {
+        [first, second] if first == end => Some(second),
+        [first, second] if second == end => Some(first),
+        _ => None,
+    };
+
+    let mut chained = HashSet::new();
+    let mut chains = Vec::new();
+
+    let mut walk = |start: usize, entry: PointHandle, chained: &mut HashSet<usize>| {
+        let mut chain = Chain {
+            curves: Vec::new(),
+            closed: false,
+        };
+        let first = (start, entry);
+        let (mut index, mut entry) = first;
+
+        loop {
+            chained.insert(index);
+            chain.curves.push((index, entry == PointHandle::D));
+
+            let exit = match entry {
+                PointHandle::A => PointHandle::D,
+                _ => PointHandle::A,
+            };
+
+            match partner((index, exit)) {
+                Some(next) if next == first => {
+                    chain.closed = true;
+                    break;
+                }
+                Some((next, next_entry)) if !chained.contains(&next) => {
+                    (index, entry) = (next, next_entry);
+                }
+                _ => break,
+            }
+        }
+
+        chains.push(chain);
+    };
+
+    // Chains starting at a loose end or a branch first, in either direction,
+    // then whatever is left, which are loops.
+    for entry in [PointHandle::A, PointHandle::D] {
+        for &index in subpath {
+            if !chained.contains(&index) && partner((index, entry)).is_none() {
+                walk(index, entry, &mut chained);
+            }
+        }
+    }
+
+    for &index in subpath {
+        if !chained.contains(&index) {
+            walk(index, PointHandle::A, &mut chained);
+        }
+    }
+
+    chains
+}
+
+/// Every curve once, in the order following the paths visits them: chains
+/// of each subpath in turn, subpaths by their lowest curve index, as
+/// `chains` splits them. Within a subpath, chains start from loose ends and
+/// branch points, those at curves' starts first, each in curve order, and a
+/// chain ending at a branch point stops there, so branching paths are
+/// traversed the same way every time.
+pub fn traversal(curves: &[Curve]) -> Vec<Chain> {
+    subpaths(curves)
+        .into_iter()
+        .flat_map(|subpath| chains(curves, &subpath.curves))
+        .collect()
+}
+
+/// The ends among `subpath`'s curves that meet no other curve end, in curve
+/// order.
+pub fn loose_ends(curves: &[Curve], subpath: &[usize]) -> Vec<(usize, PointHandle)> {
+    subpath
+        .iter()
+        .flat_map(|&index| [(index, PointHandle::A), (index, PointHandle::D)])
+        .filter(|&(index, handle)| {
+            let point = curves[index].point(handle);
+
+            subpath
+                .iter()
+                .flat_map(|&other| [(other, PointHandle::A), (other, PointHandle::D)])
+                .filter(|&end| end != (index, handle))
+                .all(|(other, other_handle)| curves[other].point(other_handle) != point)
+        })
+        .collect()
+}
+
+/// Pairs of curve ends that lie within `tolerance` of each other without
+/// meeting exactly, so they look joined but aren't. Each end is in at most
+/// one pair, with the closest pairs taken first.
+pub fn weld_candidates(curves: &[Curve], tolerance: f64) -> Vec<[(usize, PointHandle); 2]> {
+    let ends: Vec<(usize, PointHandle)> = (0..curves.len())
+        .flat_map(|index| [(index, PointHandle::A), (index, PointHandle::D)])
+        .collect();
+    let point = |(index, handle): (usize, PointHandle)| curves[index].point(handle);
+
+    // Ends bucketed into cells as large as the tolerance, so only ends in
+    // neighbouring cells need comparing.
+    let cell = |point: Point| {
+        (
+            (point.x / tolerance).floor() as i64,
+            (point.y / tolerance).floor() as i64,
+        )
+    };
+    let mut cells: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+
+    for (position, &end) in ends.iter().enumerate() {
+        cells.entry(cell(point(end))).or_default().push(position);
+    }
+
+    let mut pairs = Vec::new();
+
+    for (position, &end) in ends.iter().enumerate() {
+        let (x, y) = cell(point(end));
+
+        for neighbour in (x - 1..=x + 1).flat_map(|x| (y - 1..=y + 1).map(move |y| (x, y))) {
+            for &other_position in cells.get(&neighbour).into_iter().flatten() {
+                let other = ends[other_position];
+                let gap = distance(point(end), point(other));
+
+                if other_position > position && other.0 != end.0 && gap > 0.0 && gap <= tolerance {
+                    pairs.push((gap, [end, other]));
+                }
+            }
+        }
+    }
+
+    pairs.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut ends_at: HashMap<(u64, u64), usize> = HashMap::new();
+
+    for &end in &ends {
+        *ends_at.entry(key(point(end))).or_default() += 1;
+    }
+
+    // Positions where an end is already joined to another, or already paired.
+    let mut taken: HashSet<(u64, u64)> = ends_at
+        .into_iter()
+        .filter(|&(_, count)| count > 1)
+        .map(|(position, _)| position)
+        .collect();
+
+    pairs
+        .into_iter()
+        .filter_map(|(_, pair)| {
+            let keys = pair.map(|end| key(point(end)));
+
+            if keys.iter().any(|position| taken.contains(position)) {
+                return None;
+            }
+
+            taken.extend(keys);
+            Some(pair)
+        })
+        .collect()
+}
+
+/// Reverses the closed subpaths made only of the curves at `indices` as
+/// needed for outer contours to run counterclockwise on screen and holes,
+/// those inside an odd number of other contours, clockwise. Then filling
+/// with either rule gives the same shapes. Returns how many subpaths were
+/// reversed.
+pub fn normalize_winding(curves: &mut [Curve], indices: &[usize]) -> usize {
+    // Each contour as its curves and the polygon they trace, in the
+    // direction they run. A closed chain only passes through plain
+    // junctions, so it is a whole subpath.
+    let contours: Vec<_> = traversal(curves)
+        .into_iter()
+        .filter(|chain| chain.closed)
+        .filter(|chain| {
+            chain
+                .curves
+                .iter()
+                .all(|(index, _)| indices.contains(index))
+        })
+        .map(|chain| {
+            let run: Vec<Curve> = chain
+                .curves
+                .iter()
+                .map(|&(index, reversed)| curves[index].clone().reversed_if(reversed))
+                .collect();
+            let indices: Vec<usize> = chain.curves.iter().map(|&(index, _)| index).collect();
+
+            (indices, flatten_path(&run))
+        })
+        .collect();
+
+    let mut reversed = 0;
+
+    for (position, (indices, polygon)) in contours.iter().enumerate() {
+        let enclosing = contours
+            .iter()
+            .enumerate()
+            .filter(|&(other, (_, other_polygon))| {
+                other != position && outline::contains(other_polygon, polygon[0])
+            })
+            .count();
+
+        // With y pointing down, a positive area runs clockwise on screen.
+        let clockwise = outline::signed_area(polygon) > 0.0;
+
+        if clockwise != (enclosing % 2 == 1) {
+            for &index in indices {
+                curves[index] = curves[index].clone().reversed();
+            }
+
+            reversed += 1;
+        }
+    }
+
+    reversed
+}
+
+/// Identifies a position exactly, with both zeros the same.
+fn key(point: Point) -> (u64, u64) {
+    ((point.x + 0.0).to_bits(), (point.y + 0.0).to_bits())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines through `corners` in turn and back to the first.
+    fn polygon(corners: &[(f64, f64)]) -> Vec<Curve> {
+        let point = |(x, y)| Point::new(x, y);
+
+        corners
+            .iter()
+            .zip(corners.iter().cycle().skip(1))
+            .map(|(&from, &to)| Curve::line(point(from), point(to)))
+            .collect()
+    }
+
+    /// Twice the signed area each closed path of `curves` encloses, positive
+    /// when it runs clockwise on screen.
+    fn windings(curves: &[Curve]) -> Vec<f64> {
+        traversal(curves)
+            .iter()
+            .filter(|chain| chain.closed)
+            .map(|chain| {
+                let run: Vec<Curve> = chain
+                    .curves
+                    .iter()
+                    .map(|&(index, reversed)| curves[index].clone().reversed_if(reversed))
+                    .collect();
+
+                outline::signed_area(&flatten_path(&run))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clockwise_squares_are_reversed() {
+        let mut curves = polygon(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let all: Vec<usize> = (0..curves.len()).collect();
+
+        assert!(windings(&curves)[0] > 0.0);
+        assert_eq!(normalize_winding(&mut curves, &all), 1);
+        assert!(windings(&curves)[0] < 0.0);
+
+        // Once counterclockwise, it stays as it is.
+        let normalized = curves.clone();
+        assert_eq!(normalize_winding(&mut curves, &all), 0);
+        assert!(curves == normalized);
+    }
+
+    #[test]
+    fn only_the_given_curves_are_normalized() {
+        let mut curves = polygon(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let before = curves.clone();
+
+        assert_eq!(normalize_winding(&mut curves, &[0, 1, 2]), 0);
+        assert!(curves == before);
+    }
+
+    #[test]
+    fn traversal_follows_paths_from_their_loose_ends() {
+        let (p, q, r, s) = (
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(20.0, 5.0),
+            Point::new(30.0, 0.0),
+        );
+        let curves = [Curve::line(q, r), Curve::line(p, q), Curve::line(r, s)];
+        let chains = traversal(&curves);
+
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].curves, [(1, false), (0, false), (2, false)]);
+        assert!(!chains[0].closed);
+    }
+
+    #[test]
+    fn traversal_runs_backwards_through_reversed_curves() {
+        let (p, q, r, s) = (
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(20.0, 5.0),
+            Point::new(30.0, 0.0),
+        );
+        let curves = [Curve::line(q, r), Curve::line(q, p), Curve::line(r, s)];
+        let chains = traversal(&curves);
+
+        assert_eq!(chains.len(), 1);
+        assert_eq!(chains[0].curves, [(1, true), (0, false), (2, false)]);
+    }
+
+    #[test]
+    fn traversal_closes_loops_and_orders_subpaths() {
+        let mut curves = vec![Curve::line(Point::new(50.0, 50.0), Point::new(60.0, 50.0))];
+        curves.extend(polygon(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (0.0, 10.0),
+        ]));
+        let chains = traversal(&curves);
+
+        assert_eq!(chains.len(), 2);
+        assert_eq!(chains[0].curves, [(0, false)]);
+        assert!(!chains[0].closed);
+        assert_eq!(
+            chains[1].curves,
+            [(1, false), (2, false), (3, false), (4, false)]
+        );
+        assert!(chains[1].closed);
+    }
+
+    #[test]
+    fn holes_wind_against_their_outlines() {
+        // Both clockwise, one inside the other.
+        let mut curves = polygon(&[(0.0, 0.0), (30.0, 0.0), (30.0, 30.0), (0.0, 30.0)]);
+        curves.extend(polygon(&[
+            (10.0, 10.0),
+            (20.0, 10.0),
+            (20.0, 20.0),
+            (10.0, 20.0),
+        ]));
+        let all: Vec<usize> = (0..curves.len()).collect();
+
+        assert_eq!(normalize_winding(&mut curves, &all), 1);
+
+        let windings = windings(&curves);
+        assert!(windings[0] < 0.0);
+        assert!(windings[1] > 0.0);
+    }
+
+    #[test]
+    fn branching_contours_keep_their_winding() {
+        // A tail off one corner leaves no closed chain to orient.
+        let mut curves = polygon(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        curves.push(Curve::line(Point::new(10.0, 10.0), Point::new(20.0, 20.0)));
+        let before = curves.clone();
+        let all: Vec<usize> = (0..curves.len()).collect();
+
+        assert_eq!(normalize_winding(&mut curves, &all), 0);
+        assert!(curves == before);
+    }
+}